@@ -1,7 +1,10 @@
 // Dosya: src/app.rs
-use crate::config::AppConfig;
+use crate::config::{AppConfig, RecordingStoreKind};
 use crate::grpc::service::MyMediaService;
 use crate::metrics::start_metrics_server;
+use crate::rtp::encryption::Keyring;
+use crate::rtp::spool::{RecordingSpool, RECORDING_SPOOL_DIR};
+use crate::rtp::store::{LocalStore, MemoryStore, RecordingStore, S3Store};
 use crate::state::{AppState, PortManager};
 use crate::telemetry::SutsFormatter;
 use crate::tls::load_server_tls_config;
@@ -83,6 +86,10 @@ impl App {
                     .await;
             });
 
+            if let Some(spool) = app_state.recording_spool.clone() {
+                tokio::spawn(spool.run_retry_worker(app_state.clone()));
+            }
+
             let tls_config = load_server_tls_config(&app_config)
                 .await
                 .context("TLS konfigürasyonu yüklenemedi")?;
@@ -122,7 +129,63 @@ impl App {
         let port_manager =
             PortManager::new(config.rtp_port_min, config.rtp_port_max, config.clone());
 
-        let app_state = AppState::new(port_manager, s3_client, rabbit_channel);
+        let recording_keyring = match &config.recording_keyring_path {
+            Some(path) => {
+                let keyring = Keyring::load(path)?;
+                info!(event = "RECORDING_KEYRING_LOADED", tenants = ?keyring, "🔐 Kayıt şifreleme anahtarlığı yüklendi.");
+                Some(Arc::new(keyring))
+            }
+            None => None,
+        };
+
+        let recording_store: Arc<dyn RecordingStore> = match (config.recording_store, &s3_client) {
+            (RecordingStoreKind::S3, Some(client)) => {
+                let s3_config = config
+                    .s3_config
+                    .as_ref()
+                    .context("S3 deposu için bucket ayarları eksik")?;
+                Arc::new(S3Store::new(
+                    client.clone(),
+                    s3_config.bucket_name.clone(),
+                    s3_config.sse.clone(),
+                ))
+            }
+            (RecordingStoreKind::S3, None) => {
+                anyhow::bail!("RECORDING_STORE=s3 için S3 istemcisi oluşturulamadı")
+            }
+            (RecordingStoreKind::Local, _) => {
+                Arc::new(LocalStore::new(&config.media_recording_path))
+            }
+            (RecordingStoreKind::Memory, _) => {
+                warn!(
+                    event = "RECORDING_STORE_MEMORY",
+                    "⚠️ Kayıtlar bellekte tutuluyor; servis kapanınca kaybolur (yalnızca test/CI)."
+                );
+                Arc::new(MemoryStore::new())
+            }
+        };
+        info!(event = "RECORDING_STORE_READY", store = ?config.recording_store, "🗄️ Kayıt deposu hazır.");
+
+        // Spool yalnızca S3 hedefleri için anlamlıdır; yerel kayıtlar zaten diske yazılır.
+        let recording_spool = if s3_client.is_some() && config.recording_spool_max_bytes > 0 {
+            let dir = std::path::Path::new(&config.media_recording_path).join(RECORDING_SPOOL_DIR);
+            info!(event = "RECORDING_SPOOL_READY", dir = %dir.display(), max_bytes = config.recording_spool_max_bytes, "📦 Kayıt spool'u hazır.");
+            Some(Arc::new(RecordingSpool::new(
+                dir,
+                config.recording_spool_max_bytes,
+            )))
+        } else {
+            None
+        };
+
+        let app_state = AppState::new(
+            port_manager,
+            s3_client,
+            recording_store,
+            rabbit_channel,
+            recording_keyring,
+            recording_spool,
+        );
 
         Ok(app_state)
     }
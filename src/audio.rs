@@ -1,39 +1,1221 @@
 // Dosya: src/audio.rs
-use anyhow::{Context, Result};
+use crate::config::S3Sse;
+use crate::rtp::resampler::resample;
+use anyhow::{anyhow, bail, Context, Result};
+use audiopus::coder::Decoder as OpusDecoder;
+use audiopus::packet::Packet;
+use audiopus::{Channels, MutSignals, SampleRate};
+use aws_sdk_s3::Client as S3Client;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
+use tracing::{debug, warn};
 
-pub type AudioCache = Arc<Mutex<HashMap<String, Arc<Vec<i16>>>>>;
+/// Önbellekteki ses. Uzak kaynaklarda `etag` ile koşullu istek yapılır; yerel dosyalar
+/// süreç boyunca tekrar okunmaz.
+#[derive(Debug, Clone)]
+pub struct CachedAudio {
+    pub samples: Arc<Vec<i16>>,
+    pub etag: Option<String>,
+    pub validated_at: Instant,
+    /// Önbellek sınırı aşılınca en eski kullanılan atılır.
+    pub used_at: Instant,
+}
+
+impl CachedAudio {
+    fn new(samples: Arc<Vec<i16>>, etag: Option<String>) -> Self {
+        let now = Instant::now();
+        Self {
+            samples,
+            etag,
+            validated_at: now,
+            used_at: now,
+        }
+    }
+
+    fn size_bytes(&self) -> usize {
+        self.samples.len() * std::mem::size_of::<i16>()
+    }
+}
+
+pub type AudioCache = Arc<Mutex<HashMap<String, CachedAudio>>>;
+
+/// Sesi önbelleğe ekler; toplam boyut `max_bytes`'ı aşarsa en uzun süredir çalınmayanlar atılır.
+/// Tek başına sınırı aşan ses önbelleğe alınmaz.
+fn cache_insert(
+    cache: &mut HashMap<String, CachedAudio>,
+    key: String,
+    audio: CachedAudio,
+    max_bytes: usize,
+) {
+    cache.remove(&key);
+    if audio.size_bytes() > max_bytes {
+        return;
+    }
+    let mut total: usize = cache.values().map(CachedAudio::size_bytes).sum();
+    while total + audio.size_bytes() > max_bytes {
+        let Some(oldest) = cache
+            .iter()
+            .min_by_key(|(_, cached)| cached.used_at)
+            .map(|(key, _)| key.clone())
+        else {
+            break;
+        };
+        if let Some(evicted) = cache.remove(&oldest) {
+            total -= evicted.size_bytes();
+            debug!(event = "AUDIO_CACHE_EVICTED", uri = %display_uri(&oldest), "Anons önbellekten atıldı (boyut sınırı).");
+        }
+    }
+    cache.insert(key, audio);
+}
+
+/// `s3://` ve `http(s)://` anonslarının indirme sınırları.
+#[derive(Debug, Clone, Copy)]
+pub struct RemoteAudioLimits {
+    pub max_bytes: usize,
+    pub timeout: Duration,
+    /// Bu süre dolmadan önbellekteki kopya kaynağa sorulmadan kullanılır.
+    pub revalidate_after: Duration,
+    /// Tüm önbelleğin üst sınırı.
+    pub cache_max_bytes: usize,
+}
 
 pub async fn load_or_get_from_cache(
     cache: &AudioCache,
     audio_path: &Path,
+    cache_max_bytes: usize,
 ) -> Result<Arc<Vec<i16>>> {
     let path_key = audio_path.to_string_lossy().to_string();
     let mut cache_guard = cache.lock().await;
 
-    if let Some(cached_samples) = cache_guard.get(&path_key) {
-        return Ok(cached_samples.clone());
+    if let Some(cached) = cache_guard.get_mut(&path_key) {
+        cached.used_at = Instant::now();
+        return Ok(cached.samples.clone());
     }
 
-    let _path_str = audio_path.to_str().context("Geçersiz dosya yolu")?;
+    let path_str = audio_path.to_str().context("Geçersiz dosya yolu")?;
+    let hint = format_hint(path_str);
     let path_owned = audio_path.to_path_buf();
 
     let new_samples: Vec<i16> = tokio::task::spawn_blocking(move || -> Result<Vec<i16>> {
-        let reader = hound::WavReader::open(path_owned).context("WAV açma hatası")?;
-        let samples: Vec<i16> = reader
-            .into_samples::<i16>()
-            .map(|s| s.unwrap_or(0))
-            .collect();
-        Ok(samples)
+        let bytes = std::fs::read(&path_owned)
+            .with_context(|| format!("Ses dosyası okunamadı: {}", path_owned.display()))?;
+        decode_audio(bytes, hint)
+            .with_context(|| format!("Ses dosyası çözülemedi: {}", path_owned.display()))
     })
     .await
     .context("Thread join hatası")??;
 
     let arc_samples = Arc::new(new_samples);
-    cache_guard.insert(path_key, arc_samples.clone());
+    cache_insert(
+        &mut cache_guard,
+        path_key,
+        CachedAudio::new(arc_samples.clone(), None),
+        cache_max_bytes,
+    );
 
     Ok(arc_samples)
 }
+
+/// `data:` URI'si çözülemedi veya sınırı aşıyor; gRPC katmanı bunu `InvalidUri` olarak döner.
+#[derive(Debug)]
+pub struct InvalidDataUri(pub String);
+
+impl std::fmt::Display for InvalidDataUri {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Geçersiz data: URI: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidDataUri {}
+
+/// Uzak anons kaynağı izin listesinde değil veya iç ağa işaret ediyor; gRPC katmanı bunu
+/// `permission_denied` olarak döner.
+#[derive(Debug)]
+pub struct AudioSourceNotAllowed(pub String);
+
+impl std::fmt::Display for AudioSourceNotAllowed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Anons kaynağına izin verilmiyor: {}", self.0)
+    }
+}
+
+impl std::error::Error for AudioSourceNotAllowed {}
+
+/// `s3://` anonsu `s3_allow`'daki bir `bucket` veya `bucket/önek` altında, `http(s)://` anonsu
+/// `http_hosts`'taki bir sunucuda olmalıdır. IP adresiyle verilen sunucu listede olsa bile iç ağ
+/// adresiyse reddedilir; adların çözümü `PublicOnlyResolver`'da denetlenir.
+pub fn check_remote_source(uri: &str, s3_allow: &[String], http_hosts: &[String]) -> Result<()> {
+    let denied =
+        |reason: &str| anyhow::Error::new(AudioSourceNotAllowed(format!("{} ({})", uri, reason)));
+    if let Some(rest) = uri.strip_prefix("s3://") {
+        let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+        if key.split('/').any(|segment| segment == "..") {
+            return Err(denied("anahtarda '..'"));
+        }
+        let allowed = s3_allow.iter().any(|entry| match entry.split_once('/') {
+            Some((allowed_bucket, prefix)) => bucket == allowed_bucket && key.starts_with(prefix),
+            None => bucket == entry,
+        });
+        return match allowed {
+            true => Ok(()),
+            false => Err(denied("bucket/önek AUDIO_REMOTE_S3_ALLOW listesinde değil")),
+        };
+    }
+
+    let url = url::Url::parse(uri).map_err(|_| denied("geçersiz URL"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(denied("desteklenmeyen şema"));
+    }
+    let host = match url.host() {
+        Some(url::Host::Domain(domain)) => domain.to_string(),
+        Some(url::Host::Ipv4(ip)) if is_public_ip(IpAddr::V4(ip)) => ip.to_string(),
+        Some(url::Host::Ipv6(ip)) if is_public_ip(IpAddr::V6(ip)) => ip.to_string(),
+        Some(_) => return Err(denied("iç ağ adresi")),
+        None => return Err(denied("sunucu yok")),
+    };
+    if !http_hosts.contains(&host) {
+        return Err(denied("sunucu AUDIO_REMOTE_HTTP_HOSTS listesinde değil"));
+    }
+    Ok(())
+}
+
+/// Genel internette yönlendirilebilir adres mi: özel, loopback, link-local (bulut metadata
+/// servisi dahil), CGNAT, çoklu yayın ve ayrılmış bloklar değildir.
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                || a == 0
+                || a >= 240
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (b == 18 || b == 19)))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(v4) = ip.to_ipv4_mapped() {
+                return is_public_ip(IpAddr::V4(v4));
+            }
+            let segments = ip.segments();
+            // NAT64 (64:ff9b::/96) gömülü IPv4 adresine gider.
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [a, b] = segments[6].to_be_bytes();
+                let [c, d] = segments[7].to_be_bytes();
+                return is_public_ip(IpAddr::V4([a, b, c, d].into()));
+            }
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80
+                || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+        }
+    }
+}
+
+/// Paylaşılan HTTP istemcisinin ad çözücüsü: iç ağ adreslerini eler. İzinli bir ad sonradan
+/// özel bir IP'ye çözülse (DNS rebinding) bile istek oraya gitmez.
+#[derive(Debug, Default)]
+pub struct PublicOnlyResolver;
+
+impl reqwest::dns::Resolve for PublicOnlyResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|addr| is_public_ip(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(Box::new(AudioSourceNotAllowed(format!(
+                    "{} yalnızca iç ağ adreslerine çözülüyor",
+                    host
+                )))
+                    as Box<dyn std::error::Error + Send + Sync>);
+            }
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// Loglarda ve olaylarda kullanılacak URI; satır içi ses yerine yalnızca türü ve boyutu yazılır.
+pub fn display_uri(uri: &str) -> Cow<'_, str> {
+    match uri.strip_prefix("data:") {
+        Some(rest) => {
+            let (meta, payload) = rest.split_once(',').unwrap_or((rest, ""));
+            Cow::Owned(format!("data:{},<{} karakter>", meta, payload.len()))
+        }
+        None => Cow::Borrowed(uri),
+    }
+}
+
+/// `data:audio/wav;base64,...` (ya da `audio/mpeg`, `audio/ogg`, `audio/basic`, `audio/PCMA`)
+/// veya `data:audio/L16;rate=16000[;channels=2];base64,...` içeriğini 8 kHz mono örneklere
+/// çözer. L16 RFC 2586'ya göre big-endian'dır. Satır içi ses önbelleğe alınmaz; `max_bytes` base64'ten
+/// çözülmüş kap (WAV, MP3...) içindir ve base64 çözülmeden önce denetlenir. Kabın açıldığı ses
+/// süresi `MAX_PROMPT_DURATION` ile ayrıca sınırlıdır.
+pub fn decode_data_uri(uri: &str, max_bytes: usize) -> Result<Vec<i16>> {
+    let invalid = |reason: String| anyhow::Error::new(InvalidDataUri(reason));
+    let rest = uri
+        .strip_prefix("data:")
+        .ok_or_else(|| invalid("data: ile başlamıyor".into()))?;
+    let (meta, payload) = rest
+        .split_once(',')
+        .ok_or_else(|| invalid("',' ayracı yok".into()))?;
+    let mut fields = meta.split(';');
+    let mime = fields.next().unwrap_or_default().to_ascii_lowercase();
+    let mut params = HashMap::new();
+    let mut is_base64 = false;
+    for field in fields {
+        match field.split_once('=') {
+            Some((key, value)) => {
+                params.insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+            }
+            None if field.eq_ignore_ascii_case("base64") => is_base64 = true,
+            None => return Err(invalid(format!("bilinmeyen parametre: {}", field))),
+        }
+    }
+    if !is_base64 {
+        return Err(invalid("yalnızca base64 desteklenir".into()));
+    }
+    if payload.len() > max_bytes.div_ceil(3) * 4 {
+        return Err(invalid(format!("ses {} bayt sınırını aşıyor", max_bytes)));
+    }
+    let bytes = BASE64
+        .decode(payload)
+        .map_err(|e| invalid(format!("base64 çözülemedi: {}", e)))?;
+    if bytes.len() > max_bytes {
+        return Err(invalid(format!("ses {} bayt sınırını aşıyor", max_bytes)));
+    }
+
+    if mime == "audio/l16" {
+        let number = |key: &str, default: Option<u32>| {
+            params
+                .get(key)
+                .map(|v| v.parse::<u32>().ok())
+                .unwrap_or(default)
+                .ok_or_else(|| invalid(format!("L16 için geçerli '{}' gerekli", key)))
+        };
+        let rate = number("rate", None)?;
+        let channels = number("channels", Some(1))? as usize;
+        if channels == 0 || bytes.len() % (2 * channels) != 0 {
+            return Err(invalid("L16 verisi tam örnek içermiyor".into()));
+        }
+        check_duration(bytes.len() / 2, rate, channels).map_err(|e| invalid(e.to_string()))?;
+        let samples = bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_be_bytes([b[0], b[1]]) as f32 / 32768.0)
+            .collect();
+        return to_prompt_rate(samples, rate, channels).map_err(|e| invalid(e.to_string()));
+    }
+    let format = AudioFormat::from_mime(&mime)
+        .ok_or_else(|| invalid(format!("desteklenmeyen ses türü: {}", mime)))?;
+    decode_audio(bytes, Some(format)).map_err(|e| invalid(format!("{:#}", e)))
+}
+
+/// Anonsların oturuma verildiği örnekleme hızı; RTP tarafı 8 kHz mono PCM bekler.
+pub const PROMPT_SAMPLE_RATE: u32 = 8000;
+
+/// Tek bir anonsun çözülebileceği en uzun süre. Birkaç KB'lık sıkıştırılmış bir dosya saatlerce
+/// ses üretebilir; sınır çözme döngülerinde denetlenir, tamamı belleğe açılmadan hata döner.
+pub const MAX_PROMPT_DURATION: Duration = Duration::from_secs(600);
+
+/// Kanal sırasıyla iç içe geçmiş `samples` örneğin `MAX_PROMPT_DURATION`'ı aşıp aşmadığını denetler.
+fn check_duration(samples: usize, rate: u32, channels: usize) -> Result<()> {
+    // Hız burada henüz doğrulanmadı; başlıktaki uç değerler sınırı genişletmesin.
+    let rate = rate.clamp(8000, 48000) as u64;
+    let limit = MAX_PROMPT_DURATION.as_secs() * rate * channels.max(1) as u64;
+    if samples as u64 > limit {
+        bail!(
+            "Ses {} saniye sınırını aşıyor",
+            MAX_PROMPT_DURATION.as_secs()
+        );
+    }
+    Ok(())
+}
+
+/// Anons kaynağının biçimi. Başlığı olan biçimler içerikten tanınır; başlıksız G.711
+/// dosyaları ancak uzantıdan (`.ulaw`, `.alaw`) veya MIME türünden anlaşılır.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioFormat {
+    Wav,
+    Mulaw,
+    Alaw,
+    Mp3,
+    /// Vorbis veya Opus.
+    Ogg,
+}
+
+impl AudioFormat {
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "wav" | "wave" => Some(Self::Wav),
+            "ulaw" | "mulaw" | "ul" | "pcmu" => Some(Self::Mulaw),
+            "alaw" | "al" | "pcma" => Some(Self::Alaw),
+            "mp3" => Some(Self::Mp3),
+            "ogg" | "oga" | "opus" => Some(Self::Ogg),
+            _ => None,
+        }
+    }
+
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        match mime.to_ascii_lowercase().as_str() {
+            "audio/wav" | "audio/wave" | "audio/x-wav" => Some(Self::Wav),
+            "audio/basic" | "audio/pcmu" => Some(Self::Mulaw),
+            "audio/pcma" => Some(Self::Alaw),
+            "audio/mpeg" | "audio/mp3" => Some(Self::Mp3),
+            "audio/ogg" | "audio/opus" => Some(Self::Ogg),
+            _ => None,
+        }
+    }
+
+    fn sniff(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"RIFF") && bytes.get(8..12) == Some(b"WAVE") {
+            Some(Self::Wav)
+        } else if bytes.starts_with(b"OggS") {
+            Some(Self::Ogg)
+        } else if bytes.starts_with(b"ID3")
+            || (bytes.len() >= 2 && bytes[0] == 0xFF && bytes[1] & 0xE0 == 0xE0)
+        {
+            Some(Self::Mp3)
+        } else {
+            None
+        }
+    }
+}
+
+/// Dosya yolundan veya URI'den (sorgu dizesi hariç) uzantıya göre biçim ipucu.
+fn format_hint(path: &str) -> Option<AudioFormat> {
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(AudioFormat::from_extension)
+}
+
+/// Anons içeriğini biçimine bakmaksızın `PROMPT_SAMPLE_RATE` hızında mono 16-bit örneklere
+/// çözer: kanallar ortalanarak birleştirilir, hız `Resampler` ile dönüştürülür. Bozuk veya
+/// kesik veri sessizlikle doldurulmaz, hata döner.
+pub fn decode_audio(bytes: Vec<u8>, hint: Option<AudioFormat>) -> Result<Vec<i16>> {
+    // G.711'in başlığı yok; 0xFF ile başlayan μ-law sessizliği MP3 sanılmasın.
+    let format = match hint {
+        Some(format @ (AudioFormat::Mulaw | AudioFormat::Alaw)) => format,
+        _ => AudioFormat::sniff(&bytes)
+            .or(hint)
+            .ok_or_else(|| anyhow!("Ses biçimi tanınamadı"))?,
+    };
+    let (samples, rate, channels) = match format {
+        AudioFormat::Wav => decode_wav(bytes)?,
+        AudioFormat::Mulaw | AudioFormat::Alaw => {
+            check_duration(bytes.len(), 8000, 1)?;
+            let expand = if format == AudioFormat::Mulaw {
+                ulaw_to_linear
+            } else {
+                alaw_to_linear
+            };
+            (g711_to_f32(&bytes, expand), 8000, 1)
+        }
+        AudioFormat::Ogg if is_ogg_opus(&bytes) => decode_ogg_opus(bytes)?,
+        AudioFormat::Mp3 | AudioFormat::Ogg => decode_compressed(bytes, format)?,
+    };
+    to_prompt_rate(samples, rate, channels)
+}
+
+/// Kanal sırasıyla iç içe geçmiş [-1, 1] örnekleri mono `PROMPT_SAMPLE_RATE`'e indirir.
+fn to_prompt_rate(samples: Vec<f32>, rate: u32, channels: usize) -> Result<Vec<i16>> {
+    if !(8000..=48000).contains(&rate) {
+        bail!("Desteklenmeyen örnekleme hızı: {}", rate);
+    }
+    if channels == 0 || !samples.len().is_multiple_of(channels) {
+        bail!("Kanal sayısı ({}) örneklerle uyuşmuyor", channels);
+    }
+    if samples.is_empty() {
+        bail!("Ses boş");
+    }
+    let mono: Vec<i16> = samples
+        .chunks_exact(channels)
+        .map(|frame| {
+            let mixed = frame.iter().sum::<f32>() / channels as f32;
+            (mixed * 32768.0).round().clamp(-32768.0, 32767.0) as i16
+        })
+        .collect();
+    Ok(resample(&mono, rate, PROMPT_SAMPLE_RATE))
+}
+
+/// 8/16/24/32-bit tamsayı ve 32-bit float PCM WAV.
+fn decode_wav(bytes: Vec<u8>) -> Result<(Vec<f32>, u32, usize)> {
+    let reader =
+        hound::WavReader::new(std::io::Cursor::new(bytes)).context("WAV başlığı okunamadı")?;
+    let spec = reader.spec();
+    check_duration(
+        reader.len() as usize,
+        spec.sample_rate,
+        spec.channels as usize,
+    )?;
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Int => {
+            let scale = (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<Vec<f32>, _>>()
+        }
+        hound::SampleFormat::Float => reader.into_samples::<f32>().collect(),
+    }
+    .context("WAV verisi bozuk")?;
+    Ok((samples, spec.sample_rate, spec.channels as usize))
+}
+
+fn g711_to_f32(bytes: &[u8], expand: fn(u8) -> i16) -> Vec<f32> {
+    bytes.iter().map(|&b| expand(b) as f32 / 32768.0).collect()
+}
+
+/// ITU-T G.711 μ-law açılımı.
+fn ulaw_to_linear(byte: u8) -> i16 {
+    let u = !byte;
+    let magnitude = ((((u & 0x0F) as i16) << 3) + 0x84) << ((u & 0x70) >> 4);
+    if u & 0x80 != 0 {
+        0x84 - magnitude
+    } else {
+        magnitude - 0x84
+    }
+}
+
+/// ITU-T G.711 A-law açılımı.
+fn alaw_to_linear(byte: u8) -> i16 {
+    let a = byte ^ 0x55;
+    let segment = (a & 0x70) >> 4;
+    let mut magnitude = ((a & 0x0F) as i16) << 4;
+    match segment {
+        0 => magnitude += 8,
+        1 => magnitude += 0x108,
+        _ => magnitude = (magnitude + 0x108) << (segment - 1),
+    }
+    if a & 0x80 != 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+/// symphonia Ogg içinde yalnızca Vorbis çözer; Opus akışları ilk paketteki `OpusHead`
+/// imzasından tanınıp libopus'a yönlendirilir.
+fn is_ogg_opus(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(128)]
+        .windows(8)
+        .any(|w| w == b"OpusHead")
+}
+
+pub(crate) fn decode_ogg_opus(bytes: Vec<u8>) -> Result<(Vec<f32>, u32, usize)> {
+    let mut reader = ogg::PacketReader::new(std::io::Cursor::new(bytes));
+    let head = reader
+        .read_packet()
+        .context("Ogg verisi bozuk")?
+        .context("Ogg akışı boş")?;
+    // RFC 7845: OpusHead | sürüm | kanal sayısı | pre-skip (LE)
+    if head.data.len() < 19 || !head.data.starts_with(b"OpusHead") {
+        bail!("OpusHead başlığı geçersiz");
+    }
+    let channels = head.data[9] as usize;
+    let pre_skip = u16::from_le_bytes([head.data[10], head.data[11]]) as usize;
+    let opus_channels = match channels {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        n => bail!("Desteklenmeyen Opus kanal sayısı: {}", n),
+    };
+    let mut decoder = OpusDecoder::new(SampleRate::Hz48000, opus_channels)
+        .map_err(|e| anyhow!("Opus decoder oluşturulamadı: {}", e))?;
+
+    // İkinci paket OpusTags; ses içermez.
+    reader.read_packet().context("Ogg verisi bozuk")?;
+    // 120ms, 48 kHz'de bir Opus paketinin en uzun süresidir.
+    let mut pcm = vec![0i16; 5760 * channels];
+    let mut samples = Vec::new();
+    let mut end_granule = None;
+    while let Some(packet) = reader.read_packet().context("Ogg verisi bozuk")? {
+        if packet.last_in_stream() {
+            end_granule = Some(packet.absgp_page());
+        }
+        let input = Packet::try_from(packet.data.as_slice())
+            .map_err(|e| anyhow!("Opus paketi geçersiz: {}", e))?;
+        let output = MutSignals::try_from(pcm.as_mut_slice())
+            .map_err(|e| anyhow!("Opus tamponu geçersiz: {}", e))?;
+        let frames = decoder
+            .decode(Some(input), output, false)
+            .map_err(|e| anyhow!("Opus paketi çözülemedi: {}", e))?;
+        samples.extend(pcm[..frames * channels].iter().map(|&s| s as f32 / 32768.0));
+        check_duration(samples.len(), 48000, channels)?;
+    }
+    // Son sayfanın granülü pre-skip dahil çalınacak örnek sayısıdır; kodlayıcının son
+    // paketi tamamlamak için eklediği dolgu kırpılır (RFC 7845 §4.4).
+    if let Some(end) = end_granule {
+        samples.truncate((end as usize).saturating_mul(channels));
+    }
+    samples.drain(..(pre_skip * channels).min(samples.len()));
+    Ok((samples, 48000, channels))
+}
+
+/// MP3 ve Ogg/Vorbis (symphonia).
+fn decode_compressed(bytes: Vec<u8>, format: AudioFormat) -> Result<(Vec<f32>, u32, usize)> {
+    use symphonia::core::audio::SampleBuffer;
+    use symphonia::core::codecs::DecoderOptions;
+    use symphonia::core::errors::Error as SymphoniaError;
+    use symphonia::core::formats::FormatOptions;
+    use symphonia::core::io::MediaSourceStream;
+    use symphonia::core::meta::MetadataOptions;
+    use symphonia::core::probe::Hint;
+
+    let mut hint = Hint::new();
+    hint.with_extension(if format == AudioFormat::Mp3 {
+        "mp3"
+    } else {
+        "ogg"
+    });
+    let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(bytes)), Default::default());
+    let mut reader = symphonia::default::get_probe()
+        .format(
+            &hint,
+            source,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .context("Ses kabı tanınamadı")?
+        .format;
+    let track = reader.default_track().context("Ses izi bulunamadı")?;
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .context("Desteklenmeyen kodek")?;
+
+    let mut samples = Vec::new();
+    let mut rate = track.codec_params.sample_rate.unwrap_or_default();
+    let mut channels = track
+        .codec_params
+        .channels
+        .map(|c| c.count())
+        .unwrap_or_default();
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break
+            }
+            Err(e) => return Err(e).context("Ses kabı bozuk"),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = decoder.decode(&packet).context("Ses verisi bozuk")?;
+        let spec = *decoded.spec();
+        if !samples.is_empty() && (spec.rate != rate || spec.channels.count() != channels) {
+            bail!("Akış ortasında ses biçimi değişti");
+        }
+        rate = spec.rate;
+        channels = spec.channels.count();
+        let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        buffer.copy_interleaved_ref(decoded);
+        samples.extend_from_slice(buffer.samples());
+        check_duration(samples.len(), rate, channels)?;
+    }
+    Ok((samples, rate, channels))
+}
+
+/// Uzak kaynaktan dönen yanıt.
+enum Fetched {
+    /// Koşullu istek: önbellekteki kopya hâlâ geçerli (304).
+    NotModified,
+    Body {
+        bytes: Vec<u8>,
+        etag: Option<String>,
+    },
+}
+
+/// `s3://bucket/key` (servisin S3 istemcisiyle) veya `http(s)://` anonsunu indirip önbelleğe
+/// alır. Önbellekteki kopya `revalidate_after` süresinden eskiyse ETag ile koşullu istek
+/// yapılır; kaynak erişilemezse eski kopya kullanılmaya devam eder.
+pub async fn load_remote_or_get_from_cache(
+    cache: &AudioCache,
+    uri: &str,
+    s3: Option<(&S3Client, &S3Sse)>,
+    http: &reqwest::Client,
+    limits: RemoteAudioLimits,
+) -> Result<Arc<Vec<i16>>> {
+    // Ağ isteği sürerken önbellek kilidi tutulmaz; diğer anonslar beklemez.
+    let cached = cache.lock().await.get_mut(uri).map(|cached| {
+        cached.used_at = Instant::now();
+        cached.clone()
+    });
+    if let Some(cached) = &cached {
+        if cached.validated_at.elapsed() < limits.revalidate_after {
+            return Ok(cached.samples.clone());
+        }
+    }
+    let etag = cached.as_ref().and_then(|c| c.etag.as_deref());
+
+    let fetched = match uri.strip_prefix("s3://") {
+        Some(rest) => {
+            let (bucket, key) = rest
+                .split_once('/')
+                .filter(|(bucket, key)| !bucket.is_empty() && !key.is_empty())
+                .ok_or_else(|| anyhow!("Geçersiz S3 URI: {}", uri))?;
+            let (client, sse) = s3.ok_or_else(|| anyhow!("S3 yapılandırılmamış: {}", uri))?;
+            tokio::time::timeout(
+                limits.timeout,
+                fetch_s3(client, sse, bucket, key, etag, limits.max_bytes),
+            )
+            .await
+            .map_err(|_| anyhow!("S3 indirmesi zaman aşımına uğradı: {}", uri))?
+        }
+        None => fetch_http(http, uri, etag, limits).await,
+    };
+
+    let (bytes, etag) = match (fetched, cached) {
+        (Ok(Fetched::Body { bytes, etag }), _) => (bytes, etag),
+        (Ok(Fetched::NotModified), Some(mut cached)) => {
+            debug!(event = "AUDIO_REMOTE_NOT_MODIFIED", uri = %uri, "Uzak anons değişmemiş, önbellekteki kopya kullanılıyor.");
+            cached.validated_at = Instant::now();
+            let samples = cached.samples.clone();
+            cache_insert(
+                &mut *cache.lock().await,
+                uri.to_string(),
+                cached,
+                limits.cache_max_bytes,
+            );
+            return Ok(samples);
+        }
+        (Ok(Fetched::NotModified), None) => bail!("Koşulsuz istek 304 döndü: {}", uri),
+        (Err(e), Some(cached)) => {
+            warn!(event = "AUDIO_REMOTE_REVALIDATE_FAIL", uri = %uri, error = %e, "⚠️ Uzak anons doğrulanamadı, önbellekteki kopya kullanılıyor.");
+            return Ok(cached.samples.clone());
+        }
+        (Err(e), None) => return Err(e),
+    };
+
+    let hint = format_hint(uri);
+    let samples = tokio::task::spawn_blocking(move || decode_audio(bytes, hint))
+        .await
+        .context("Thread join hatası")?
+        .with_context(|| format!("Uzak anons çözülemedi: {}", uri))?;
+
+    let samples = Arc::new(samples);
+    cache_insert(
+        &mut *cache.lock().await,
+        uri.to_string(),
+        CachedAudio::new(samples.clone(), etag),
+        limits.cache_max_bytes,
+    );
+    debug!(event = "AUDIO_REMOTE_LOADED", uri = %uri, samples = samples.len(), "Uzak anons indirildi ve önbelleğe alındı.");
+    Ok(samples)
+}
+
+async fn fetch_s3(
+    client: &S3Client,
+    sse: &S3Sse,
+    bucket: &str,
+    key: &str,
+    etag: Option<&str>,
+    max_bytes: usize,
+) -> Result<Fetched> {
+    let result = client
+        .get_object()
+        .bucket(bucket)
+        .key(key)
+        .set_if_none_match(etag.map(str::to_string))
+        .set_sse_customer_algorithm(sse.customer_algorithm())
+        .set_sse_customer_key(sse.customer_key())
+        .set_sse_customer_key_md5(sse.customer_key_md5())
+        .send()
+        .await;
+    let output = match result {
+        Ok(output) => output,
+        Err(e) if e.raw_response().map(|r| r.status().as_u16()) == Some(304) => {
+            return Ok(Fetched::NotModified)
+        }
+        Err(e) => return Err(anyhow!("S3 anonsu alınamadı: {:?}", e)),
+    };
+    if output
+        .content_length()
+        .is_some_and(|len| len as u64 > max_bytes as u64)
+    {
+        bail!("Anons çok büyük (sınır {} bayt)", max_bytes);
+    }
+    let etag = output.e_tag().map(str::to_string);
+    let mut body = output.body;
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.try_next().await? {
+        if bytes.len() + chunk.len() > max_bytes {
+            bail!("Anons çok büyük (sınır {} bayt)", max_bytes);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Fetched::Body { bytes, etag })
+}
+
+async fn fetch_http(
+    client: &reqwest::Client,
+    url: &str,
+    etag: Option<&str>,
+    limits: RemoteAudioLimits,
+) -> Result<Fetched> {
+    let mut request = client.get(url).timeout(limits.timeout);
+    if let Some(etag) = etag {
+        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+    }
+    let mut response = request.send().await?;
+    if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+    if !response.status().is_success() {
+        bail!("Anons indirilemedi: HTTP {}", response.status());
+    }
+    if response
+        .content_length()
+        .is_some_and(|len| len > limits.max_bytes as u64)
+    {
+        bail!("Anons çok büyük (sınır {} bayt)", limits.max_bytes);
+    }
+    let etag = response
+        .headers()
+        .get(reqwest::header::ETAG)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    // Content-Length yalan söyleyebilir veya hiç olmayabilir; sınır akış sırasında da uygulanır.
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if bytes.len() + chunk.len() > limits.max_bytes {
+            bail!("Anons çok büyük (sınır {} bayt)", limits.max_bytes);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(Fetched::Body { bytes, etag })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn wav(samples: &[i16]) -> Vec<u8> {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut out = std::io::Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut out, spec).unwrap();
+        for s in samples {
+            writer.write_sample(*s).unwrap();
+        }
+        writer.finalize().unwrap();
+        out.into_inner()
+    }
+
+    /// Her bağlantıya bir yanıt veren HTTP sunucusu; `If-None-Match` eşleşirse 304 döner.
+    async fn serve(body: Vec<u8>) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/anons.wav", listener.local_addr().unwrap());
+        let hits = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                let mut request = vec![0u8; 4096];
+                let n = socket.read(&mut request).await.unwrap();
+                let request = String::from_utf8_lossy(&request[..n]).to_lowercase();
+                let head = if request.contains("if-none-match: \"v1\"") {
+                    "HTTP/1.1 304 Not Modified\r\netag: \"v1\"\r\ncontent-length: 0\r\n\r\n"
+                        .to_string()
+                } else {
+                    format!(
+                        "HTTP/1.1 200 OK\r\netag: \"v1\"\r\ncontent-length: {}\r\n\r\n",
+                        body.len()
+                    )
+                };
+                socket.write_all(head.as_bytes()).await.unwrap();
+                if !head.starts_with("HTTP/1.1 304") {
+                    socket.write_all(&body).await.unwrap();
+                }
+            }
+        });
+        (url, hits)
+    }
+
+    #[tokio::test]
+    async fn test_prompts_are_downmixed_resampled_and_corrupt_files_fail() {
+        // 16 kHz stereo 24-bit ve 44.1 kHz mono float -> 8 kHz mono.
+        let encode = |spec: hound::WavSpec, frames: usize| {
+            let mut out = std::io::Cursor::new(Vec::new());
+            let mut writer = hound::WavWriter::new(&mut out, spec).unwrap();
+            for _ in 0..frames * spec.channels as usize {
+                match spec.sample_format {
+                    hound::SampleFormat::Int => writer.write_sample(1 << 20).unwrap(),
+                    hound::SampleFormat::Float => writer.write_sample(0.125f32).unwrap(),
+                }
+            }
+            writer.finalize().unwrap();
+            out.into_inner()
+        };
+        let int24 = encode(
+            hound::WavSpec {
+                channels: 2,
+                sample_rate: 16000,
+                bits_per_sample: 24,
+                sample_format: hound::SampleFormat::Int,
+            },
+            3200,
+        );
+        let float = encode(
+            hound::WavSpec {
+                channels: 1,
+                sample_rate: 44100,
+                bits_per_sample: 32,
+                sample_format: hound::SampleFormat::Float,
+            },
+            8820,
+        );
+        for bytes in [int24.clone(), float] {
+            let decoded = decode_audio(bytes, None).unwrap();
+            assert_eq!(decoded.len(), 1600);
+            assert!(decoded[200..1400].iter().all(|s| (s - 4096).abs() < 50));
+        }
+
+        // Başlıksız G.711 dosyası uzantısından tanınır; 0xFF μ-law sessizliğidir.
+        let dir = std::env::temp_dir().join(format!("sentiric-audio-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cache = AudioCache::default();
+        let path = dir.join("silence.ulaw");
+        std::fs::write(&path, [0xFFu8; 800]).unwrap();
+        let samples = load_or_get_from_cache(&cache, &path, usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(samples.len(), 800);
+        assert!(samples.iter().all(|&s| s == 0));
+        assert_eq!(alaw_to_linear(0xD5), 8);
+        assert_eq!(ulaw_to_linear(0x00), -32124);
+
+        // Kesik WAV sessizlikle doldurulmaz.
+        let path = dir.join("truncated.wav");
+        std::fs::write(&path, &int24[..int24.len() - 1000]).unwrap();
+        assert!(load_or_get_from_cache(&cache, &path, usize::MAX)
+            .await
+            .is_err());
+        assert!(decode_audio(vec![1, 2, 3, 4], None).is_err());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_data_uri_decodes_wav_and_l16_and_rejects_bad_input() {
+        let samples: Vec<i16> = (0..1600).map(|i| (i % 100) as i16 * 100).collect();
+        let uri = format!("data:audio/wav;base64,{}", BASE64.encode(wav(&samples)));
+        assert_eq!(decode_data_uri(&uri, 64 * 1024).unwrap(), samples);
+
+        // 16 kHz stereo L16 (big-endian) -> 8 kHz mono.
+        let l16: Vec<u8> = (0..3200).flat_map(|_| 1000i16.to_be_bytes()).collect();
+        let uri = format!(
+            "data:audio/L16;rate=16000;channels=2;base64,{}",
+            BASE64.encode(&l16)
+        );
+        let decoded = decode_data_uri(&uri, 64 * 1024).unwrap();
+        assert_eq!(decoded.len(), 800);
+        assert!(decoded[100..700].iter().all(|s| (s - 1000).abs() < 20));
+
+        for bad in [
+            uri.clone(),
+            "data:audio/L16;base64,AAAA".to_string(),
+            "data:audio/wav;base64,!!!!".to_string(),
+            "data:audio/wav;base64,AAAAAAAA".to_string(),
+            "data:audio/mpeg;base64,AAAA".to_string(),
+        ] {
+            let err = decode_data_uri(&bad, 1024).unwrap_err();
+            assert!(err.is::<InvalidDataUri>(), "{}", bad);
+        }
+    }
+
+    /// Vorbis başlıkları için LSB-önce bit yazıcı.
+    #[derive(Default)]
+    struct Bits {
+        out: Vec<u8>,
+        len: usize,
+    }
+
+    impl Bits {
+        fn put(&mut self, value: u32, bits: usize) -> &mut Self {
+            for i in 0..bits {
+                if self.len.is_multiple_of(8) {
+                    self.out.push(0);
+                }
+                if value >> i & 1 == 1 {
+                    *self.out.last_mut().unwrap() |= 1 << (self.len % 8);
+                }
+                self.len += 1;
+            }
+            self
+        }
+    }
+
+    fn ogg(serial: u32, packets: Vec<(Vec<u8>, ogg::PacketWriteEndInfo, u64)>) -> Vec<u8> {
+        let mut writer = ogg::PacketWriter::new(Vec::new());
+        for (packet, end, granule) in packets {
+            writer
+                .write_packet(packet.into_boxed_slice(), serial, end, granule)
+                .unwrap();
+        }
+        writer.into_inner()
+    }
+
+    /// En küçük geçerli Vorbis I akışı: tek kısa blok modu, her ses paketinde floor "kullanılmıyor"
+    /// işaretli (sessizlik). İlk paket örnek üretmez, sonrakiler 256/4 + 256/4 = 128 örnek.
+    fn silent_vorbis(packets: usize) -> Vec<u8> {
+        use ogg::PacketWriteEndInfo::{EndPage, EndStream, NormalPacket};
+        let mut ident = vec![1];
+        ident.extend_from_slice(b"vorbis");
+        ident.extend_from_slice(&0u32.to_le_bytes());
+        ident.push(1);
+        ident.extend_from_slice(&8000u32.to_le_bytes());
+        ident.extend_from_slice(&[0; 12]);
+        ident.extend_from_slice(&[0xB8, 1]);
+        let mut comment = vec![3];
+        comment.extend_from_slice(b"vorbis");
+        comment.extend_from_slice(&[0; 8]);
+        comment.push(1);
+
+        let mut setup = Bits::default();
+        for byte in b"\x05vorbis" {
+            setup.put(*byte as u32, 8);
+        }
+        // Kod kitabı: 1 boyut, iki adet 1 bitlik giriş, arama tablosu yok.
+        setup.put(0, 8).put(0x564342, 24).put(1, 16).put(2, 24);
+        setup.put(0, 1).put(0, 1).put(0, 5).put(0, 5).put(0, 4);
+        // Zaman alanı, floor 1 (bölümsüz), residue 0, eşleme 0, mod 0 ve çerçeve biti.
+        setup.put(0, 6).put(0, 16);
+        setup.put(0, 6).put(1, 16).put(0, 5).put(0, 2).put(8, 4);
+        setup.put(0, 6).put(0, 16).put(0, 24).put(0, 24).put(31, 24);
+        setup.put(0, 6).put(0, 8).put(0, 3).put(0, 1);
+        setup.put(0, 6).put(0, 16).put(0, 1).put(0, 1).put(0, 2);
+        setup.put(0, 8).put(0, 8).put(0, 8);
+        setup.put(0, 6).put(0, 1).put(0, 16).put(0, 16).put(0, 8);
+        setup.put(1, 1);
+
+        let mut stream = vec![
+            (ident, EndPage, 0),
+            (comment, NormalPacket, 0),
+            (setup.out, EndPage, 0),
+        ];
+        for n in 0..packets {
+            let end = if n + 1 == packets {
+                EndStream
+            } else {
+                NormalPacket
+            };
+            stream.push((vec![0], end, n as u64 * 128));
+        }
+        ogg(0x766f72, stream)
+    }
+
+    #[test]
+    fn test_mp3_vorbis_and_opus_decode_to_prompt_rate() {
+        // MPEG-1 Layer III, 128 kbps, 44.1 kHz mono; yan bilgisi sıfır çerçeveler sessizliktir.
+        let mut frame = vec![0xFF, 0xFB, 0x90, 0xC0];
+        frame.resize(417, 0);
+        let mp3 = frame.repeat(20);
+        let decoded = decode_audio(mp3, None).unwrap();
+        assert_eq!(decoded.len(), 20 * 1152 * 8000 / 44100);
+        assert!(decoded.iter().all(|&s| s == 0));
+
+        let decoded = decode_audio(silent_vorbis(21), None).unwrap();
+        assert_eq!(decoded.len(), 20 * 128);
+        assert!(decoded.iter().all(|&s| s == 0));
+
+        // Ogg/Opus: 10 paket (9600 örnek @48 kHz), pre-skip 312, son granül 312 + 7200. Çözücü
+        // baştaki 312 örneği atlar, sondaki dolguyu kırpar: 7200 / 6 = 1200 örnek @8 kHz.
+        use ogg::PacketWriteEndInfo::{EndPage, EndStream, NormalPacket};
+        let encoder = audiopus::coder::Encoder::new(
+            SampleRate::Hz48000,
+            Channels::Mono,
+            audiopus::Application::Voip,
+        )
+        .unwrap();
+        let mut head = b"OpusHead".to_vec();
+        head.extend_from_slice(&[1, 1]);
+        head.extend_from_slice(&312u16.to_le_bytes());
+        head.extend_from_slice(&8000u32.to_le_bytes());
+        head.extend_from_slice(&[0, 0, 0]);
+        let mut tags = b"OpusTags".to_vec();
+        tags.extend_from_slice(&[0; 8]);
+        let mut stream = vec![(head, EndPage, 0), (tags, EndPage, 0)];
+        let tone: Vec<i16> = (0..960).map(|i| ((i % 48) as i16 - 24) * 400).collect();
+        for n in 0..10u64 {
+            let mut packet = vec![0u8; 1500];
+            let len = encoder.encode(&tone, &mut packet).unwrap();
+            packet.truncate(len);
+            let (end, granule) = match n {
+                9 => (EndStream, 312 + 7200),
+                _ => (NormalPacket, (n + 1) * 960),
+            };
+            stream.push((packet, end, granule));
+        }
+        let decoded = decode_audio(ogg(1, stream), None).unwrap();
+        assert_eq!(decoded.len(), 1200);
+    }
+
+    #[test]
+    fn test_decoding_stops_at_max_prompt_duration() {
+        // ~40 KB'lık Vorbis akışı 10 dakikadan uzun sessizliğe açılır.
+        let limit = MAX_PROMPT_DURATION.as_secs() as usize * 8000;
+        let packets = limit / 128 + 2;
+        let err = decode_audio(silent_vorbis(packets), None).unwrap_err();
+        assert!(
+            err.to_string().contains("saniye sınırını aşıyor"),
+            "{:#}",
+            err
+        );
+        assert!(decode_audio(silent_vorbis(packets - 1), None).is_ok());
+
+        let err = decode_audio(vec![0xFF; limit + 1], Some(AudioFormat::Mulaw)).unwrap_err();
+        assert!(
+            err.to_string().contains("saniye sınırını aşıyor"),
+            "{:#}",
+            err
+        );
+        assert_eq!(
+            decode_audio(vec![0xFF; limit], Some(AudioFormat::Mulaw))
+                .unwrap()
+                .len(),
+            limit
+        );
+    }
+
+    #[tokio::test]
+    async fn test_http_audio_is_cached_and_revalidated_by_etag() {
+        let samples: Vec<i16> = (0..800).map(|i| i as i16).collect();
+        let (url, hits) = serve(wav(&samples)).await;
+        let cache: AudioCache = Arc::new(Mutex::new(HashMap::new()));
+        let http = reqwest::Client::new();
+        let mut limits = RemoteAudioLimits {
+            max_bytes: 1024 * 1024,
+            timeout: Duration::from_secs(5),
+            revalidate_after: Duration::from_secs(60),
+            cache_max_bytes: usize::MAX,
+        };
+
+        let first = load_remote_or_get_from_cache(&cache, &url, None, &http, limits)
+            .await
+            .unwrap();
+        assert_eq!(*first, samples);
+        load_remote_or_get_from_cache(&cache, &url, None, &http, limits)
+            .await
+            .unwrap();
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Süre dolunca koşullu istek yapılır; 304 aynı örnekleri döndürür.
+        limits.revalidate_after = Duration::ZERO;
+        let again = load_remote_or_get_from_cache(&cache, &url, None, &http, limits)
+            .await
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        cache.lock().await.clear();
+        limits.max_bytes = 100;
+        assert!(
+            load_remote_or_get_from_cache(&cache, &url, None, &http, limits)
+                .await
+                .is_err()
+        );
+
+        // Servisin istemcisi iç ağa çözülen adlara bağlanmaz.
+        let guarded = reqwest::Client::builder()
+            .dns_resolver(Arc::new(PublicOnlyResolver))
+            .build()
+            .unwrap();
+        limits.max_bytes = 1024 * 1024;
+        let local = url.replace("127.0.0.1", "localhost");
+        let err = load_remote_or_get_from_cache(&cache, &local, None, &guarded, limits)
+            .await
+            .unwrap_err();
+        assert!(err.chain().any(|c| c.is::<AudioSourceNotAllowed>()));
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_remote_sources_are_allowlisted_and_internal_addresses_rejected() {
+        let s3_allow = ["prompts".to_string(), "shared/anons/".to_string()];
+        let http_hosts = ["cdn.example.com".to_string(), "93.184.216.34".to_string()];
+        let allowed = |uri: &str| check_remote_source(uri, &s3_allow, &http_hosts).is_ok();
+
+        assert!(allowed("s3://prompts/tr/hosgeldin.wav"));
+        assert!(allowed("s3://shared/anons/menu.wav"));
+        assert!(!allowed("s3://shared/recordings/cagri.wav"));
+        assert!(!allowed("s3://recordings/2026/01/01/cagri.wav"));
+        assert!(!allowed("s3://promptsx/a.wav"));
+        assert!(!allowed("s3://shared/anons/../recordings/cagri.wav"));
+
+        assert!(allowed("https://cdn.example.com/menu.mp3"));
+        assert!(allowed("https://CDN.example.com:8443/menu.mp3"));
+        assert!(allowed("http://93.184.216.34/menu.wav"));
+        assert!(!allowed("https://evil.example.com/menu.mp3"));
+        assert!(!allowed("https://cdn.example.com.evil.net/menu.mp3"));
+        // İç ağ adresleri listede olsa bile reddedilir.
+        for (uri, host) in [
+            (
+                "http://169.254.169.254/latest/meta-data/",
+                "169.254.169.254",
+            ),
+            ("http://127.0.0.1/a.wav", "127.0.0.1"),
+            ("http://10.0.0.5/a.wav", "10.0.0.5"),
+            ("http://[::1]/a.wav", "::1"),
+            ("http://[fe80::1]/a.wav", "fe80::1"),
+            ("http://[::ffff:192.168.1.1]/a.wav", "::ffff:192.168.1.1"),
+        ] {
+            let err = check_remote_source(uri, &s3_allow, &[host.to_string()]).unwrap_err();
+            assert!(
+                err.downcast_ref::<AudioSourceNotAllowed>().is_some(),
+                "{}",
+                uri
+            );
+        }
+
+        assert!(is_public_ip("8.8.8.8".parse().unwrap()));
+        assert!(is_public_ip("2606:4700::1111".parse().unwrap()));
+        for ip in [
+            "100.64.0.1",
+            "172.16.0.1",
+            "0.1.2.3",
+            "fd00::1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used_over_limit() {
+        let mut cache = HashMap::new();
+        let audio = |len| CachedAudio::new(Arc::new(vec![0i16; len]), None);
+        cache_insert(&mut cache, "a".into(), audio(100), 400);
+        cache_insert(&mut cache, "b".into(), audio(50), 400);
+        // "a" yeniden çalındı; sınır aşılınca "b" atılır.
+        cache.get_mut("a").unwrap().used_at = Instant::now() + Duration::from_secs(1);
+        cache_insert(&mut cache, "c".into(), audio(100), 400);
+        let mut keys: Vec<&String> = cache.keys().collect();
+        keys.sort();
+        assert_eq!(keys, ["a", "c"]);
+
+        cache_insert(&mut cache, "dev".into(), audio(201), 400);
+        assert!(!cache.contains_key("dev"));
+        assert_eq!(cache.len(), 2);
+    }
+}
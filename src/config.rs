@@ -1,5 +1,7 @@
 // Dosya: src/config.rs
 use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use md5::{Digest, Md5};
 use std::env;
 use std::net::SocketAddr;
 use std::time::Duration;
@@ -10,6 +12,14 @@ pub enum MediaEngineMode {
     Hardware,
 }
 
+/// Varsayılan kayıt deposu (`RECORDING_STORE`).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordingStoreKind {
+    S3,
+    Local,
+    Memory,
+}
+
 #[derive(Debug, Clone)]
 pub struct S3Config {
     pub endpoint_url: String,
@@ -17,6 +27,102 @@ pub struct S3Config {
     pub access_key_id: String,
     pub secret_access_key: String,
     pub bucket_name: String,
+    pub sse: S3Sse,
+}
+
+/// Bucket tarafı şifreleme (SSE). Destekleyen bucket'larda kayıt yüklemelerine başlık olarak eklenir.
+#[derive(Clone, Default, PartialEq)]
+pub enum S3Sse {
+    #[default]
+    None,
+    /// SSE-S3 (`AES256`).
+    S3Managed,
+    /// SSE-KMS; anahtar verilmezse bucket'ın varsayılan KMS anahtarı kullanılır.
+    Kms { key_id: Option<String> },
+    /// SSE-C: anahtar her istekte gönderilir, S3 saklamaz.
+    CustomerKey { key: String, key_md5: String },
+}
+
+impl std::fmt::Debug for S3Sse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::None => write!(f, "None"),
+            Self::S3Managed => write!(f, "S3Managed"),
+            Self::Kms { key_id } => write!(f, "Kms({:?})", key_id),
+            // SSE-C anahtarı loglara düşmemeli.
+            Self::CustomerKey { .. } => write!(f, "CustomerKey(***)"),
+        }
+    }
+}
+
+impl S3Sse {
+    // BUCKET_SSE=none|AES256|aws:kms|SSE-C
+    fn from_env() -> Result<Self> {
+        let mode = env::var("BUCKET_SSE").unwrap_or_else(|_| "none".to_string());
+        match mode.to_uppercase().as_str() {
+            "" | "NONE" => Ok(Self::None),
+            "AES256" => Ok(Self::S3Managed),
+            "AWS:KMS" => Ok(Self::Kms {
+                key_id: env::var("BUCKET_SSE_KMS_KEY_ID").ok(),
+            }),
+            "SSE-C" => {
+                let key =
+                    env::var("BUCKET_SSE_C_KEY").context("SSE-C için BUCKET_SSE_C_KEY eksik")?;
+                let raw = BASE64
+                    .decode(key.trim())
+                    .context("BUCKET_SSE_C_KEY base64 değil")?;
+                if raw.len() != 32 {
+                    bail!("BUCKET_SSE_C_KEY 32 bayt (AES-256) olmalı.");
+                }
+                Ok(Self::CustomerKey {
+                    key: BASE64.encode(&raw),
+                    key_md5: BASE64.encode(Md5::digest(&raw)),
+                })
+            }
+            other => bail!("Geçersiz BUCKET_SSE değeri: {}", other),
+        }
+    }
+
+    pub fn algorithm(&self) -> Option<&'static str> {
+        match self {
+            Self::S3Managed => Some("AES256"),
+            Self::Kms { .. } => Some("aws:kms"),
+            Self::None | Self::CustomerKey { .. } => None,
+        }
+    }
+
+    pub fn kms_key_id(&self) -> Option<String> {
+        match self {
+            Self::Kms { key_id } => key_id.clone(),
+            _ => None,
+        }
+    }
+
+    pub fn customer_algorithm(&self) -> Option<String> {
+        matches!(self, Self::CustomerKey { .. }).then(|| "AES256".to_string())
+    }
+
+    pub fn customer_key(&self) -> Option<String> {
+        match self {
+            Self::CustomerKey { key, .. } => Some(key.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn customer_key_md5(&self) -> Option<String> {
+        match self {
+            Self::CustomerKey { key_md5, .. } => Some(key_md5.clone()),
+            _ => None,
+        }
+    }
+
+    /// Olay metadatasına yazılan kısa ad.
+    pub fn label(&self) -> Option<&'static str> {
+        match self {
+            Self::CustomerKey { .. } => Some("SSE-C"),
+            other => other.algorithm(),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -28,6 +134,18 @@ pub struct AppConfig {
     pub rtp_port_max: u16,
     pub rtp_port_quarantine_duration: Duration,
     pub assets_base_path: String,
+    /// `s3://` ve `http(s)://` anonsları için indirme sınırları.
+    pub audio_remote_max_bytes: usize,
+    pub audio_remote_timeout: Duration,
+    pub audio_remote_revalidate: Duration,
+    /// İzin verilen `s3://` anons kaynakları (`bucket` veya `bucket/önek`); boşsa şema kapalıdır.
+    pub audio_remote_s3_allow: Vec<String>,
+    /// İzin verilen `http(s)://` anons sunucuları; boşsa şema kapalıdır.
+    pub audio_remote_http_hosts: Vec<String>,
+    /// Çözülmüş anons önbelleğinin üst sınırı; aşılınca en uzun süredir çalınmayan atılır.
+    pub audio_cache_max_bytes: usize,
+    /// `data:` URI ile satır içi gelen ses kabının (base64 çözüldükten sonraki) üst sınırı.
+    pub audio_data_uri_max_bytes: usize,
     pub media_recording_path: String,
     pub env: String,
     pub rust_log: String,
@@ -45,6 +163,33 @@ pub struct AppConfig {
     pub key_path: String,
     pub ca_path: String,
     pub audio_recording_gain: f32,
+    pub jitter_buffer_min_ms: u64,
+    pub jitter_buffer_max_ms: u64,
+    pub dtmf_inband_detection: bool,
+    /// SDP'de anlaşılan telephone-event (RFC 4733) dinamik payload tipi.
+    pub dtmf_payload_type: u8,
+    pub dtmf_tone_duration: Duration,
+    pub dtmf_inter_digit_gap: Duration,
+    pub dtmf_send_mode: String,
+    pub vad_enabled: bool,
+    pub vad_threshold_db: f32,
+    pub vad_min_energy_dbfs: f32,
+    pub vad_min_speech_ms: u32,
+    pub vad_hangover_ms: u32,
+    pub barge_in_duck_db: f32,
+    pub aec_enabled: bool,
+    pub aec_tail_ms: u32,
+    pub aec_bulk_delay_ms: u32,
+    pub ns_enabled: bool,
+    pub ns_max_attenuation_db: f32,
+    pub agc_enabled: bool,
+    pub agc_target_dbfs: f32,
+    pub agc_max_gain_db: f32,
+
+    pub recording_keyring_path: Option<String>,
+    pub recording_store: RecordingStoreKind,
+    /// S3'e yüklenemeyen kayıtlar için yerel spool üst sınırı; 0 spool'u kapatır.
+    pub recording_spool_max_bytes: u64,
 
     pub tenant_id: String, // [ARCH-COMPLIANCE] Tenant ID runtime'da çözülmek için eklendi
 }
@@ -83,6 +228,7 @@ impl AppConfig {
                 access_key_id: env::var("BUCKET_ACCESS_KEY_ID")?,
                 secret_access_key: env::var("BUCKET_SECRET_ACCESS_KEY")?,
                 bucket_name: env::var("BUCKET_NAME")?,
+                sse: S3Sse::from_env()?,
             })
         } else {
             None
@@ -102,6 +248,153 @@ impl AppConfig {
             .parse()
             .unwrap_or(1.0);
 
+        let jitter_buffer_min_ms: u64 = env::var("RTP_JITTER_BUFFER_MIN_MS")
+            .unwrap_or_else(|_| "40".to_string())
+            .parse()?;
+        let jitter_buffer_max_ms: u64 = env::var("RTP_JITTER_BUFFER_MAX_MS")
+            .unwrap_or_else(|_| "200".to_string())
+            .parse()?;
+
+        // Varsayılan: bucket tanımlıysa S3, değilse `MEDIA_RECORDING_PATH` (MinIO'suz geliştirme/CI).
+        let recording_store = match env::var("RECORDING_STORE") {
+            Ok(value) => match value.to_lowercase().as_str() {
+                "s3" => RecordingStoreKind::S3,
+                "local" => RecordingStoreKind::Local,
+                "memory" => RecordingStoreKind::Memory,
+                other => bail!("RECORDING_STORE geçersiz: {} (s3 | local | memory)", other),
+            },
+            Err(_) if s3_config.is_some() => RecordingStoreKind::S3,
+            Err(_) => RecordingStoreKind::Local,
+        };
+        if recording_store == RecordingStoreKind::S3 && s3_config.is_none() {
+            bail!("RECORDING_STORE=s3 için BUCKET_ENDPOINT_URL ve bucket ayarları gerekli.");
+        }
+
+        let audio_remote_max_kb: usize = env::var("AUDIO_REMOTE_MAX_KB")
+            .unwrap_or_else(|_| "10240".to_string())
+            .parse()?;
+        let audio_remote_timeout_ms: u64 = env::var("AUDIO_REMOTE_TIMEOUT_MS")
+            .unwrap_or_else(|_| "5000".to_string())
+            .parse()?;
+        let audio_remote_revalidate_seconds: u64 = env::var("AUDIO_REMOTE_REVALIDATE_SECONDS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()?;
+
+        // Servisin S3 kimliği kayıt bucket'ını da okuyabilir; anons kaynakları açıkça sayılır.
+        let list = |name: &str| -> Vec<String> {
+            env::var(name)
+                .unwrap_or_default()
+                .split(',')
+                .map(|item| item.trim().to_string())
+                .filter(|item| !item.is_empty())
+                .collect()
+        };
+        let audio_remote_s3_allow = list("AUDIO_REMOTE_S3_ALLOW");
+        let audio_remote_http_hosts: Vec<String> = list("AUDIO_REMOTE_HTTP_HOSTS")
+            .into_iter()
+            .map(|host| host.to_lowercase())
+            .collect();
+        let audio_cache_max_mb: usize = env::var("AUDIO_CACHE_MAX_MB")
+            .unwrap_or_else(|_| "256".to_string())
+            .parse()?;
+
+        let audio_data_uri_max_kb: usize = env::var("AUDIO_DATA_URI_MAX_KB")
+            .unwrap_or_else(|_| "256".to_string())
+            .parse()?;
+
+        let recording_spool_max_mb: u64 = env::var("RECORDING_SPOOL_MAX_MB")
+            .unwrap_or_else(|_| "2048".to_string())
+            .parse()?;
+
+        if jitter_buffer_min_ms > jitter_buffer_max_ms {
+            bail!("Jitter buffer aralığı geçersiz (min > max).");
+        }
+
+        // RFC 4733 göndermeyen eski santraller için PCM üzerinde Goertzel tabanlı yedek dedektör.
+        let dtmf_inband_detection: bool = env::var("DTMF_INBAND_DETECTION")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
+
+        let dtmf_payload_type: u8 = env::var("DTMF_PAYLOAD_TYPE")
+            .unwrap_or_else(|_| "101".to_string())
+            .parse()?;
+        if !(96..=127).contains(&dtmf_payload_type) {
+            bail!("DTMF_PAYLOAD_TYPE dinamik aralıkta (96-127) olmalı.");
+        }
+
+        let dtmf_tone_ms: u64 = env::var("DTMF_TONE_DURATION_MS")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse()?;
+        let dtmf_gap_ms: u64 = env::var("DTMF_INTER_DIGIT_GAP_MS")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse()?;
+
+        // VAD: konuşma başlangıç/bitiş olayları ve canlı akıştaki frame işaretleri.
+        let vad_enabled: bool = env::var("VAD_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse()
+            .unwrap_or(true);
+        let vad_threshold_db: f32 = env::var("VAD_THRESHOLD_DB")
+            .unwrap_or_else(|_| "9.0".to_string())
+            .parse()?;
+        let vad_min_energy_dbfs: f32 = env::var("VAD_MIN_ENERGY_DBFS")
+            .unwrap_or_else(|_| "-50.0".to_string())
+            .parse()?;
+        let vad_min_speech_ms: u32 = env::var("VAD_MIN_SPEECH_MS")
+            .unwrap_or_else(|_| "60".to_string())
+            .parse()?;
+        let vad_hangover_ms: u32 = env::var("VAD_HANGOVER_MS")
+            .unwrap_or_else(|_| "400".to_string())
+            .parse()?;
+
+        // Barge-in "duck" modunda bot sesine uygulanan zayıflatma (negatif dB).
+        let barge_in_duck_db: f32 = env::var("BARGE_IN_DUCK_DB")
+            .unwrap_or_else(|_| "-18.0".to_string())
+            .parse()?;
+
+        // AEC: Hoparlörlü telefonlardan geri sızan bot sesini (TTS) arayan sesinden temizler.
+        let aec_enabled: bool = env::var("AEC_ENABLED")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
+        // Tail, RTT + jitter buffer tahmininin üstündeki karşı taraf (telefon jitter buffer'ı,
+        // akustik yol) gecikmesini kapsar.
+        let aec_tail_ms: u32 = env::var("AEC_TAIL_MS")
+            .unwrap_or_else(|_| "128".to_string())
+            .parse()?;
+        // RTCP ile RTT ölçülene kadar kullanılan ağ gecikmesi tahmini.
+        let aec_bulk_delay_ms: u32 = env::var("AEC_BULK_DELAY_MS")
+            .unwrap_or_else(|_| "40".to_string())
+            .parse()?;
+
+        if aec_bulk_delay_ms < 20 {
+            bail!("AEC_BULK_DELAY_MS en az bir frame (20ms) olmalı.");
+        }
+
+        // Ingress DSP: spektral gürültü bastırma + hedef seviyeli AGC (limiter dahil).
+        let ns_enabled: bool = env::var("NS_ENABLED")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
+        let ns_max_attenuation_db: f32 = env::var("NS_MAX_ATTENUATION_DB")
+            .unwrap_or_else(|_| "18.0".to_string())
+            .parse()?;
+        let agc_enabled: bool = env::var("AGC_ENABLED")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
+        let agc_target_dbfs: f32 = env::var("AGC_TARGET_DBFS")
+            .unwrap_or_else(|_| "-18.0".to_string())
+            .parse()?;
+        let agc_max_gain_db: f32 = env::var("AGC_MAX_GAIN_DB")
+            .unwrap_or_else(|_| "24.0".to_string())
+            .parse()?;
+
+        if agc_target_dbfs >= 0.0 {
+            bail!("AGC_TARGET_DBFS 0 dBFS'in altında olmalı.");
+        }
+
         // [ARCH-COMPLIANCE] tenant_id zorunlu alan doğrulaması
         let tenant_id = env::var("TENANT_ID").map_err(|_| {
             anyhow::anyhow!("[ARCH-COMPLIANCE] TENANT_ID env var zorunludur, tanımlanmamış")
@@ -118,6 +411,13 @@ impl AppConfig {
             rtp_advertised_ip: env::var("RTP_SERVICE_ADVERTISED_IP")
                 .context("RTP_SERVICE_ADVERTISED_IP (Node IP) eksik!")?,
             assets_base_path: env::var("ASSETS_BASE_PATH").unwrap_or_else(|_| "assets".to_string()),
+            audio_remote_max_bytes: audio_remote_max_kb * 1024,
+            audio_remote_timeout: Duration::from_millis(audio_remote_timeout_ms),
+            audio_remote_revalidate: Duration::from_secs(audio_remote_revalidate_seconds),
+            audio_remote_s3_allow,
+            audio_remote_http_hosts,
+            audio_cache_max_bytes: audio_cache_max_mb * 1024 * 1024,
+            audio_data_uri_max_bytes: audio_data_uri_max_kb * 1024,
             media_recording_path: env::var("MEDIA_RECORDING_PATH")
                 .unwrap_or_else(|_| "/tmp/sentiric/recordings".to_string()),
             rtp_port_min,
@@ -139,6 +439,31 @@ impl AppConfig {
             key_path: env::var("MEDIA_SERVICE_KEY_PATH")?,
             ca_path: env::var("GRPC_TLS_CA_PATH")?,
             audio_recording_gain,
+            jitter_buffer_min_ms,
+            jitter_buffer_max_ms,
+            dtmf_inband_detection,
+            dtmf_payload_type,
+            dtmf_tone_duration: Duration::from_millis(dtmf_tone_ms),
+            dtmf_inter_digit_gap: Duration::from_millis(dtmf_gap_ms),
+            dtmf_send_mode: env::var("DTMF_SEND_MODE").unwrap_or_else(|_| "rfc4733".to_string()),
+            vad_enabled,
+            vad_threshold_db,
+            vad_min_energy_dbfs,
+            vad_min_speech_ms,
+            vad_hangover_ms,
+            barge_in_duck_db,
+            aec_enabled,
+            aec_tail_ms,
+            aec_bulk_delay_ms,
+            ns_enabled,
+            ns_max_attenuation_db,
+            agc_enabled,
+            agc_target_dbfs,
+            agc_max_gain_db,
+            // Kayıtların zarf şifrelemesi için kiracı anahtarlığı (JSON); yoksa kayıtlar şifresiz yazılır.
+            recording_keyring_path: env::var("RECORDING_KEYRING_PATH").ok(),
+            recording_store,
+            recording_spool_max_bytes: recording_spool_max_mb * 1024 * 1024,
             tenant_id,
         })
     }
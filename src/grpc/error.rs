@@ -11,6 +11,24 @@ pub enum ServiceError {
     InvalidUri {
         uri: String,
     },
+    AudioSourceNotAllowed {
+        uri: String,
+    },
+    UnsupportedSampleRate {
+        rate: u32,
+    },
+    RecordingTargetUnavailable {
+        uri: String,
+    },
+    NoActiveRecording {
+        port: u16,
+    },
+    RecordingAlreadyActive {
+        port: u16,
+    },
+    EncryptionKeyUnavailable {
+        tenant_id: String,
+    },
     InvalidTargetAddress {
         addr: String,
         source: std::net::AddrParseError,
@@ -32,6 +50,24 @@ impl Display for ServiceError {
             ServiceError::InvalidUri { uri } => {
                 write!(f, "Unsupported or invalid URI scheme: {}", uri)
             }
+            ServiceError::AudioSourceNotAllowed { uri } => {
+                write!(f, "Audio source is not allowed: {}", uri)
+            }
+            ServiceError::UnsupportedSampleRate { rate } => {
+                write!(f, "Unsupported target sample rate: {} Hz", rate)
+            }
+            ServiceError::RecordingTargetUnavailable { uri } => {
+                write!(f, "Recording target is not configured: {}", uri)
+            }
+            ServiceError::NoActiveRecording { port } => {
+                write!(f, "No active recording on session port {}.", port)
+            }
+            ServiceError::RecordingAlreadyActive { port } => {
+                write!(f, "A recording is already active on session port {}.", port)
+            }
+            ServiceError::EncryptionKeyUnavailable { tenant_id } => {
+                write!(f, "No recording encryption key for tenant: {}", tenant_id)
+            }
             ServiceError::InvalidTargetAddress { addr, .. } => {
                 write!(f, "Invalid target RTP address format: {}", addr)
             }
@@ -54,9 +90,14 @@ impl From<ServiceError> for Status {
         match err {
             ServiceError::PortPoolExhausted => Status::resource_exhausted(message),
             ServiceError::SessionNotFound { .. } => Status::not_found(message),
-            ServiceError::InvalidUri { .. } | ServiceError::InvalidTargetAddress { .. } => {
-                Status::invalid_argument(message)
-            }
+            ServiceError::InvalidUri { .. }
+            | ServiceError::UnsupportedSampleRate { .. }
+            | ServiceError::InvalidTargetAddress { .. } => Status::invalid_argument(message),
+            ServiceError::AudioSourceNotAllowed { .. } => Status::permission_denied(message),
+            ServiceError::RecordingTargetUnavailable { .. }
+            | ServiceError::NoActiveRecording { .. }
+            | ServiceError::RecordingAlreadyActive { .. }
+            | ServiceError::EncryptionKeyUnavailable { .. } => Status::failed_precondition(message),
 
             // --- GÜÇLENDİRİLMİŞ HATA YÖNETİMİ ---
             ServiceError::RecordingSaveFailed { source } => {
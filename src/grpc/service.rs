@@ -1,8 +1,16 @@
 // Dosya: src/grpc/service.rs
+use crate::audio::{display_uri, AudioSourceNotAllowed, InvalidDataUri};
 use crate::grpc::error::ServiceError;
 use crate::metrics::{ACTIVE_SESSIONS, GRPC_REQUESTS_TOTAL};
 use crate::rtp::command::{RecordingSession, RtpCommand};
+use crate::rtp::dtmf::{digit_to_event, DtmfSendMode};
+use crate::rtp::live_stream::{LiveStreamChannel, LiveStreamEncoding, LiveStreamFrameTags};
+use crate::rtp::recording::{RecordingFormat, RecordingPipeline};
 use crate::rtp::session::RtpSession;
+use crate::rtp::session_handlers::BargeInMode;
+use crate::rtp::session_utils;
+use crate::rtp::store::{LocalStore, S3Store};
+use crate::rtp::writers::{RecordingTarget, RECORDING_SAMPLE_RATES};
 use crate::state::AppState;
 use anyhow::Result;
 use hound::{SampleFormat, WavSpec};
@@ -33,6 +41,112 @@ impl MyMediaService {
         Self { app_state, config }
     }
 
+    /// `output_uri`'yi çözümler ve hedef depoya bağlar (bkz. `parse_recording_uri`).
+    fn parse_recording_output(
+        &self,
+        output_uri: &str,
+        call_id: &str,
+    ) -> Result<(RecordingTarget, WavSpec, RecordingFormat), ServiceError> {
+        let (output, spec, format) = parse_recording_uri(output_uri, call_id)?;
+        let target = match output {
+            RecordingOutput::Default => RecordingTarget::new(
+                self.app_state.recording_store.clone(),
+                session_utils::default_recording_key(call_id, format.extension()),
+            ),
+            RecordingOutput::S3 { bucket, key } => {
+                let client = self.app_state.s3_client.clone().ok_or_else(|| {
+                    ServiceError::RecordingTargetUnavailable {
+                        uri: output_uri.to_string(),
+                    }
+                })?;
+                let sse = self
+                    .config
+                    .s3_config
+                    .as_ref()
+                    .map(|c| c.sse.clone())
+                    .unwrap_or_default();
+                RecordingTarget::new(Arc::new(S3Store::new(client, bucket, sse)), key)
+            }
+            RecordingOutput::File { relative } => RecordingTarget::new(
+                Arc::new(LocalStore::new(&self.config.media_recording_path)),
+                relative,
+            ),
+        };
+        Ok((target, spec, format))
+    }
+
+    // control://ingress_dsp?ns=on|off&agc=on|off
+    fn parse_ingress_dsp_command(
+        uri: &str,
+        params: &std::collections::HashMap<String, String>,
+    ) -> Result<RtpCommand, ServiceError> {
+        let flag = |key: &str| -> Result<Option<bool>, ServiceError> {
+            match params.get(key).map(|v| v.to_ascii_lowercase()).as_deref() {
+                None => Ok(None),
+                Some("on" | "true" | "1") => Ok(Some(true)),
+                Some("off" | "false" | "0") => Ok(Some(false)),
+                Some(_) => Err(ServiceError::InvalidUri {
+                    uri: uri.to_string(),
+                }),
+            }
+        };
+        let noise_suppression = flag("ns")?;
+        let agc = flag("agc")?;
+        if noise_suppression.is_none() && agc.is_none() {
+            return Err(ServiceError::InvalidUri {
+                uri: uri.to_string(),
+            });
+        }
+        Ok(RtpCommand::SetIngressDsp {
+            noise_suppression,
+            agc,
+        })
+    }
+
+    // control://dtmf?digits=123#&duration_ms=100&gap_ms=100&mode=rfc4733|inband|auto
+    fn parse_dtmf_command(
+        &self,
+        uri: &str,
+        params: &std::collections::HashMap<String, String>,
+    ) -> Result<RtpCommand, ServiceError> {
+        let invalid = || ServiceError::InvalidUri {
+            uri: uri.to_string(),
+        };
+
+        let digits = params.get("digits").ok_or_else(invalid)?;
+        if digits.is_empty() || !digits.chars().all(|d| digit_to_event(d).is_some()) {
+            return Err(invalid());
+        }
+
+        let millis = |key: &str, default: std::time::Duration| match params.get(key) {
+            Some(v) => v
+                .parse::<u64>()
+                .map(std::time::Duration::from_millis)
+                .map_err(|_| invalid()),
+            None => Ok(default),
+        };
+        let tone_duration = millis("duration_ms", self.config.dtmf_tone_duration)?;
+        let inter_digit_gap = millis("gap_ms", self.config.dtmf_inter_digit_gap)?;
+        if tone_duration.as_millis() < 40 || tone_duration.as_secs() > 8 {
+            return Err(invalid());
+        }
+
+        let mode = DtmfSendMode::parse(
+            params
+                .get("mode")
+                .map(String::as_str)
+                .unwrap_or(&self.config.dtmf_send_mode),
+        )
+        .ok_or_else(invalid)?;
+
+        Ok(RtpCommand::SendDtmf {
+            digits: digits.clone(),
+            tone_duration,
+            inter_digit_gap,
+            mode,
+        })
+    }
+
     fn extract_trace_id<T>(req: &Request<T>) -> String {
         req.metadata()
             .get("x-trace-id")
@@ -42,6 +156,116 @@ impl MyMediaService {
     }
 }
 
+/// Çözümlenmiş kayıt hedefi; depoya bağlanması (`S3Store`, `LocalStore`) çağırana kalır.
+#[derive(Debug, PartialEq)]
+enum RecordingOutput {
+    /// Varsayılan depo, `default_recording_key` anahtarı.
+    Default,
+    S3 {
+        bucket: String,
+        key: String,
+    },
+    /// `media_recording_path` altında göreli yol.
+    File {
+        relative: String,
+    },
+}
+
+// s3://bucket/key.wav | file:///alt/dizin/kayit.flac | "" (varsayılan bucket)
+// İsteğe bağlı: ?sample_rate=8000|16000|24000|48000&channels=1|2&format=wav|flac|opus
+// Biçim uzantıdan da seçilir (.wav, .flac, .opus/.ogg); ikisi çelişirse istek reddedilir.
+// Anahtar/yol boşsa veya '/' ile bitiyorsa önek sayılır ve `{call_id}.{uzantı}` eklenir.
+fn parse_recording_uri(
+    output_uri: &str,
+    call_id: &str,
+) -> Result<(RecordingOutput, WavSpec, RecordingFormat), ServiceError> {
+    let invalid = || ServiceError::InvalidUri {
+        uri: output_uri.to_string(),
+    };
+    let (location, query) = output_uri.split_once('?').unwrap_or((output_uri, ""));
+    let params: std::collections::HashMap<String, String> =
+        url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+
+    let channels = match params.get("channels") {
+        Some(value) => value
+            .parse::<u16>()
+            .ok()
+            .filter(|c| *c == 1 || *c == 2)
+            .ok_or_else(invalid)?,
+        None => 2,
+    };
+    let sample_rate = match params.get("sample_rate") {
+        Some(value) => value
+            .parse::<u32>()
+            .ok()
+            .filter(|rate| RECORDING_SAMPLE_RATES.contains(rate))
+            .ok_or_else(invalid)?,
+        None => 8000,
+    };
+    let spec = WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: SampleFormat::Int,
+    };
+
+    let requested_path = match location.strip_prefix("s3://") {
+        Some(rest) => rest.split_once('/').map_or("", |(_, key)| key),
+        None => location.strip_prefix("file://").unwrap_or(""),
+    };
+    let format_param = match params.get("format") {
+        Some(value) => Some(RecordingFormat::parse(value).ok_or_else(invalid)?),
+        None => None,
+    };
+    let format_ext = if requested_path.is_empty() || requested_path.ends_with('/') {
+        None
+    } else {
+        RecordingFormat::from_path(requested_path).map_err(|_| invalid())?
+    };
+    let format = match (format_param, format_ext) {
+        (Some(param), Some(ext)) if param != ext => return Err(invalid()),
+        (Some(format), _) | (None, Some(format)) => format,
+        (None, None) => RecordingFormat::Wav,
+    };
+
+    let with_file_name = |path: &str| {
+        if path.is_empty() || path.ends_with('/') {
+            format!("{}{}.{}", path, call_id, format.extension())
+        } else {
+            path.to_string()
+        }
+    };
+    let output = if location.is_empty() {
+        RecordingOutput::Default
+    } else if let Some(rest) = location.strip_prefix("s3://") {
+        let (bucket, key) = rest.split_once('/').unwrap_or((rest, ""));
+        if bucket.is_empty() || key.starts_with('/') || key.split('/').any(|c| c == "..") {
+            return Err(invalid());
+        }
+        RecordingOutput::S3 {
+            bucket: bucket.to_string(),
+            key: with_file_name(key),
+        }
+    } else if let Some(path) = location.strip_prefix("file://") {
+        // Yol her zaman `media_recording_path` altına bağlanır; dışarı çıkılamaz. Noktayla
+        // başlayan dizinler (`.spool`, `.uploads`) servisin kendi çalışma alanıdır.
+        let relative = with_file_name(path.trim_start_matches('/'));
+        let escapes = std::path::Path::new(&relative)
+            .components()
+            .any(|c| !matches!(c, std::path::Component::Normal(_)))
+            || relative.starts_with('.');
+        if escapes {
+            return Err(invalid());
+        }
+        RecordingOutput::File { relative }
+    } else {
+        return Err(invalid());
+    };
+    Ok((output, spec, format))
+}
+
 #[tonic::async_trait]
 impl MediaService for MyMediaService {
     type StreamAudioToCallStream =
@@ -249,7 +473,12 @@ impl MediaService for MyMediaService {
             .await;
 
         if req.audio_uri.starts_with("control://") {
-            let cmd = req.audio_uri.strip_prefix("control://").unwrap();
+            let control = req.audio_uri.strip_prefix("control://").unwrap();
+            let (cmd, query) = control.split_once('?').unwrap_or((control, ""));
+            let params: std::collections::HashMap<String, String> =
+                url::form_urlencoded::parse(query.as_bytes())
+                    .into_owned()
+                    .collect();
             match cmd {
                 "enable_echo" => {
                     let _ = session.send_command(RtpCommand::EnableEchoTest).await;
@@ -265,6 +494,107 @@ impl MediaService for MyMediaService {
                         message: "Echo Off".into(),
                     }));
                 }
+                "stop_audio" => {
+                    session
+                        .send_command(RtpCommand::StopAudio)
+                        .await
+                        .map_err(|e| ServiceError::CommandSendError(e.to_string()))?;
+                    return Ok(Response::new(PlayAudioResponse {
+                        success: true,
+                        message: "Audio Stopped".into(),
+                    }));
+                }
+                "dtmf" => {
+                    let command = self.parse_dtmf_command(&req.audio_uri, &params)?;
+                    session
+                        .send_command(command)
+                        .await
+                        .map_err(|e| ServiceError::CommandSendError(e.to_string()))?;
+                    return Ok(Response::new(PlayAudioResponse {
+                        success: true,
+                        message: "DTMF Queued".into(),
+                    }));
+                }
+                "enable_barge_in" => {
+                    let mode = match params.get("mode") {
+                        Some(m) => {
+                            BargeInMode::parse(m).ok_or_else(|| ServiceError::InvalidUri {
+                                uri: req.audio_uri.clone(),
+                            })?
+                        }
+                        None => BargeInMode::Flush,
+                    };
+                    session
+                        .send_command(RtpCommand::EnableBargeIn { mode })
+                        .await
+                        .map_err(|e| ServiceError::CommandSendError(e.to_string()))?;
+                    return Ok(Response::new(PlayAudioResponse {
+                        success: true,
+                        message: "Barge-in On".into(),
+                    }));
+                }
+                "disable_barge_in" => {
+                    session
+                        .send_command(RtpCommand::DisableBargeIn)
+                        .await
+                        .map_err(|e| ServiceError::CommandSendError(e.to_string()))?;
+                    return Ok(Response::new(PlayAudioResponse {
+                        success: true,
+                        message: "Barge-in Off".into(),
+                    }));
+                }
+                "ingress_dsp" => {
+                    let command = Self::parse_ingress_dsp_command(&req.audio_uri, &params)?;
+                    session
+                        .send_command(command)
+                        .await
+                        .map_err(|e| ServiceError::CommandSendError(e.to_string()))?;
+                    return Ok(Response::new(PlayAudioResponse {
+                        success: true,
+                        message: "Ingress DSP Updated".into(),
+                    }));
+                }
+                "enable_aec" | "disable_aec" => {
+                    let (command, message) = if cmd == "enable_aec" {
+                        (RtpCommand::EnableEchoCancellation, "AEC On")
+                    } else {
+                        (RtpCommand::DisableEchoCancellation, "AEC Off")
+                    };
+                    session
+                        .send_command(command)
+                        .await
+                        .map_err(|e| ServiceError::CommandSendError(e.to_string()))?;
+                    return Ok(Response::new(PlayAudioResponse {
+                        success: true,
+                        message: message.into(),
+                    }));
+                }
+                // PCI: kart bilgisi alınırken kayıt kapatılmadan duraklatılır.
+                "pause_recording" | "resume_recording" => {
+                    let paused = cmd == "pause_recording";
+                    let (tx, rx) = oneshot::channel();
+                    session
+                        .send_command(RtpCommand::SetRecordingPaused {
+                            paused,
+                            responder: tx,
+                        })
+                        .await
+                        .map_err(|e| ServiceError::CommandSendError(e.to_string()))?;
+                    let changed = rx
+                        .await
+                        .map_err(|e| ServiceError::CommandSendError(e.to_string()))?
+                        .map_err(|_| ServiceError::NoActiveRecording { port: rtp_port })?;
+                    let message = match (paused, changed) {
+                        (true, true) => "Recording Paused",
+                        (true, false) => "Recording Already Paused",
+                        (false, true) => "Recording Resumed",
+                        (false, false) => "Recording Not Paused",
+                    };
+                    return Ok(Response::new(PlayAudioResponse {
+                        success: true,
+                        message: message.into(),
+                    }));
+                }
                 "set_target" => {
                     return Ok(Response::new(PlayAudioResponse {
                         success: true,
@@ -275,10 +605,11 @@ impl MediaService for MyMediaService {
             }
         }
 
+        let audio_uri = req.audio_uri;
         let (tx, rx) = oneshot::channel();
         session
             .send_command(RtpCommand::PlayAudioUri {
-                audio_uri: req.audio_uri,
+                audio_uri: audio_uri.clone(),
                 candidate_target_addr: target_addr,
                 cancellation_token: tokio_util::sync::CancellationToken::new(),
                 responder: Some(tx),
@@ -291,7 +622,26 @@ impl MediaService for MyMediaService {
                 success: true,
                 message: "OK".into(),
             })),
-            _ => Err(Status::internal("Playback failed")),
+            Ok(Err(e)) => {
+                // Çözücünün reddi reqwest hatasının kaynak zincirinde gelir.
+                if let Some(denied) = e
+                    .chain()
+                    .find_map(|c| c.downcast_ref::<AudioSourceNotAllowed>())
+                {
+                    return Err(ServiceError::AudioSourceNotAllowed {
+                        uri: denied.0.clone(),
+                    }
+                    .into());
+                }
+                match e.downcast_ref::<InvalidDataUri>() {
+                    Some(invalid) => Err(ServiceError::InvalidUri {
+                        uri: format!("{} ({})", display_uri(&audio_uri), invalid),
+                    }
+                    .into()),
+                    None => Err(Status::internal("Playback failed")),
+                }
+            }
+            Err(_) => Err(Status::internal("Playback failed")),
         }
     }
 
@@ -303,7 +653,42 @@ impl MediaService for MyMediaService {
         &self,
         request: Request<RecordAudioRequest>,
     ) -> Result<Response<Self::RecordAudioStream>, Status> {
+        // Proto'da alanı olmayan abone seçenekleri metadata ile gelir.
+        let metadata_value = |key: &str| {
+            request
+                .metadata()
+                .get(key)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        let channel = match metadata_value("x-live-stream-channel") {
+            Some(value) => LiveStreamChannel::parse(&value).ok_or_else(|| {
+                Status::invalid_argument(format!("Unknown live stream channel: {}", value))
+            })?,
+            None => LiveStreamChannel::Caller,
+        };
+        let encoding = match metadata_value("x-live-stream-encoding") {
+            Some(value) => LiveStreamEncoding::parse(&value).ok_or_else(|| {
+                Status::invalid_argument(format!("Unknown live stream encoding: {}", value))
+            })?,
+            None => LiveStreamEncoding::L16,
+        };
+        let frame_tags = match metadata_value("x-live-stream-frame-tags") {
+            Some(value) => LiveStreamFrameTags::parse(&value).ok_or_else(|| {
+                Status::invalid_argument(format!("Unknown live stream frame tags: {}", value))
+            })?,
+            None => LiveStreamFrameTags::default(),
+        };
+        let subscriber_id = metadata_value("x-live-stream-id")
+            .filter(|id| !id.is_empty())
+            .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
         let req = request.into_inner();
+        if let Some(rate) = req.target_sample_rate {
+            if !encoding.supports_sample_rate(rate) {
+                return Err(ServiceError::UnsupportedSampleRate { rate }.into());
+            }
+        }
         let rtp_port = req.server_rtp_port as u16;
         let session = self
             .app_state
@@ -314,8 +699,12 @@ impl MediaService for MyMediaService {
         let (tx, rx) = mpsc::channel(64);
         session
             .send_command(RtpCommand::StartLiveAudioStream {
+                subscriber_id: subscriber_id.clone(),
                 stream_sender: tx,
                 target_sample_rate: req.target_sample_rate,
+                channel,
+                encoding,
+                frame_tags,
             })
             .await
             .map_err(|_| Status::internal("Fail"))?;
@@ -325,7 +714,12 @@ impl MediaService for MyMediaService {
                 media_type: f.media_type,
             })
         });
-        Ok(Response::new(Box::pin(out)))
+        let mut response = Response::new(Box::pin(out) as Self::RecordAudioStream);
+        // İstemci sonradan aboneliğini tanımlayabilsin diye atanan ID döndürülür.
+        if let Ok(value) = subscriber_id.parse() {
+            response.metadata_mut().insert("x-live-stream-id", value);
+        }
+        Ok(response)
     }
 
     async fn start_recording(
@@ -339,24 +733,48 @@ impl MediaService for MyMediaService {
             .get_session(req.server_rtp_port as u16)
             .await
             .ok_or(Status::not_found("No session"))?;
+        // Kayıt çağrı sürerken hedefe akıtıldığı için hedef en baştan doğrulanır.
+        let (target, spec, format) = self.parse_recording_output(&req.output_uri, &req.call_id)?;
+        // Anahtarlık yapılandırılmışsa kayıt şifresiz yazılmaz; kiracının anahtarı yoksa reddedilir.
+        let encryptor = match &self.app_state.recording_keyring {
+            Some(keyring) => Some(keyring.encryptor(&self.config.tenant_id).map_err(|_| {
+                ServiceError::EncryptionKeyUnavailable {
+                    tenant_id: self.config.tenant_id.clone(),
+                }
+            })?),
+            None => None,
+        };
+        let pipeline = RecordingPipeline::start(
+            target,
+            format,
+            spec,
+            req.call_id.clone(),
+            encryptor,
+            self.app_state.recording_spool.clone(),
+        )
+        .map_err(ServiceError::InternalError)?;
+        let (tx, rx) = oneshot::channel();
         session
-            .send_command(RtpCommand::StartPermanentRecording(RecordingSession {
-                output_uri: req.output_uri,
-                spec: WavSpec {
-                    channels: 2,
-                    sample_rate: 8000,
-                    bits_per_sample: 16,
-                    sample_format: SampleFormat::Int,
-                },
-                rx_buffer: Vec::new(),
-                tx_buffer: Vec::new(),
-                call_id: req.call_id,
-                trace_id: req.trace_id,
-                max_reached_warned: false,
-            }))
+            .send_command(RtpCommand::StartPermanentRecording {
+                session: RecordingSession::new(
+                    req.output_uri,
+                    spec,
+                    pipeline,
+                    req.call_id,
+                    req.trace_id,
+                ),
+                responder: tx,
+            })
             .await
             .map_err(|_| Status::internal("Command fail"))?;
-        Ok(Response::new(StartRecordingResponse { success: true }))
+        match rx.await {
+            Ok(Ok(())) => Ok(Response::new(StartRecordingResponse { success: true })),
+            Ok(Err(_)) => Err(ServiceError::RecordingAlreadyActive {
+                port: req.server_rtp_port as u16,
+            }
+            .into()),
+            Err(_) => Err(Status::internal("Command fail")),
+        }
     }
 
     async fn stop_recording(
@@ -380,3 +798,92 @@ impl MediaService for MyMediaService {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recording_uri_rejects_escapes_and_bad_params() {
+        for uri in [
+            "file:///../x.wav",
+            "file://../x.wav",
+            "file://.spool/x",
+            "file:///.uploads/x.wav",
+            "s3:///k",
+            "s3://b//k.wav",
+            "s3://b/../k",
+            "s3://b/a/../../k.wav",
+            "s3://b/k.wav?channels=3",
+            "s3://b/k.wav?sample_rate=11025",
+            "s3://b/k.wav?format=mp3",
+            "s3://b/x.flac?format=opus",
+            "x.flac?format=opus",
+            "s3://b/x.mp3",
+            "http://h/x.wav",
+        ] {
+            assert!(
+                matches!(
+                    parse_recording_uri(uri, "c"),
+                    Err(ServiceError::InvalidUri { .. })
+                ),
+                "{} reddedilmeliydi",
+                uri
+            );
+        }
+    }
+
+    #[test]
+    fn test_recording_uri_targets_and_formats() {
+        let s3 = |key: &str| RecordingOutput::S3 {
+            bucket: "b".into(),
+            key: key.into(),
+        };
+        let cases = [
+            ("", RecordingOutput::Default, 2, 8000, RecordingFormat::Wav),
+            ("s3://b/a/", s3("a/c.wav"), 2, 8000, RecordingFormat::Wav),
+            (
+                "s3://b?format=flac",
+                s3("c.flac"),
+                2,
+                8000,
+                RecordingFormat::Flac,
+            ),
+            (
+                "s3://b/k.FLAC?format=flac&channels=1",
+                s3("k.FLAC"),
+                1,
+                8000,
+                RecordingFormat::Flac,
+            ),
+            (
+                "file:///x/k.opus?sample_rate=48000",
+                RecordingOutput::File {
+                    relative: "x/k.opus".into(),
+                },
+                2,
+                48000,
+                RecordingFormat::OggOpus,
+            ),
+            (
+                "file://x/?format=ogg",
+                RecordingOutput::File {
+                    relative: "x/c.opus".into(),
+                },
+                2,
+                8000,
+                RecordingFormat::OggOpus,
+            ),
+        ];
+        for (uri, output, channels, sample_rate, format) in cases {
+            let (parsed, spec, parsed_format) = parse_recording_uri(uri, "c").unwrap();
+            assert_eq!(parsed, output, "{}", uri);
+            assert_eq!(
+                (spec.channels, spec.sample_rate, parsed_format),
+                (channels, sample_rate, format),
+                "{}",
+                uri
+            );
+        }
+    }
+}
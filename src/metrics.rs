@@ -4,7 +4,7 @@ use hyper::{
     service::{make_service_fn, service_fn},
     Body, Request, Response, Server as HyperServer, StatusCode,
 };
-use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
 use std::convert::Infallible;
 use std::net::SocketAddr;
 use tracing::{error, info};
@@ -16,6 +16,28 @@ pub const ACTIVE_SESSIONS: &str = "sentiric_media_active_sessions";
 pub const RECORDING_BUFFER_BYTES: &str = "sentiric_media_recording_buffer_bytes";
 pub const S3_UPLOAD_FAILURES: &str = "sentiric_media_s3_upload_failures_total";
 
+// KAYIT SPOOL'U (S3 kesintisinde yerel kuyruk; dropped label: reason)
+pub const RECORDING_SPOOL_BYTES: &str = "sentiric_media_recording_spool_bytes";
+pub const RECORDING_SPOOL_ENTRIES: &str = "sentiric_media_recording_spool_entries";
+pub const RECORDING_SPOOL_DROPPED_TOTAL: &str = "sentiric_media_recording_spool_dropped_total";
+
+// RTP QoS METRİKLERİ (label: codec)
+pub const RTP_PACKET_LOSS_RATIO: &str = "sentiric_media_rtp_packet_loss_ratio";
+pub const RTP_JITTER_MS: &str = "sentiric_media_rtp_jitter_ms";
+pub const RTP_JITTER_BUFFER_DEPTH: &str = "sentiric_media_rtp_jitter_buffer_depth_packets";
+pub const RTP_PACKETS_LOST_TOTAL: &str = "sentiric_media_rtp_packets_lost_total";
+pub const RTP_PACKETS_OUT_OF_ORDER_TOTAL: &str = "sentiric_media_rtp_packets_out_of_order_total";
+pub const RTP_PACKETS_DUPLICATE_TOTAL: &str = "sentiric_media_rtp_packets_duplicate_total";
+pub const RTP_SESSION_LOSS_RATIO: &str = "sentiric_media_rtp_session_loss_ratio";
+pub const RTP_RTT_MS: &str = "sentiric_media_rtp_rtt_ms";
+
+// CANLI AKIŞ (label: channel)
+pub const LIVE_STREAM_DROPPED_FRAMES_TOTAL: &str =
+    "sentiric_media_live_stream_dropped_frames_total";
+
+// BARGE-IN (label: mode)
+pub const BARGE_IN_TOTAL: &str = "sentiric_media_barge_in_total";
+
 async fn route_handler(
     req: Request<Body>,
     recorder_handle: PrometheusHandle,
@@ -43,6 +65,35 @@ async fn route_handler(
 
 pub fn start_metrics_server(addr: SocketAddr) {
     let recorder_handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(RTP_PACKET_LOSS_RATIO.to_string()),
+            &[0.0, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5, 1.0],
+        )
+        .and_then(|b| {
+            b.set_buckets_for_metric(
+                Matcher::Full(RTP_SESSION_LOSS_RATIO.to_string()),
+                &[0.0, 0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5, 1.0],
+            )
+        })
+        .and_then(|b| {
+            b.set_buckets_for_metric(
+                Matcher::Full(RTP_JITTER_MS.to_string()),
+                &[1.0, 5.0, 10.0, 20.0, 30.0, 50.0, 80.0, 120.0, 200.0],
+            )
+        })
+        .and_then(|b| {
+            b.set_buckets_for_metric(
+                Matcher::Full(RTP_JITTER_BUFFER_DEPTH.to_string()),
+                &[1.0, 2.0, 3.0, 4.0, 6.0, 8.0, 10.0, 15.0, 20.0],
+            )
+        })
+        .and_then(|b| {
+            b.set_buckets_for_metric(
+                Matcher::Full(RTP_RTT_MS.to_string()),
+                &[10.0, 25.0, 50.0, 100.0, 150.0, 200.0, 300.0, 500.0, 1000.0],
+            )
+        })
+        .expect("Prometheus histogram bucket tanımı geçersiz")
         .install_recorder()
         .expect("Prometheus recorder kurulumu başarısız oldu");
 
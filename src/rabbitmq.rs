@@ -83,6 +83,17 @@ impl RabbitMqClient {
         &self,
         routing_key: &str,
         payload: &[u8],
+    ) -> anyhow::Result<()> {
+        self.publish_with_headers(routing_key, payload, FieldTable::default())
+            .await
+    }
+
+    /// Protobuf sözleşmesinde alanı olmayan ek bilgiler AMQP başlıklarıyla taşınır.
+    pub async fn publish_with_headers(
+        &self,
+        routing_key: &str,
+        payload: &[u8],
+        headers: FieldTable,
     ) -> anyhow::Result<()> {
         let channel_guard = self.channel.read().await;
         if let Some(channel) = channel_guard.as_ref() {
@@ -92,7 +103,9 @@ impl RabbitMqClient {
                     routing_key,
                     BasicPublishOptions::default(),
                     payload,
-                    BasicProperties::default().with_delivery_mode(2),
+                    BasicProperties::default()
+                        .with_delivery_mode(2)
+                        .with_headers(headers),
                 )
                 .await?
                 .await?;
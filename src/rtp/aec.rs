@@ -0,0 +1,206 @@
+// Dosya: src/rtp/aec.rs
+use crate::config::AppConfig;
+
+const FRAME_SAMPLES: usize = 160;
+const SAMPLES_PER_MS: usize = 8;
+const STEP_SIZE: f32 = 0.5;
+const REGULARIZATION: f32 = 1e-6;
+// Geigel çift konuşma (double-talk) dedektörü: yakın uç, uzak ucun bu oranını aşarsa
+// arayan konuşuyordur; filtre bozulmasın diye adaptasyon dondurulur.
+const GEIGEL_THRESHOLD: f32 = 0.5;
+const DOUBLE_TALK_HOLD_SAMPLES: usize = 240;
+// Uzak uç bu enerjinin altındaysa (sessizlik) adaptasyon yapılmaz.
+const MIN_FAR_ENERGY: f32 = 1e-5;
+// RTT + jitter buffer (en fazla ~200ms) için üst sınır.
+const MAX_ECHO_DELAY_MS: u32 = 1000;
+
+/// NLMS tabanlı akustik yankı giderici (8 kHz).
+/// Referans olarak gönderilen `tx_frame`'leri, hedef olarak `rx_frame`'leri kullanır.
+/// Yankı yolu ağ RTT'si + jitter buffer beklemesi + cihaz gecikmesidir; `echo_delay` bunun
+/// bilinen kısmıdır, filtre (tail) kalanını kapsar. Gönderilen ses duvar saatine göre
+/// `tx_log`'a yazılır; filtrenin referans geçmişi (`far`) ise yalnızca bir `rx_frame`
+/// işlendiğinde, o frame'e `echo_delay` kadar önce gönderilmiş sesle ilerler. Böylece arayan
+/// sesi gelmeyen tick'ler hizalamayı kaydırmaz.
+#[derive(Debug)]
+pub struct EchoCanceller {
+    weights: Vec<f32>,
+    // Filtre penceresi: son `taps - 1 + frame` hizalanmış referans örneği (en eski başta).
+    far: Vec<f32>,
+    // Duvar saatine göre gönderilen ses; uzunluğu sabit `MAX_ECHO_DELAY + frame`.
+    tx_log: Vec<f32>,
+    echo_delay: usize,
+    double_talk_hold: usize,
+}
+
+impl EchoCanceller {
+    pub fn new(tail_ms: u32, echo_delay_ms: u32) -> Self {
+        let taps = (tail_ms as usize * SAMPLES_PER_MS).max(FRAME_SAMPLES);
+        let mut aec = Self {
+            weights: vec![0.0; taps],
+            far: vec![0.0; taps - 1],
+            tx_log: vec![0.0; MAX_ECHO_DELAY_MS as usize * SAMPLES_PER_MS + FRAME_SAMPLES],
+            echo_delay: FRAME_SAMPLES,
+            double_talk_hold: 0,
+        };
+        aec.set_echo_delay_ms(echo_delay_ms);
+        aec
+    }
+
+    pub fn from_app_config(config: &AppConfig) -> Self {
+        Self::new(config.aec_tail_ms, config.aec_bulk_delay_ms)
+    }
+
+    /// Yankı yolunun bilinen gecikmesini (RTT + jitter buffer derinliği) günceller. Ölçümdeki
+    /// küçük oynamalar referansı kesintiye uğratmasın diye bir frame'den az değişimler yok sayılır.
+    pub fn set_echo_delay_ms(&mut self, delay_ms: u32) {
+        let delay = (delay_ms.min(MAX_ECHO_DELAY_MS) as usize * SAMPLES_PER_MS).max(FRAME_SAMPLES);
+        if delay.abs_diff(self.echo_delay) >= FRAME_SAMPLES {
+            self.echo_delay = delay;
+        }
+    }
+
+    /// Karşı tarafa gönderilen frame'i kaydeder. Her tick'te, egress frame'i kesinleştikten
+    /// sonra (arayan sesi gelsin gelmesin) çağrılmalıdır.
+    pub fn push_reference(&mut self, tx_frame: &[i16]) {
+        self.tx_log
+            .extend(tx_frame.iter().map(|&s| s as f32 / 32768.0));
+        let excess =
+            self.tx_log.len() - (MAX_ECHO_DELAY_MS as usize * SAMPLES_PER_MS + FRAME_SAMPLES);
+        self.tx_log.drain(..excess);
+    }
+
+    /// `rx_frame`'deki yankıyı yerinde temizler.
+    pub fn process(&mut self, rx_frame: &mut [i16]) {
+        let taps = self.weights.len();
+
+        // rx_frame'in ilk örneği, tx_log'un sonundan `echo_delay` örnek önce gönderilmiş sese denk gelir.
+        let start = self.tx_log.len() - self.echo_delay;
+        let aligned = &self.tx_log[start..(start + rx_frame.len()).min(self.tx_log.len())];
+        self.far.extend_from_slice(aligned);
+        self.far.resize(taps - 1 + rx_frame.len(), 0.0);
+
+        for (i, sample) in rx_frame.iter_mut().enumerate() {
+            let window = &self.far[i..i + taps];
+
+            let mut estimate = 0.0f32;
+            let mut energy = 0.0f32;
+            let mut far_peak = 0.0f32;
+            for (w, x) in self.weights.iter().zip(window.iter().rev()) {
+                estimate += w * x;
+                energy += x * x;
+                far_peak = far_peak.max(x.abs());
+            }
+
+            let near = *sample as f32 / 32768.0;
+            let error = near - estimate;
+
+            if near.abs() > GEIGEL_THRESHOLD * far_peak {
+                self.double_talk_hold = DOUBLE_TALK_HOLD_SAMPLES;
+            } else if self.double_talk_hold > 0 {
+                self.double_talk_hold -= 1;
+            }
+
+            if self.double_talk_hold == 0 && energy > MIN_FAR_ENERGY {
+                let step = STEP_SIZE * error / (energy + REGULARIZATION);
+                for (w, x) in self.weights.iter_mut().zip(window.iter().rev()) {
+                    *w += step * x;
+                }
+            }
+
+            *sample = (error * 32768.0).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+
+        let consumed = rx_frame.len();
+        self.far.drain(..consumed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn energy(frame: &[i16]) -> f64 {
+        frame.iter().map(|&s| (s as f64) * (s as f64)).sum()
+    }
+
+    #[test]
+    fn test_cancels_delayed_echo() {
+        let mut aec = EchoCanceller::new(32, 40);
+        let mut seed: u32 = 12345;
+        let mut far_signal = Vec::new();
+        for _ in 0..8000 * 4 {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            far_signal.push(((seed >> 16) as i16 as i32 / 4) as i16);
+        }
+
+        // Yankı: 50ms gecikmeli, -6 dB zayıflamış uzak uç sesi.
+        let delay = 400;
+        let mut early = 0.0;
+        let mut late = 0.0;
+        for (n, tx_frame) in far_signal.chunks(160).enumerate() {
+            let start = n * 160;
+            let mut rx_frame: Vec<i16> = (start..start + 160)
+                .map(|t| {
+                    if t >= delay {
+                        far_signal[t - delay] / 2
+                    } else {
+                        0
+                    }
+                })
+                .collect();
+            let echo = energy(&rx_frame);
+            aec.process(&mut rx_frame);
+            aec.push_reference(tx_frame);
+
+            if (10..20).contains(&n) {
+                early += energy(&rx_frame) / echo.max(1.0);
+            }
+            if n >= 180 {
+                late += energy(&rx_frame) / echo.max(1.0);
+            }
+        }
+        let late = late / 20.0;
+        assert!(late < early / 10.0);
+        // 20 dB'den fazla yankı bastırma (ERLE).
+        assert!(late < 0.01, "residual ratio {late}");
+    }
+
+    #[test]
+    fn test_stalled_ingress_keeps_alignment() {
+        let mut aec = EchoCanceller::new(32, 40);
+        let mut seed: u32 = 777;
+        let far_signal: Vec<i16> = (0..8000 * 5)
+            .map(|_| {
+                seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                ((seed >> 16) as i16 as i32 / 4) as i16
+            })
+            .collect();
+
+        // 150. tick'ten sonra 5 tick arayan sesi gelmez; jitter buffer 100ms derinleşir ve
+        // sonraki frame'lerdeki yankı duvar saatine göre 100ms daha gecikmeli olur.
+        let mut lag = 400;
+        let mut residual = 0.0;
+        for (n, tx_frame) in far_signal.chunks(160).enumerate() {
+            if (150..155).contains(&n) {
+                aec.push_reference(tx_frame);
+                continue;
+            }
+            if n == 155 {
+                lag += 800;
+                aec.set_echo_delay_ms(40 + 100);
+            }
+            let start = n * 160;
+            let mut rx_frame: Vec<i16> = (start..start + 160)
+                .map(|t| t.checked_sub(lag).map_or(0, |t| far_signal[t] / 2))
+                .collect();
+            let echo = energy(&rx_frame);
+            aec.process(&mut rx_frame);
+            aec.push_reference(tx_frame);
+            if n >= 165 {
+                residual += energy(&rx_frame) / echo.max(1.0);
+            }
+        }
+        let residual = residual / 85.0;
+        assert!(residual < 0.01, "residual ratio {residual}");
+    }
+}
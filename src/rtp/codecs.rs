@@ -35,6 +35,15 @@ impl AudioCodec {
     pub fn to_payload_type(&self) -> u8 {
         self.to_core_type() as u8
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioCodec::G729 => "G729",
+            AudioCodec::Pcmu => "PCMU",
+            AudioCodec::Pcma => "PCMA",
+            AudioCodec::TelephoneEvent => "telephone-event",
+        }
+    }
 }
 
 // [KALDIRILDI]: decode_rtp_to_lpcm16 ve decode_rtp_native_8k fonksiyonları kaldırıldı.
@@ -1,19 +1,19 @@
 // Dosya: src/rtp/command.rs
+use crate::rtp::live_stream::{
+    LiveStreamChannel, LiveStreamEncoding, LiveStreamFrameTags, LiveStreamHub,
+};
+use crate::rtp::recording::RecordingPipeline;
+use crate::rtp::resampler::Resampler;
 use anyhow::Result;
 use bytes::Bytes;
 use hound::WavSpec;
 use std::net::SocketAddr;
+use std::time::{Duration, SystemTime};
 use tokio::sync::{mpsc, oneshot};
 use tokio_util::sync::CancellationToken;
 use tonic::Status;
 
-pub type SharedLiveStreamSender = std::sync::Arc<
-    tokio::sync::Mutex<
-        std::option::Option<
-            tokio::sync::mpsc::Sender<std::result::Result<AudioFrame, tonic::Status>>,
-        >,
-    >,
->;
+pub type SharedLiveStreams = std::sync::Arc<tokio::sync::Mutex<LiveStreamHub>>;
 
 #[derive(Debug)]
 pub struct AudioFrame {
@@ -21,15 +21,175 @@ pub struct AudioFrame {
     pub media_type: String,
 }
 
+/// Kayıt zaman çizelgesinde (ms) duraklatılmış aralık; devam edilene kadar `end_ms` boştur.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordingPause {
+    pub start_ms: u64,
+    pub end_ms: Option<u64>,
+}
+
+/// Kayıt metadatası için çağrının QoS özeti (kümülatif, son ölçüm).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RecordingQos {
+    pub expected: u64,
+    pub received: u64,
+    pub lost: u64,
+    pub loss_pct: f64,
+    pub jitter_ms: f64,
+    pub out_of_order: u64,
+    pub duplicates: u64,
+    pub rtt_ms: Option<f64>,
+}
+
 #[derive(Debug)]
 pub struct RecordingSession {
     pub output_uri: String,
     pub spec: WavSpec,
-    pub rx_buffer: Vec<i16>,
-    pub tx_buffer: Vec<i16>,
+    pub pipeline: RecordingPipeline,
+    /// Kanal başına yazılmış (8 kHz) örnek sayısı.
+    pub samples_written: usize,
     pub call_id: String,
     pub trace_id: String,
     pub max_reached_warned: bool,
+    pub pauses: Vec<RecordingPause>,
+    pub started_at: SystemTime,
+    /// Kayıt boyunca hatta görülen kodekler (görülme sırasıyla).
+    pub wire_codecs: Vec<&'static str>,
+    pub qos: Option<RecordingQos>,
+    resume_requested: bool,
+    // Devam emrinden sonra hâlâ susturulacak örnek sayısı (ilk yazımda hesaplanır).
+    resume_guard: Option<usize>,
+    // Mono kayıtta karışık ses, stereo kayıtta sol (arayan) kanal için.
+    rx_resampler: Resampler,
+    tx_resampler: Resampler,
+}
+
+impl RecordingSession {
+    pub fn new(
+        output_uri: String,
+        spec: WavSpec,
+        pipeline: RecordingPipeline,
+        call_id: String,
+        trace_id: String,
+    ) -> Self {
+        Self {
+            output_uri,
+            spec,
+            pipeline,
+            samples_written: 0,
+            call_id,
+            trace_id,
+            max_reached_warned: false,
+            pauses: Vec::new(),
+            started_at: SystemTime::now(),
+            wire_codecs: Vec::new(),
+            qos: None,
+            resume_requested: false,
+            resume_guard: None,
+            rx_resampler: Resampler::new(8000, spec.sample_rate),
+            tx_resampler: Resampler::new(8000, spec.sample_rate),
+        }
+    }
+
+    /// Kaydı oturuma bağlar. Çağrıda tek kayıt olur: çalışan kayıt varsa yenisi reddedilir ve
+    /// atılır (henüz ses almadığından hedefe bir şey yazmaz); ilk kaydın bekleyen sesi, olayı ve
+    /// varsayılan anahtarı korunur.
+    pub fn install(self, slot: &mut Option<Self>) -> bool {
+        if slot.is_some() {
+            return false;
+        }
+        *slot = Some(self);
+        true
+    }
+
+    pub fn note_wire_codec(&mut self, codec: &'static str) {
+        if !self.wire_codecs.contains(&codec) {
+            self.wire_codecs.push(codec);
+        }
+    }
+
+    pub fn position_ms(&self) -> u64 {
+        self.samples_written as u64 / 8
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.pauses.last().is_some_and(|p| p.end_ms.is_none())
+    }
+
+    /// Durum değiştiyse `true` döner. Bekleyen bir devam emrini de iptal eder.
+    pub fn pause(&mut self) -> bool {
+        if self.is_paused() {
+            let cancelled = self.resume_requested;
+            self.resume_requested = false;
+            self.resume_guard = None;
+            return cancelled;
+        }
+        self.pauses.push(RecordingPause {
+            start_ms: self.position_ms(),
+            end_ms: None,
+        });
+        true
+    }
+
+    /// Ses, devam emrinden önce yakalanmış arayan sesi (jitter buffer'da bekleyen) tükenince
+    /// geri gelir; böylece duraklatma sırasında tuşlanan DTMF tonları kayda sızmaz.
+    pub fn resume(&mut self) -> bool {
+        if !self.is_paused() || self.resume_requested {
+            return false;
+        }
+        self.resume_requested = true;
+        true
+    }
+
+    /// Açık kalan duraklatmayı kaydın sonunda kapatır.
+    pub fn close_pauses(&mut self) {
+        let end = self.position_ms();
+        if let Some(pause) = self.pauses.last_mut().filter(|p| p.end_ms.is_none()) {
+            pause.end_ms = Some(end);
+        }
+    }
+
+    /// Bir tick'i `spec`'e göre kayda ekler: stereo (sol: arayan, sağ: bot) veya mono karışım.
+    /// `ingress_backlog`: bu frame'den sonra kayda ulaşacak arayan sesi (örnek): jitter buffer ve
+    /// kuyrukta bekleyenler ile DSP (gürültü bastırma) gecikmesi.
+    /// Duraklatılmışken iki kanal da sessizlik olarak yazılır; zaman çizelgesi korunur.
+    pub fn write_frame(&mut self, rx_frame: &[i16], tx_frame: &[i16], ingress_backlog: usize) {
+        if self.resume_requested && self.resume_guard.is_none() {
+            self.resume_guard = Some(ingress_backlog + rx_frame.len());
+        }
+        match self.resume_guard {
+            Some(0) => {
+                self.close_pauses();
+                self.resume_requested = false;
+                self.resume_guard = None;
+            }
+            Some(left) => self.resume_guard = Some(left.saturating_sub(rx_frame.len())),
+            None => {}
+        }
+        let silence;
+        let (rx_frame, tx_frame) = if self.is_paused() {
+            silence = vec![0i16; rx_frame.len()];
+            (silence.as_slice(), silence.as_slice())
+        } else {
+            (rx_frame, tx_frame)
+        };
+
+        self.samples_written += rx_frame.len();
+        if self.spec.channels == 1 {
+            let mixed: Vec<i16> = rx_frame
+                .iter()
+                .zip(tx_frame)
+                .map(|(rx, tx)| rx.saturating_add(*tx))
+                .collect();
+            let mixed = self.rx_resampler.process(&mixed);
+            self.pipeline.push(&mixed);
+        } else {
+            let rx = self.rx_resampler.process(rx_frame);
+            let tx = self.tx_resampler.process(tx_frame);
+            let interleaved: Vec<i16> = rx.iter().zip(&tx).flat_map(|(l, r)| [*l, *r]).collect();
+            self.pipeline.push(&interleaved);
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -42,18 +202,161 @@ pub enum RtpCommand {
     },
     StopAudio,
     StartLiveAudioStream {
+        subscriber_id: String,
         stream_sender: mpsc::Sender<Result<AudioFrame, Status>>,
         target_sample_rate: Option<u32>,
+        channel: LiveStreamChannel,
+        encoding: LiveStreamEncoding,
+        frame_tags: LiveStreamFrameTags,
+    },
+    /// `None`: tüm aboneler.
+    StopLiveAudioStream {
+        subscriber_id: Option<String>,
     },
-    StopLiveAudioStream,
     EnableEchoTest,
     DisableEchoTest,
+    EnableBargeIn {
+        mode: crate::rtp::session_handlers::BargeInMode,
+    },
+    DisableBargeIn,
+    EnableEchoCancellation,
+    DisableEchoCancellation,
+    /// `None` olan ayar değiştirilmez.
+    SetIngressDsp {
+        noise_suppression: Option<bool>,
+        agc: Option<bool>,
+    },
+    SendDtmf {
+        digits: String,
+        tone_duration: Duration,
+        inter_digit_gap: Duration,
+        mode: crate::rtp::dtmf::DtmfSendMode,
+    },
     SetTargetAddress {
         target: SocketAddr,
     },
-    StartPermanentRecording(RecordingSession),
+    /// Yanıt: aktif bir kayıt varsa hata.
+    StartPermanentRecording {
+        session: RecordingSession,
+        responder: oneshot::Sender<Result<(), String>>,
+    },
     StopPermanentRecording {
         responder: oneshot::Sender<Result<String, String>>,
     },
+    /// Kaydı bitirmeden duraklatır/sürdürür. Yanıt: durum değişti mi; aktif kayıt yoksa hata.
+    SetRecordingPaused {
+        paused: bool,
+        responder: oneshot::Sender<Result<bool, String>>,
+    },
     Shutdown,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtp::recording::RecordingFormat;
+    use crate::rtp::store::MemoryStore;
+    use crate::rtp::writers::RecordingTarget;
+    use hound::SampleFormat;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_pause_mutes_until_ingress_backlog_drains() {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let store = Arc::new(MemoryStore::new());
+        let target = RecordingTarget::new(store.clone(), "kayit.wav");
+        let pipeline = RecordingPipeline::start(
+            target,
+            RecordingFormat::Wav,
+            spec,
+            "test".into(),
+            None,
+            None,
+        )
+        .unwrap();
+        let mut rec =
+            RecordingSession::new(String::new(), spec, pipeline, "test".into(), "t".into());
+
+        let tone = vec![1000i16; 160];
+        let silence = vec![0i16; 160];
+        for _ in 0..10 {
+            rec.write_frame(&tone, &silence, 0);
+        }
+        assert!(rec.pause());
+        assert!(!rec.pause());
+        for _ in 0..10 {
+            rec.write_frame(&tone, &tone, 320);
+        }
+        assert!(rec.resume());
+        // Devam anında tamponda bekleyen 2 frame + mevcut frame hâlâ duraklatma aralığındadır.
+        for _ in 0..10 {
+            rec.write_frame(&tone, &silence, 320);
+        }
+        assert_eq!(
+            rec.pauses,
+            vec![RecordingPause {
+                start_ms: 200,
+                end_ms: Some(460)
+            }]
+        );
+
+        rec.pipeline.finish().await.unwrap();
+        let body = store.object("kayit.wav").unwrap().body;
+        let samples: Vec<i16> = hound::WavReader::new(std::io::Cursor::new(body))
+            .unwrap()
+            .samples::<i16>()
+            .map(|s| s.unwrap())
+            .collect();
+        assert!(samples[..1600].iter().all(|&s| s == 1000));
+        assert!(samples[1600..3680].iter().all(|&s| s == 0));
+        assert!(samples[3680..].iter().all(|&s| s == 1000));
+    }
+
+    #[tokio::test]
+    async fn test_second_recording_is_rejected_and_first_survives() {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let store = Arc::new(MemoryStore::new());
+        let start = |key: &str| {
+            let pipeline = RecordingPipeline::start(
+                RecordingTarget::new(store.clone(), key.to_string()),
+                RecordingFormat::Wav,
+                spec,
+                "test".into(),
+                None,
+                None,
+            )
+            .unwrap();
+            RecordingSession::new(key.to_string(), spec, pipeline, "test".into(), "t".into())
+        };
+
+        let mut slot = None;
+        assert!(start("ilk.wav").install(&mut slot));
+        let tone = vec![1000i16; 160];
+        for _ in 0..100 {
+            slot.as_mut().unwrap().write_frame(&tone, &tone, 0);
+        }
+        assert!(!start("ikinci.wav").install(&mut slot));
+        for _ in 0..100 {
+            slot.as_mut().unwrap().write_frame(&tone, &tone, 0);
+        }
+
+        let rec = slot.take().unwrap();
+        assert_eq!(rec.output_uri, "ilk.wav");
+        rec.pipeline.finish().await.unwrap();
+        tokio::task::yield_now().await;
+        assert_eq!(store.keys(), ["ilk.wav"]);
+        let body = store.object("ilk.wav").unwrap().body;
+        let reader = hound::WavReader::new(std::io::Cursor::new(body)).unwrap();
+        assert_eq!(reader.duration(), 200 * 160);
+    }
+}
@@ -0,0 +1,343 @@
+// Dosya: src/rtp/dsp.rs
+use crate::config::AppConfig;
+use std::collections::VecDeque;
+use std::f32::consts::PI;
+
+const FFT_SIZE: usize = 256;
+const HOP: usize = FFT_SIZE / 2;
+const BINS: usize = FFT_SIZE / 2 + 1;
+// Decision-directed a-priori SNR yumuşatması (Ephraim-Malah).
+const DD_ALPHA: f32 = 0.98;
+// Gürültü tahmini, yumuşatılmış periodogramın minimumunu izler: daha sessiz bir blokta
+// hemen düşer, aksi halde en fazla ~2.7 dB/sn yükselir (uzun ünlüler gürültü sanılmasın).
+// Minimum takibi düşük tahmin ettiği için düzeltilir.
+const PSD_SMOOTHING: f32 = 0.7;
+const NOISE_RISE: f32 = 1.01;
+const NOISE_FLOOR_PSD: f32 = 1e-10;
+const NOISE_BIAS: f32 = 1.5;
+const NOISE_INIT_BLOCKS: u32 = 8;
+
+const AGC_NOISE_GATE_DBFS: f32 = -50.0;
+const AGC_LEVEL_SMOOTHING: f32 = 0.1;
+const AGC_ATTACK: f32 = 0.3;
+const AGC_RELEASE: f32 = 0.05;
+const LIMITER_THRESHOLD: f32 = 0.89; // ~ -1 dBFS
+const LIMITER_RELEASE_MS: f32 = 50.0;
+
+/// Sıralı, yerinde (in-place) radix-2 karmaşık FFT. `inverse` ise 1/N ölçeklemesi yapılmaz.
+fn fft(re: &mut [f32], im: &mut [f32], inverse: bool) {
+    let n = re.len();
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * PI / len as f32;
+        for start in (0..n).step_by(len) {
+            for k in 0..len / 2 {
+                let (sin, cos) = (angle * k as f32).sin_cos();
+                let a = start + k;
+                let b = a + len / 2;
+                let tr = re[b] * cos - im[b] * sin;
+                let ti = re[b] * sin + im[b] * cos;
+                re[b] = re[a] - tr;
+                im[b] = im[a] - ti;
+                re[a] += tr;
+                im[a] += ti;
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// STFT (256 nokta, %50 örtüşme, sqrt-Hann) üzerinde Wiener kazançlı spektral gürültü bastırıcı.
+/// Blok işleme nedeniyle sese sabit 32ms gecikme ekler.
+#[derive(Debug)]
+pub struct NoiseSuppressor {
+    window: Vec<f32>,
+    input: Vec<f32>,
+    overlap: Vec<f32>,
+    output: VecDeque<f32>,
+    smoothed_psd: Vec<f32>,
+    noise_psd: Vec<f32>,
+    prev_gain: Vec<f32>,
+    prev_snr_post: Vec<f32>,
+    min_gain: f32,
+    blocks: u32,
+}
+
+impl NoiseSuppressor {
+    /// `max_attenuation_db`: bir frekans bandına uygulanabilecek en fazla bastırma (pozitif dB).
+    pub fn new(max_attenuation_db: f32) -> Self {
+        let window = (0..FFT_SIZE)
+            .map(|n| (0.5 - 0.5 * (2.0 * PI * n as f32 / FFT_SIZE as f32).cos()).sqrt())
+            .collect();
+        Self {
+            window,
+            input: vec![0.0; FFT_SIZE - HOP],
+            overlap: vec![0.0; FFT_SIZE],
+            // Her çağrıda istenen kadar çıkış hazır olsun diye bir hop'luk ön dolgu.
+            output: std::iter::repeat_n(0.0, HOP).collect(),
+            smoothed_psd: vec![0.0; BINS],
+            noise_psd: vec![0.0; BINS],
+            prev_gain: vec![1.0; BINS],
+            prev_snr_post: vec![1.0; BINS],
+            min_gain: 10f32.powf(-max_attenuation_db.abs() / 20.0),
+            blocks: 0,
+        }
+    }
+
+    pub fn from_app_config(config: &AppConfig) -> Self {
+        Self::new(config.ns_max_attenuation_db)
+    }
+
+    /// Girişteki bir örneğin çıkışta görünmesine kadar geçen süre (örnek): 32ms.
+    pub fn delay_samples(&self) -> usize {
+        FFT_SIZE
+    }
+
+    pub fn process(&mut self, frame: &mut [i16]) {
+        for &s in frame.iter() {
+            self.input.push(s as f32 / 32768.0);
+            if self.input.len() == FFT_SIZE {
+                self.process_block();
+                self.input.drain(..HOP);
+            }
+        }
+        for s in frame.iter_mut() {
+            let v = self.output.pop_front().unwrap_or(0.0);
+            *s = (v * 32768.0).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+    }
+
+    fn process_block(&mut self) {
+        let mut re: Vec<f32> = self
+            .input
+            .iter()
+            .zip(&self.window)
+            .map(|(x, w)| x * w)
+            .collect();
+        let mut im = vec![0.0f32; FFT_SIZE];
+        fft(&mut re, &mut im, false);
+
+        self.blocks = self.blocks.saturating_add(1);
+        for k in 0..BINS {
+            let power = re[k] * re[k] + im[k] * im[k];
+
+            if self.blocks <= NOISE_INIT_BLOCKS {
+                // İlk blokların ortalaması başlangıç gürültü tahminidir.
+                self.smoothed_psd[k] += (power - self.smoothed_psd[k]) / self.blocks as f32;
+                self.noise_psd[k] = self.smoothed_psd[k] / NOISE_BIAS;
+            } else {
+                let smoothed = PSD_SMOOTHING * self.smoothed_psd[k] + (1.0 - PSD_SMOOTHING) * power;
+                self.smoothed_psd[k] = smoothed;
+                self.noise_psd[k] =
+                    (self.noise_psd[k].max(NOISE_FLOOR_PSD) * NOISE_RISE).min(smoothed);
+            }
+
+            let noise = (self.noise_psd[k] * NOISE_BIAS).max(NOISE_FLOOR_PSD);
+            let snr_post = power / noise;
+            let snr_prio = DD_ALPHA * self.prev_gain[k] * self.prev_gain[k] * self.prev_snr_post[k]
+                + (1.0 - DD_ALPHA) * (snr_post - 1.0).max(0.0);
+            let gain = (snr_prio / (1.0 + snr_prio)).max(self.min_gain);
+            self.prev_gain[k] = gain;
+            self.prev_snr_post[k] = snr_post;
+
+            re[k] *= gain;
+            im[k] *= gain;
+            // Gerçek sinyal: negatif frekanslar eşlenik simetriktir.
+            if k > 0 && k < FFT_SIZE / 2 {
+                re[FFT_SIZE - k] = re[k];
+                im[FFT_SIZE - k] = -im[k];
+            }
+        }
+
+        fft(&mut re, &mut im, true);
+        for ((acc, x), w) in self.overlap.iter_mut().zip(&re).zip(&self.window) {
+            *acc += x / FFT_SIZE as f32 * w;
+        }
+        self.output.extend(self.overlap.drain(..HOP));
+        self.overlap.resize(FFT_SIZE, 0.0);
+    }
+}
+
+/// Konuşma seviyesini hedef dBFS'e çeken AGC ve kırpılmayı önleyen tepe sınırlayıcı (limiter).
+#[derive(Debug)]
+pub struct AutomaticGainControl {
+    target_dbfs: f32,
+    max_gain_db: f32,
+    level_dbfs: Option<f32>,
+    gain_db: f32,
+    limiter_envelope: f32,
+    limiter_release: f32,
+}
+
+impl AutomaticGainControl {
+    pub fn new(target_dbfs: f32, max_gain_db: f32) -> Self {
+        Self {
+            target_dbfs,
+            max_gain_db,
+            level_dbfs: None,
+            gain_db: 0.0,
+            limiter_envelope: 0.0,
+            limiter_release: (-1.0 / (LIMITER_RELEASE_MS * 8.0)).exp(),
+        }
+    }
+
+    pub fn from_app_config(config: &AppConfig) -> Self {
+        Self::new(config.agc_target_dbfs, config.agc_max_gain_db)
+    }
+
+    pub fn process(&mut self, frame: &mut [i16]) {
+        if frame.is_empty() {
+            return;
+        }
+        let mean_sq = frame
+            .iter()
+            .map(|&s| (s as f32 / 32768.0).powi(2))
+            .sum::<f32>()
+            / frame.len() as f32;
+        let frame_dbfs = 10.0 * mean_sq.max(1e-10).log10();
+
+        // Seviye sadece konuşma/sinyal varken güncellenir; sessizlikte gürültü yükseltilmez.
+        if frame_dbfs > AGC_NOISE_GATE_DBFS {
+            let level = match self.level_dbfs {
+                Some(level) => level + (frame_dbfs - level) * AGC_LEVEL_SMOOTHING,
+                None => frame_dbfs,
+            };
+            self.level_dbfs = Some(level);
+        }
+
+        let previous_gain = 10f32.powf(self.gain_db / 20.0);
+        if let Some(level) = self.level_dbfs {
+            let wanted = (self.target_dbfs - level).min(self.max_gain_db);
+            let rate = if wanted < self.gain_db {
+                AGC_ATTACK
+            } else {
+                AGC_RELEASE
+            };
+            self.gain_db += (wanted - self.gain_db) * rate;
+        }
+        let gain = 10f32.powf(self.gain_db / 20.0);
+
+        let len = frame.len() as f32;
+        for (n, s) in frame.iter_mut().enumerate() {
+            // Kazanç frame boyunca doğrusal geçer (zipper gürültüsü olmasın).
+            let g = previous_gain + (gain - previous_gain) * (n as f32 + 1.0) / len;
+            let x = *s as f32 / 32768.0 * g;
+
+            let peak = x.abs();
+            // Anlık atak: zarf her zaman örneğin mutlak değerinden büyük/eşit kalır.
+            self.limiter_envelope = peak.max(self.limiter_envelope * self.limiter_release);
+            let limited = if self.limiter_envelope > LIMITER_THRESHOLD {
+                x * LIMITER_THRESHOLD / self.limiter_envelope
+            } else {
+                x
+            };
+            *s = (limited * 32768.0).clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rms_dbfs(samples: &[i16]) -> f32 {
+        let mean_sq = samples
+            .iter()
+            .map(|&s| (s as f32 / 32768.0).powi(2))
+            .sum::<f32>()
+            / samples.len() as f32;
+        10.0 * mean_sq.max(1e-10).log10()
+    }
+
+    fn tone(offset: usize, amplitude: f32) -> Vec<i16> {
+        (offset..offset + 160)
+            .map(|n| (amplitude * (2.0 * PI * 440.0 * n as f32 / 8000.0).sin()) as i16)
+            .collect()
+    }
+
+    #[test]
+    fn test_agc_reaches_target_and_limits() {
+        let mut agc = AutomaticGainControl::new(-18.0, 30.0);
+        let mut last = Vec::new();
+        for i in 0..200 {
+            last = tone(i * 160, 800.0); // ~ -35 dBFS
+            agc.process(&mut last);
+        }
+        assert!((rms_dbfs(&last) + 18.0).abs() < 1.5);
+
+        // Ani yüksek seviye kırpılmadan sınırlandırılır.
+        let mut loud = tone(0, 32000.0);
+        agc.process(&mut loud);
+        let peak = loud.iter().map(|s| (*s as i32).abs()).max().unwrap();
+        assert!(peak <= (LIMITER_THRESHOLD * 32768.0) as i32 + 1);
+    }
+
+    #[test]
+    fn test_noise_suppression_attenuates_stationary_noise() {
+        let mut ns = NoiseSuppressor::new(18.0);
+        let mut seed: u32 = 7;
+        let mut noise_frame = || -> Vec<i16> {
+            (0..160)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    ((seed >> 16) as i16) / 64
+                })
+                .collect()
+        };
+
+        let mut input_energy = 0.0;
+        let mut output_energy = 0.0;
+        for i in 0..300 {
+            let mut frame = noise_frame();
+            if i >= 200 {
+                input_energy += rms_dbfs(&frame);
+            }
+            ns.process(&mut frame);
+            if i >= 200 {
+                output_energy += rms_dbfs(&frame);
+            }
+        }
+        // Durağan gürültü en az 10 dB bastırılmalı.
+        assert!((input_energy - output_energy) / 100.0 > 10.0);
+    }
+
+    #[test]
+    fn test_noise_suppression_delay_matches_reported_latency() {
+        // 200 ms sessizlikten sonra başlayan ton, çıkışta `delay_samples` kadar geç belirir.
+        let mut ns = NoiseSuppressor::new(18.0);
+        let onset = 1600;
+        let mut output = Vec::new();
+        for i in 0..20 {
+            let mut frame: Vec<i16> = (i * 160..(i + 1) * 160)
+                .map(|n| match n >= onset {
+                    true => (8000.0 * (2.0 * PI * n as f32 / 8.0).cos()) as i16,
+                    false => 0,
+                })
+                .collect();
+            ns.process(&mut frame);
+            output.extend(frame);
+        }
+        let first = output.iter().position(|s| s.abs() > 100).unwrap();
+        let delay = ns.delay_samples();
+        assert!(
+            first + HOP / 2 >= onset + delay && first <= onset + delay,
+            "{}",
+            first
+        );
+    }
+}
@@ -0,0 +1,605 @@
+// Dosya: src/rtp/dtmf.rs
+use std::f32::consts::PI;
+
+const SAMPLE_RATE: f32 = 8000.0;
+const ROW_FREQS: [f32; 4] = [697.0, 770.0, 852.0, 941.0];
+const COL_FREQS: [f32; 4] = [1209.0, 1336.0, 1477.0, 1633.0];
+const KEYPAD: [[char; 4]; 4] = [
+    ['1', '2', '3', 'A'],
+    ['4', '5', '6', 'B'],
+    ['7', '8', '9', 'C'],
+    ['*', '0', '#', 'D'],
+];
+
+// In-band dedektör eşikleri (160 örneklik / 20ms bloklar için).
+const MIN_FRAME_ENERGY: f32 = 1.0e6; // ~ -45 dBFS altı sessizlik sayılır
+const MIN_TONE_RATIO: f32 = 0.6; // iki tonun toplam enerjiye oranı
+const MAX_TWIST_DB: f32 = 8.0;
+const MIN_TONE_FRAMES: u32 = 2; // 40ms
+const MIN_GAP_FRAMES: u32 = 2;
+/// Bu kadar (RTP saatinde 10 sn) eski zaman damgalı olay paketi gecikmiş sayılır; daha eskisi
+/// yeniden başlayan akış kabul edilir.
+const LATE_EVENT_WINDOW: u32 = 8000 * 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtmfSource {
+    Rfc4733,
+    InBand,
+}
+
+impl DtmfSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DtmfSource::Rfc4733 => "rfc4733",
+            DtmfSource::InBand => "inband",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DtmfDigit {
+    pub digit: char,
+    pub duration_ms: u32,
+    pub source: DtmfSource,
+}
+
+/// RFC 4733 2.3: telephone-event payload (4 bayt).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TelephoneEvent {
+    pub event: u8,
+    pub end: bool,
+    pub volume: u8,
+    pub duration: u16,
+}
+
+impl TelephoneEvent {
+    pub fn parse(payload: &[u8]) -> Option<Self> {
+        if payload.len() < 4 {
+            return None;
+        }
+        Some(Self {
+            event: payload[0],
+            end: payload[1] & 0x80 != 0,
+            volume: payload[1] & 0x3F,
+            duration: u16::from_be_bytes([payload[2], payload[3]]),
+        })
+    }
+
+    pub fn to_bytes(&self) -> [u8; 4] {
+        let d = self.duration.to_be_bytes();
+        [
+            self.event,
+            ((self.end as u8) << 7) | (self.volume & 0x3F),
+            d[0],
+            d[1],
+        ]
+    }
+}
+
+pub fn event_to_digit(event: u8) -> Option<char> {
+    match event {
+        0..=9 => Some((b'0' + event) as char),
+        10 => Some('*'),
+        11 => Some('#'),
+        12..=15 => Some((b'A' + event - 12) as char),
+        _ => None,
+    }
+}
+
+pub fn digit_to_event(digit: char) -> Option<u8> {
+    match digit.to_ascii_uppercase() {
+        d @ '0'..='9' => Some(d as u8 - b'0'),
+        '*' => Some(10),
+        '#' => Some(11),
+        d @ 'A'..='D' => Some(d as u8 - b'A' + 12),
+        _ => None,
+    }
+}
+
+/// RFC 4733 olaylarını tekilleştirir. Bir olay aynı RTP zaman damgasını taşıyan
+/// birden çok paketten (ve tekrar eden 3 "end" paketinden) oluşur; her olay bir kez raporlanır.
+#[derive(Debug, Default)]
+pub struct Rfc4733Detector {
+    current: Option<(u32, TelephoneEvent)>,
+    last_reported_ts: Option<u32>,
+}
+
+impl Rfc4733Detector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn on_packet(&mut self, timestamp: u32, payload: &[u8]) -> Vec<DtmfDigit> {
+        let mut out = Vec::new();
+        let Some(event) = TelephoneEvent::parse(payload) else {
+            return out;
+        };
+
+        if self.last_reported_ts == Some(timestamp) {
+            // Tekrar eden "end" paketi veya gecikmiş ara paket.
+            return out;
+        }
+        // Önceki bir olaydan gecikerek gelen paket (sıra dışı başlangıç, geç kalan "end"):
+        // eski rakamı yeniden raporlamaz, süren olayı da erken kapatmaz.
+        let newest = self.current.map(|(ts, _)| ts).or(self.last_reported_ts);
+        if newest.is_some_and(|newest| is_older(timestamp, newest)) {
+            return out;
+        }
+
+        // Önceki olayın "end" paketleri kaybolduysa yeni olay başlarken onu kapat.
+        if let Some((ts, prev)) = self.current {
+            if ts != timestamp {
+                out.extend(Self::report(prev));
+                self.last_reported_ts = Some(ts);
+                self.current = None;
+            }
+        }
+
+        if event.end {
+            out.extend(Self::report(event));
+            self.last_reported_ts = Some(timestamp);
+            self.current = None;
+        } else {
+            self.current = Some((timestamp, event));
+        }
+        out
+    }
+
+    fn report(event: TelephoneEvent) -> Option<DtmfDigit> {
+        event_to_digit(event.event).map(|digit| DtmfDigit {
+            digit,
+            duration_ms: event.duration as u32 / 8,
+            source: DtmfSource::Rfc4733,
+        })
+    }
+}
+
+/// RFC 1982 seri karşılaştırma: `ts`, `reference`'tan en çok `LATE_EVENT_WINDOW` kadar eski mi.
+fn is_older(ts: u32, reference: u32) -> bool {
+    (1..=LATE_EVENT_WINDOW).contains(&reference.wrapping_sub(ts))
+}
+
+fn goertzel_power(samples: &[f32], freq: f32) -> f32 {
+    let coeff = 2.0 * (2.0 * PI * freq / SAMPLE_RATE).cos();
+    let (mut s1, mut s2) = (0.0f32, 0.0f32);
+    for &x in samples {
+        let s0 = x + coeff * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+    s1 * s1 + s2 * s2 - coeff * s1 * s2
+}
+
+fn strongest(powers: &[f32; 4]) -> (usize, f32, f32) {
+    let mut best = 0;
+    for i in 1..4 {
+        if powers[i] > powers[best] {
+            best = i;
+        }
+    }
+    let second = powers
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != best)
+        .map(|(_, p)| *p)
+        .fold(0.0f32, f32::max);
+    (best, powers[best], second)
+}
+
+/// Çözülmüş 8 kHz PCM üzerinde Goertzel tabanlı in-band DTMF dedektörü.
+/// Ton bittiğinde (süresiyle birlikte) raporlar.
+#[derive(Debug, Default)]
+pub struct InbandDetector {
+    candidate: Option<char>,
+    candidate_frames: u32,
+    active: Option<char>,
+    active_frames: u32,
+    gap_frames: u32,
+}
+
+impl InbandDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn classify(frame: &[i16]) -> Option<char> {
+        let samples: Vec<f32> = frame.iter().map(|&s| s as f32).collect();
+        let energy: f32 = samples.iter().map(|s| s * s).sum();
+        if energy < MIN_FRAME_ENERGY * samples.len() as f32 / 160.0 {
+            return None;
+        }
+
+        let rows = ROW_FREQS.map(|f| goertzel_power(&samples, f));
+        let cols = COL_FREQS.map(|f| goertzel_power(&samples, f));
+        let (row, row_p, row_second) = strongest(&rows);
+        let (col, col_p, col_second) = strongest(&cols);
+
+        // Her grupta baskın ton diğerlerinden en az ~6 dB güçlü olmalı.
+        if row_p < row_second * 4.0 || col_p < col_second * 4.0 {
+            return None;
+        }
+
+        let twist_db = 10.0 * (row_p / col_p).log10();
+        if twist_db.abs() > MAX_TWIST_DB {
+            return None;
+        }
+
+        // Goertzel gücü ~ (A*N/2)^2; toplam enerji ~ A^2*N/2. Oranı N/2 ile normalize et.
+        let tone_energy = (row_p + col_p) * 2.0 / samples.len() as f32;
+        if tone_energy < energy * MIN_TONE_RATIO {
+            return None;
+        }
+
+        Some(KEYPAD[row][col])
+    }
+
+    pub fn process(&mut self, frame: &[i16]) -> Option<DtmfDigit> {
+        let detected = Self::classify(frame);
+        let frame_ms = (frame.len() as u32 * 1000) / SAMPLE_RATE as u32;
+
+        if let Some(active) = self.active {
+            if detected == Some(active) {
+                self.active_frames += 1;
+                self.gap_frames = 0;
+                return None;
+            }
+            self.gap_frames += 1;
+            if self.gap_frames < MIN_GAP_FRAMES {
+                return None;
+            }
+            self.active = None;
+            self.candidate = None;
+            self.candidate_frames = 0;
+            return Some(DtmfDigit {
+                digit: active,
+                duration_ms: self.active_frames * frame_ms,
+                source: DtmfSource::InBand,
+            });
+        }
+
+        match detected {
+            Some(d) if self.candidate == Some(d) => {
+                self.candidate_frames += 1;
+                if self.candidate_frames >= MIN_TONE_FRAMES {
+                    self.active = Some(d);
+                    self.active_frames = self.candidate_frames;
+                    self.gap_frames = 0;
+                }
+            }
+            Some(d) => {
+                self.candidate = Some(d);
+                self.candidate_frames = 1;
+            }
+            None => {
+                self.candidate = None;
+                self.candidate_frames = 0;
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtmfSendMode {
+    Rfc4733,
+    InBand,
+    /// Karşı taraf bu oturumda telephone-event gönderdiyse RFC 4733, aksi halde in-band.
+    Auto,
+}
+
+impl DtmfSendMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "rfc4733" | "rfc2833" => Some(Self::Rfc4733),
+            "inband" => Some(Self::InBand),
+            "auto" => Some(Self::Auto),
+            _ => None,
+        }
+    }
+}
+
+/// Bir 20ms tick'te gönderilecek DTMF çıktısı.
+#[derive(Debug)]
+pub enum DtmfOutput {
+    /// Aynı zaman damgasını taşıyan RFC 4733 paketleri (son paket 3 kez tekrarlanır).
+    Events {
+        timestamp: u32,
+        marker: bool,
+        payloads: Vec<[u8; 4]>,
+    },
+    /// Ses yerine gönderilecek in-band ton.
+    Tone(Vec<i16>),
+    /// Rakamlar arası boşluk.
+    Silence,
+}
+
+/// Kuyruktaki rakam; kendi gönderim emrinin süre, boşluk ve kip ayarını taşır.
+#[derive(Debug)]
+struct QueuedDigit {
+    digit: char,
+    tone_frames: u32,
+    gap_frames: u32,
+    inband: bool,
+}
+
+#[derive(Debug)]
+enum SenderState {
+    Idle,
+    Tone {
+        event: u8,
+        frame: u32,
+        timestamp: u32,
+        queued: QueuedDigit,
+    },
+    Gap {
+        remaining: u32,
+    },
+}
+
+const FRAME_SAMPLES: u32 = 160;
+const EVENT_VOLUME: u8 = 10; // -10 dBm0
+const TONE_AMPLITUDE: f32 = 10_000.0;
+const END_PACKET_REPEATS: usize = 3;
+
+/// Rakam dizisini oturumun 20ms saatine göre RFC 4733 paketlerine veya in-band tona çevirir.
+#[derive(Debug)]
+pub struct DtmfSender {
+    queue: std::collections::VecDeque<QueuedDigit>,
+    state: SenderState,
+}
+
+impl DtmfSender {
+    pub fn new(
+        digits: &str,
+        tone_duration: std::time::Duration,
+        inter_digit_gap: std::time::Duration,
+        inband: bool,
+    ) -> Self {
+        let mut sender = Self {
+            queue: std::collections::VecDeque::new(),
+            state: SenderState::Idle,
+        };
+        sender.enqueue(digits, tone_duration, inter_digit_gap, inband);
+        sender
+    }
+
+    /// Gönderim sürerken gelen emir kuyruğa eklenir; ayarları yalnızca kendi rakamlarına uygulanır.
+    pub fn enqueue(
+        &mut self,
+        digits: &str,
+        tone_duration: std::time::Duration,
+        inter_digit_gap: std::time::Duration,
+        inband: bool,
+    ) {
+        // RFC 4733 duration alanı 16 bit: tek olay en fazla ~8 saniye.
+        let max_frames = u16::MAX as u32 / FRAME_SAMPLES;
+        let tone_frames = ((tone_duration.as_millis() / 20) as u32).clamp(2, max_frames);
+        let gap_frames = ((inter_digit_gap.as_millis() / 20) as u32).max(1);
+        self.queue.extend(
+            digits
+                .chars()
+                .filter(|d| digit_to_event(*d).is_some())
+                .map(|digit| QueuedDigit {
+                    digit,
+                    tone_frames,
+                    gap_frames,
+                    inband,
+                }),
+        );
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.queue.is_empty() && matches!(self.state, SenderState::Idle)
+    }
+
+    /// `current_ts`: bu tick'te gönderilecek RTP paketinin zaman damgası.
+    pub fn next_frame(&mut self, current_ts: u32) -> Option<DtmfOutput> {
+        if let SenderState::Idle = self.state {
+            let queued = self.queue.pop_front()?;
+            self.state = SenderState::Tone {
+                event: digit_to_event(queued.digit)?,
+                frame: 0,
+                timestamp: current_ts,
+                queued,
+            };
+        }
+
+        match &mut self.state {
+            SenderState::Tone {
+                event,
+                frame,
+                timestamp,
+                queued,
+            } => {
+                let index = *frame;
+                *frame += 1;
+                let last = *frame >= queued.tone_frames;
+
+                let output = if queued.inband {
+                    DtmfOutput::Tone(generate_tone(
+                        queued.digit,
+                        (index * FRAME_SAMPLES) as usize,
+                        FRAME_SAMPLES as usize,
+                    ))
+                } else {
+                    let payload = TelephoneEvent {
+                        event: *event,
+                        end: last,
+                        volume: EVENT_VOLUME,
+                        duration: (*frame * FRAME_SAMPLES) as u16,
+                    }
+                    .to_bytes();
+                    DtmfOutput::Events {
+                        timestamp: *timestamp,
+                        marker: index == 0,
+                        payloads: vec![payload; if last { END_PACKET_REPEATS } else { 1 }],
+                    }
+                };
+
+                if last {
+                    self.state = SenderState::Gap {
+                        remaining: queued.gap_frames,
+                    };
+                }
+                Some(output)
+            }
+            SenderState::Gap { remaining } => {
+                *remaining = remaining.saturating_sub(1);
+                if *remaining == 0 {
+                    self.state = SenderState::Idle;
+                }
+                Some(DtmfOutput::Silence)
+            }
+            SenderState::Idle => None,
+        }
+    }
+}
+
+/// 8 kHz çift-ton DTMF PCM üretir. `offset` faz sürekliliği için ton başından itibaren örnek indeksidir.
+pub fn generate_tone(digit: char, offset: usize, len: usize) -> Vec<i16> {
+    let position = KEYPAD
+        .iter()
+        .enumerate()
+        .find_map(|(r, row)| row.iter().position(|&k| k == digit).map(|c| (r, c)));
+    let Some((row, col)) = position else {
+        return vec![0; len];
+    };
+    let (f1, f2) = (ROW_FREQS[row], COL_FREQS[col]);
+    (offset..offset + len)
+        .map(|n| {
+            let t = n as f32 / SAMPLE_RATE;
+            let v = TONE_AMPLITUDE * 0.5 * ((2.0 * PI * f1 * t).sin() + (2.0 * PI * f2 * t).sin());
+            v as i16
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packet(event: u8, end: bool, duration: u16) -> [u8; 4] {
+        TelephoneEvent {
+            event,
+            end,
+            volume: 10,
+            duration,
+        }
+        .to_bytes()
+    }
+
+    #[test]
+    fn test_rfc4733_dedup_redundant_end_packets() {
+        let mut det = Rfc4733Detector::new();
+        assert!(det.on_packet(1000, &packet(5, false, 160)).is_empty());
+        assert!(det.on_packet(1000, &packet(5, false, 320)).is_empty());
+        let digits = det.on_packet(1000, &packet(5, true, 800));
+        assert_eq!(digits.len(), 1);
+        assert_eq!(digits[0].digit, '5');
+        assert_eq!(digits[0].duration_ms, 100);
+        assert!(det.on_packet(1000, &packet(5, true, 800)).is_empty());
+        assert!(det.on_packet(1000, &packet(5, true, 800)).is_empty());
+    }
+
+    #[test]
+    fn test_rfc4733_lost_end_packet() {
+        let mut det = Rfc4733Detector::new();
+        det.on_packet(1000, &packet(11, false, 320));
+        let digits = det.on_packet(3000, &packet(1, false, 160));
+        assert_eq!(digits.len(), 1);
+        assert_eq!(digits[0].digit, '#');
+    }
+
+    #[test]
+    fn test_rfc4733_late_packets_of_previous_event_are_ignored() {
+        let mut det = Rfc4733Detector::new();
+        // A: 1000, B: 2600. A'nın geç kalan "end" paketi B başladıktan sonra gelir.
+        det.on_packet(1000, &packet(1, false, 160));
+        let digits = det.on_packet(2600, &packet(2, false, 160));
+        assert_eq!(digits.len(), 1);
+        assert_eq!(digits[0].digit, '1');
+        assert!(det.on_packet(1000, &packet(1, true, 800)).is_empty());
+        // Sıra dışı gelen A başlangıcı da B'yi erken kapatmaz.
+        assert!(det.on_packet(1000, &packet(1, false, 320)).is_empty());
+        let digits = det.on_packet(2600, &packet(2, true, 960));
+        assert_eq!(digits.len(), 1);
+        assert_eq!((digits[0].digit, digits[0].duration_ms), ('2', 120));
+
+        // B bittikten sonra gelen A "end" paketi de yok sayılır.
+        assert!(det.on_packet(1000, &packet(1, true, 800)).is_empty());
+        assert!(det.on_packet(1000, &packet(1, false, 160)).is_empty());
+        // Zaman damgası sarması: 0'a dönen yeni olay eski sayılmaz.
+        let mut det = Rfc4733Detector::new();
+        det.on_packet(u32::MAX - 100, &packet(3, true, 800));
+        assert_eq!(det.on_packet(500, &packet(4, true, 800)).len(), 1);
+    }
+
+    #[test]
+    fn test_sender_rfc4733_timing() {
+        let mut sender = DtmfSender::new(
+            "1#",
+            std::time::Duration::from_millis(60),
+            std::time::Duration::from_millis(40),
+            false,
+        );
+        let mut packets = Vec::new();
+        let mut ts = 0u32;
+        while let Some(out) = sender.next_frame(ts) {
+            if let DtmfOutput::Events {
+                timestamp,
+                marker,
+                payloads,
+            } = out
+            {
+                for p in payloads {
+                    packets.push((timestamp, marker, TelephoneEvent::parse(&p).unwrap()));
+                }
+            }
+            ts += 160;
+        }
+        assert!(sender.is_finished());
+        // 3 ara paket + 2 ek end tekrarı, her rakam için
+        assert_eq!(packets.len(), 10);
+        assert!(packets[0].1);
+        assert_eq!(packets[2].2.duration, 480);
+        assert!(packets[2].2.end && packets[4].2.end);
+        assert_eq!(packets[5].0, 160 * 5);
+        assert_eq!(packets[5].2.event, 11);
+    }
+
+    #[test]
+    fn test_queued_command_keeps_its_own_mode_and_timing() {
+        let ms = std::time::Duration::from_millis;
+        let mut sender = DtmfSender::new("1", ms(40), ms(20), false);
+        sender.enqueue("2", ms(60), ms(40), true);
+        let mut outputs = Vec::new();
+        let mut ts = 0u32;
+        while let Some(out) = sender.next_frame(ts) {
+            outputs.push(match out {
+                DtmfOutput::Events { .. } => 'e',
+                DtmfOutput::Tone(_) => 't',
+                DtmfOutput::Silence => '-',
+            });
+            ts += 160;
+        }
+        assert_eq!(outputs.iter().collect::<String>(), "ee-ttt--");
+    }
+
+    #[test]
+    fn test_inband_detection() {
+        let mut det = InbandDetector::new();
+        let mut found = Vec::new();
+        let mut offset = 0;
+        for _ in 0..5 {
+            let frame = generate_tone('7', offset, 160);
+            offset += 160;
+            found.extend(det.process(&frame));
+        }
+        for _ in 0..3 {
+            found.extend(det.process(&[0i16; 160]));
+        }
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].digit, '7');
+        assert_eq!(found[0].duration_ms, 100);
+    }
+}
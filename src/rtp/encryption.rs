@@ -0,0 +1,313 @@
+// Dosya: src/rtp/encryption.rs
+use aes_gcm::aead::{Aead, KeyInit, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+const MAGIC: &[u8; 4] = b"SREC";
+const VERSION: u8 = 1;
+const WRAP_NONCE_LEN: usize = 12;
+const BASE_NONCE_LEN: usize = 8;
+pub const TAG_LEN: usize = 16;
+
+#[derive(Deserialize)]
+struct KeyringFile {
+    tenants: HashMap<String, TenantKeysFile>,
+}
+
+#[derive(Deserialize)]
+struct TenantKeysFile {
+    active_key_id: String,
+    keys: HashMap<String, String>,
+}
+
+struct TenantKeys {
+    active_key_id: String,
+    keys: HashMap<String, [u8; 32]>,
+}
+
+/// Kiracı anahtarları (KEK). Dosya biçimi:
+/// `{"tenants": {"<tenant_id>": {"active_key_id": "k2", "keys": {"k1": "<base64 32 bayt>", "k2": "..."}}}}`.
+/// Eski anahtarlar eski kayıtları açabilmek için dosyada kalır; yeni kayıtlar aktif anahtarı kullanır.
+pub struct Keyring {
+    tenants: HashMap<String, TenantKeys>,
+}
+
+impl std::fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Anahtar baytları asla loglanmaz.
+        let mut map = f.debug_map();
+        for (tenant, keys) in &self.tenants {
+            map.entry(tenant, &keys.active_key_id);
+        }
+        map.finish()
+    }
+}
+
+impl Keyring {
+    pub fn load(path: &str) -> Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("Kayıt anahtarlığı okunamadı: {}", path))?;
+        Self::parse(&raw)
+    }
+
+    pub fn parse(raw: &str) -> Result<Self> {
+        let file: KeyringFile =
+            serde_json::from_str(raw).context("Kayıt anahtarlığı geçersiz JSON")?;
+        let mut tenants = HashMap::new();
+        for (tenant, entry) in file.tenants {
+            let mut keys = HashMap::new();
+            for (key_id, encoded) in entry.keys {
+                if key_id.is_empty() || key_id.len() > u8::MAX as usize {
+                    bail!("Geçersiz anahtar kimliği ({}): {:?}", tenant, key_id);
+                }
+                let key: [u8; 32] = BASE64
+                    .decode(encoded.trim())
+                    .ok()
+                    .and_then(|bytes| bytes.try_into().ok())
+                    .ok_or_else(|| {
+                        anyhow!("{}/{} anahtarı 32 bayt base64 değil", tenant, key_id)
+                    })?;
+                keys.insert(key_id, key);
+            }
+            if !keys.contains_key(&entry.active_key_id) {
+                bail!(
+                    "{} için aktif anahtar ({}) anahtarlıkta yok",
+                    tenant,
+                    entry.active_key_id
+                );
+            }
+            tenants.insert(
+                tenant,
+                TenantKeys {
+                    active_key_id: entry.active_key_id,
+                    keys,
+                },
+            );
+        }
+        Ok(Self { tenants })
+    }
+
+    fn active_key(&self, tenant_id: &str) -> Option<(&str, &[u8; 32])> {
+        let tenant = self.tenants.get(tenant_id)?;
+        let key = tenant.keys.get(&tenant.active_key_id)?;
+        Some((&tenant.active_key_id, key))
+    }
+
+    fn key(&self, tenant_id: &str, key_id: &str) -> Option<&[u8; 32]> {
+        self.tenants.get(tenant_id)?.keys.get(key_id)
+    }
+
+    /// Kiracının aktif anahtarıyla yeni bir kayıt şifreleyicisi (rastgele veri anahtarı) üretir.
+    pub fn encryptor(&self, tenant_id: &str) -> Result<RecordingEncryptor> {
+        let (key_id, kek) = self
+            .active_key(tenant_id)
+            .ok_or_else(|| anyhow!("{} kiracısı için kayıt anahtarı yok", tenant_id))?;
+        RecordingEncryptor::new(key_id, kek)
+    }
+}
+
+/// Zarf şifreleme (envelope): her kayda rastgele bir AES-256-GCM veri anahtarı (DEK) üretilir,
+/// DEK kiracı anahtarıyla (KEK) sarılıp nesnenin başındaki zarf başlığına yazılır.
+///
+/// Biçim: `"SREC" | sürüm | key_id uzunluğu | key_id | sarma nonce (12) | sarılı DEK (48) |
+/// segment boyu (u32 BE) | temel nonce (8)`, ardından segmentler. Her segment bağımsız
+/// şifrelenir (nonce = temel nonce ‖ segment no); son segment işaretlidir, bu sayede
+/// kesilmiş/yeniden sıralanmış nesne çözülemez.
+pub struct RecordingEncryptor {
+    cipher: Aes256Gcm,
+    key_id: String,
+    header: Vec<u8>,
+    base_nonce: [u8; BASE_NONCE_LEN],
+}
+
+impl std::fmt::Debug for RecordingEncryptor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingEncryptor")
+            .field("key_id", &self.key_id)
+            .finish()
+    }
+}
+
+impl RecordingEncryptor {
+    fn new(key_id: &str, kek: &[u8; 32]) -> Result<Self> {
+        let mut dek = [0u8; 32];
+        let mut wrap_nonce = [0u8; WRAP_NONCE_LEN];
+        let mut base_nonce = [0u8; BASE_NONCE_LEN];
+        OsRng.fill_bytes(&mut dek);
+        OsRng.fill_bytes(&mut wrap_nonce);
+        OsRng.fill_bytes(&mut base_nonce);
+
+        let wrapped = Aes256Gcm::new(kek.into())
+            .encrypt(
+                &wrap_nonce.into(),
+                Payload {
+                    msg: &dek,
+                    aad: &wrap_aad(key_id),
+                },
+            )
+            .map_err(|_| anyhow!("Veri anahtarı sarılamadı"))?;
+
+        let mut header = Vec::with_capacity(64 + key_id.len());
+        header.extend_from_slice(MAGIC);
+        header.push(VERSION);
+        header.push(key_id.len() as u8);
+        header.extend_from_slice(key_id.as_bytes());
+        header.extend_from_slice(&wrap_nonce);
+        header.extend_from_slice(&wrapped);
+        header.extend_from_slice(&(crate::rtp::writers::RECORDING_PART_SIZE as u32).to_be_bytes());
+        header.extend_from_slice(&base_nonce);
+
+        Ok(Self {
+            cipher: Aes256Gcm::new(&dek.into()),
+            key_id: key_id.to_string(),
+            header,
+            base_nonce,
+        })
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    /// Nesnenin başına yazılan zarf başlığı.
+    pub fn header(&self) -> &[u8] {
+        &self.header
+    }
+
+    /// `index` numaralı segmenti şifreler; çıktı `plaintext.len() + TAG_LEN` bayttır.
+    pub fn seal(&self, index: u32, last: bool, plaintext: &[u8]) -> Vec<u8> {
+        self.cipher
+            .encrypt(
+                &segment_nonce(&self.base_nonce, index),
+                Payload {
+                    msg: plaintext,
+                    aad: &segment_aad(&self.header, index, last),
+                },
+            )
+            .expect(
+                "AES-GCM yalnızca 64 GiB üzeri segmentte hata verir; segmentler parça boyundadır",
+            )
+    }
+}
+
+fn wrap_aad(key_id: &str) -> Vec<u8> {
+    [MAGIC.as_slice(), key_id.as_bytes()].concat()
+}
+
+fn segment_nonce(base: &[u8; BASE_NONCE_LEN], index: u32) -> Nonce<aes_gcm::aead::consts::U12> {
+    let mut nonce = [0u8; 12];
+    nonce[..BASE_NONCE_LEN].copy_from_slice(base);
+    nonce[BASE_NONCE_LEN..].copy_from_slice(&index.to_be_bytes());
+    nonce.into()
+}
+
+fn segment_aad(header: &[u8], index: u32, last: bool) -> Vec<u8> {
+    let mut aad = Vec::with_capacity(header.len() + 5);
+    aad.extend_from_slice(header);
+    aad.extend_from_slice(&index.to_be_bytes());
+    aad.push(last as u8);
+    aad
+}
+
+/// Şifreli kaydı çözer (arşivden geri okuma araçları ve testler için).
+pub fn open_recording(keyring: &Keyring, tenant_id: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let corrupt = || anyhow!("Şifreli kayıt bozuk veya kesilmiş");
+    if data.len() < 6 || &data[..4] != MAGIC || data[4] != VERSION {
+        return Err(corrupt());
+    }
+    let key_id_len = data[5] as usize;
+    let header_len = 6 + key_id_len + WRAP_NONCE_LEN + 32 + TAG_LEN + 4 + BASE_NONCE_LEN;
+    if data.len() < header_len {
+        return Err(corrupt());
+    }
+    let (header, body) = data.split_at(header_len);
+    let key_id = std::str::from_utf8(&header[6..6 + key_id_len]).map_err(|_| corrupt())?;
+    let kek = keyring
+        .key(tenant_id, key_id)
+        .ok_or_else(|| anyhow!("Anahtar bulunamadı: {}/{}", tenant_id, key_id))?;
+
+    let mut offset = 6 + key_id_len;
+    let wrap_nonce: [u8; WRAP_NONCE_LEN] =
+        header[offset..offset + WRAP_NONCE_LEN].try_into().unwrap();
+    offset += WRAP_NONCE_LEN;
+    let wrapped = &header[offset..offset + 32 + TAG_LEN];
+    offset += 32 + TAG_LEN;
+    let segment_size =
+        u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap()) as usize + TAG_LEN;
+    offset += 4;
+    let base_nonce: [u8; BASE_NONCE_LEN] = header[offset..].try_into().unwrap();
+
+    let dek = Aes256Gcm::new(kek.into())
+        .decrypt(
+            &wrap_nonce.into(),
+            Payload {
+                msg: wrapped,
+                aad: &wrap_aad(key_id),
+            },
+        )
+        .map_err(|_| anyhow!("Veri anahtarı açılamadı (yanlış anahtar?)"))?;
+    let cipher = Aes256Gcm::new_from_slice(&dek).map_err(|_| corrupt())?;
+
+    // Tam boy segmentler ardından her zaman daha kısa bir son segment gelir.
+    let mut plaintext = Vec::with_capacity(body.len());
+    let mut rest = body;
+    let mut index = 0u32;
+    loop {
+        let last = rest.len() < segment_size;
+        let (segment, next) = rest.split_at(rest.len().min(segment_size));
+        let opened = cipher
+            .decrypt(
+                &segment_nonce(&base_nonce, index),
+                Payload {
+                    msg: segment,
+                    aad: &segment_aad(header, index, last),
+                },
+            )
+            .map_err(|_| corrupt())?;
+        plaintext.extend_from_slice(&opened);
+        if last {
+            return Ok(plaintext);
+        }
+        rest = next;
+        index += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_roundtrip_and_tamper_detection() {
+        let key = BASE64.encode([7u8; 32]);
+        let keyring = Keyring::parse(&format!(
+            r#"{{"tenants": {{"acme": {{"active_key_id": "k1", "keys": {{"k1": "{}"}}}}}}}}"#,
+            key
+        ))
+        .unwrap();
+        let encryptor = keyring.encryptor("acme").unwrap();
+        assert_eq!(encryptor.key_id(), "k1");
+        assert!(keyring.encryptor("other").is_err());
+
+        let mut object = encryptor.header().to_vec();
+        object.extend(encryptor.seal(0, true, b"RIFF....WAVE"));
+        assert_eq!(
+            open_recording(&keyring, "acme", &object).unwrap(),
+            b"RIFF....WAVE"
+        );
+
+        // Son segment işareti olmadan şifrelenmiş (kesilmiş) nesne reddedilir.
+        let mut truncated = encryptor.header().to_vec();
+        truncated.extend(encryptor.seal(0, false, b"RIFF"));
+        assert!(open_recording(&keyring, "acme", &truncated).is_err());
+
+        let last = object.len() - 1;
+        object[last] ^= 1;
+        assert!(open_recording(&keyring, "acme", &object).is_err());
+    }
+}
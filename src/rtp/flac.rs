@@ -0,0 +1,439 @@
+// Dosya: src/rtp/flac.rs
+use anyhow::{anyhow, Result};
+
+// Sabit blok boyu (kanal başına örnek). Son blok daha kısa olabilir.
+const BLOCK_SIZE: usize = 4096;
+const BITS_PER_SAMPLE: u32 = 16;
+// "fLaC" + metadata blok başlığı + 34 baytlık STREAMINFO.
+pub const FLAC_HEADER_LEN: usize = 4 + 4 + 34;
+// 4 bitlik Rice parametresinde 15 kaçış kodudur; kullanılmaz.
+const MAX_RICE_PARAM: u32 = 14;
+const MAX_PARTITION_ORDER: u32 = 8;
+
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u64,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            bytes: Vec::new(),
+            acc: 0,
+            bits: 0,
+        }
+    }
+
+    fn write(&mut self, value: u64, bits: u32) {
+        debug_assert!(bits <= 32);
+        if bits == 0 {
+            return;
+        }
+        self.acc = (self.acc << bits) | (value & ((1u64 << bits) - 1));
+        self.bits += bits;
+        while self.bits >= 8 {
+            self.bits -= 8;
+            self.bytes.push((self.acc >> self.bits) as u8);
+        }
+        self.acc &= (1u64 << self.bits) - 1;
+    }
+
+    fn write_signed(&mut self, value: i32, bits: u32) {
+        self.write(value as u32 as u64, bits);
+    }
+
+    fn write_unary(&mut self, mut zeros: u32) {
+        while zeros >= 32 {
+            self.write(0, 32);
+            zeros -= 32;
+        }
+        self.write(1, zeros + 1);
+    }
+
+    fn align(&mut self) {
+        if self.bits > 0 {
+            self.write(0, 8 - self.bits);
+        }
+    }
+}
+
+fn crc8(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |mut crc, &byte| {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |mut crc, &byte| {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x8005
+            } else {
+                crc << 1
+            };
+        }
+        crc
+    })
+}
+
+/// Çerçeve numarası için FLAC'ın UTF-8 benzeri değişken uzunluklu kodlaması.
+fn utf8_number(value: u64) -> Vec<u8> {
+    if value < 0x80 {
+        return vec![value as u8];
+    }
+    let len = match value {
+        v if v < 0x800 => 2,
+        v if v < 0x1_0000 => 3,
+        v if v < 0x20_0000 => 4,
+        v if v < 0x400_0000 => 5,
+        v if v < 0x8000_0000 => 6,
+        _ => 7,
+    };
+    let mut bytes = vec![((0xFF00u16 >> len) as u8) | (value >> (6 * (len - 1))) as u8];
+    for i in (0..len - 1).rev() {
+        bytes.push(0x80 | ((value >> (6 * i)) & 0x3F) as u8);
+    }
+    bytes
+}
+
+fn sample_rate_code(sample_rate: u32) -> Option<u64> {
+    Some(match sample_rate {
+        8000 => 4,
+        16000 => 5,
+        22050 => 6,
+        24000 => 7,
+        32000 => 8,
+        44100 => 9,
+        48000 => 10,
+        _ => return None,
+    })
+}
+
+/// Sabit (fixed) öngörücü artıkları; `order` kadar ısınma örneği atlanır.
+fn fixed_residual(samples: &[i32], order: usize) -> Vec<i32> {
+    (order..samples.len())
+        .map(|i| {
+            let x = |k: usize| samples[i - k];
+            match order {
+                0 => x(0),
+                1 => x(0) - x(1),
+                2 => x(0) - 2 * x(1) + x(2),
+                3 => x(0) - 3 * x(1) + 3 * x(2) - x(3),
+                _ => x(0) - 4 * x(1) + 6 * x(2) - 4 * x(3) + x(4),
+            }
+        })
+        .collect()
+}
+
+fn zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+fn rice_param(sum: u64, count: u64) -> u32 {
+    let mut k = 0;
+    while k < MAX_RICE_PARAM && count << (k + 1) < sum {
+        k += 1;
+    }
+    k
+}
+
+/// En az bit harcayan bölümleme derecesini ve her bölümün Rice parametresini seçer.
+/// Dönüş: (tahmini bit, bölüm derecesi, parametreler).
+fn plan_residual(residual: &[i32], block_len: usize, order: usize) -> (u64, u32, Vec<u32>) {
+    let mut max_order = 0;
+    while max_order < MAX_PARTITION_ORDER
+        && block_len.is_multiple_of(1 << (max_order + 1))
+        && (block_len >> (max_order + 1)) > order
+    {
+        max_order += 1;
+    }
+
+    // En ince bölümlemenin toplamları; kaba bölümlemeler ikili birleştirmeyle bulunur.
+    let partition_len = block_len >> max_order;
+    let mut sums: Vec<(u64, u64)> = Vec::with_capacity(1 << max_order);
+    let mut start = 0;
+    for p in 0..(1usize << max_order) {
+        let len = if p == 0 {
+            partition_len - order
+        } else {
+            partition_len
+        };
+        let sum = residual[start..start + len]
+            .iter()
+            .map(|&e| zigzag(e) as u64)
+            .sum();
+        sums.push((sum, len as u64));
+        start += len;
+    }
+
+    let mut best: Option<(u64, u32, Vec<u32>)> = None;
+    let mut partition_order = max_order;
+    loop {
+        let params: Vec<u32> = sums.iter().map(|&(s, n)| rice_param(s, n)).collect();
+        let bits: u64 = sums
+            .iter()
+            .zip(&params)
+            .map(|(&(s, n), &k)| 4 + n * (k as u64 + 1) + (s >> k))
+            .sum();
+        if best.as_ref().is_none_or(|(b, _, _)| bits < *b) {
+            best = Some((bits, partition_order, params));
+        }
+        if partition_order == 0 {
+            break;
+        }
+        sums = sums
+            .chunks(2)
+            .map(|pair| (pair[0].0 + pair[1].0, pair[0].1 + pair[1].1))
+            .collect();
+        partition_order -= 1;
+    }
+    best.expect("en az bir bölümleme derecesi denenir")
+}
+
+fn write_subframe(w: &mut BitWriter, samples: &[i32]) {
+    // Sessizlik (ve sabit DC) tek örnekle kodlanır.
+    if samples.iter().all(|&s| s == samples[0]) {
+        w.write(0, 8);
+        w.write_signed(samples[0], BITS_PER_SAMPLE);
+        return;
+    }
+
+    let verbatim_bits = samples.len() as u64 * BITS_PER_SAMPLE as u64;
+    let best = (0..=4usize.min(samples.len() - 1))
+        .map(|order| {
+            let residual = fixed_residual(samples, order);
+            let (bits, partition_order, params) = plan_residual(&residual, samples.len(), order);
+            let total = bits + 6 + order as u64 * BITS_PER_SAMPLE as u64;
+            (total, order, residual, partition_order, params)
+        })
+        .min_by_key(|candidate| candidate.0);
+
+    match best {
+        Some((total, order, residual, partition_order, params)) if total < verbatim_bits => {
+            w.write(0, 1);
+            w.write(0b001000 | order as u64, 6);
+            w.write(0, 1);
+            for &s in &samples[..order] {
+                w.write_signed(s, BITS_PER_SAMPLE);
+            }
+            w.write(0, 2);
+            w.write(partition_order as u64, 4);
+            let partition_len = samples.len() >> partition_order;
+            let mut start = 0;
+            for (p, &k) in params.iter().enumerate() {
+                let len = if p == 0 {
+                    partition_len - order
+                } else {
+                    partition_len
+                };
+                w.write(k as u64, 4);
+                for &e in &residual[start..start + len] {
+                    let u = zigzag(e);
+                    w.write_unary(u >> k);
+                    w.write(u as u64, k);
+                }
+                start += len;
+            }
+        }
+        _ => {
+            w.write(0, 1);
+            w.write(1, 6);
+            w.write(0, 1);
+            for &s in samples {
+                w.write_signed(s, BITS_PER_SAMPLE);
+            }
+        }
+    }
+}
+
+/// Kayıplı olmayan FLAC kodlayıcı (16-bit, mono/stereo). Çıktı akış olarak yazılabilir;
+/// toplam örnek sayısı ve çerçeve boyları içeren STREAMINFO sonunda `header` ile yeniden
+/// üretilip akışın başına yamalanır.
+#[derive(Debug)]
+pub struct FlacEncoder {
+    sample_rate: u32,
+    channels: usize,
+    rate_code: u64,
+    // İç içe (interleaved) bekleyen örnekler.
+    pending: Vec<i16>,
+    frame_number: u64,
+    total_samples: u64,
+    min_frame_len: u32,
+    max_frame_len: u32,
+}
+
+impl FlacEncoder {
+    pub fn new(sample_rate: u32, channels: u16) -> Result<Self> {
+        let rate_code = sample_rate_code(sample_rate)
+            .ok_or_else(|| anyhow!("FLAC için desteklenmeyen hız: {} Hz", sample_rate))?;
+        if !(1..=2).contains(&channels) {
+            return Err(anyhow!(
+                "FLAC için desteklenmeyen kanal sayısı: {}",
+                channels
+            ));
+        }
+        Ok(Self {
+            sample_rate,
+            channels: channels as usize,
+            rate_code,
+            pending: Vec::new(),
+            frame_number: 0,
+            total_samples: 0,
+            min_frame_len: 0,
+            max_frame_len: 0,
+        })
+    }
+
+    /// "fLaC" + STREAMINFO; o ana kadar kodlanan akışı tanımlar. Her zaman `FLAC_HEADER_LEN` bayttır.
+    pub fn header(&self) -> Vec<u8> {
+        let mut w = BitWriter::new();
+        w.bytes.extend_from_slice(b"fLaC");
+        w.write(1, 1); // Son metadata bloğu
+        w.write(0, 7); // STREAMINFO
+        w.write(34, 24);
+        w.write(BLOCK_SIZE as u64, 16);
+        w.write(BLOCK_SIZE as u64, 16);
+        w.write(self.min_frame_len as u64, 24);
+        w.write(self.max_frame_len as u64, 24);
+        w.write(self.sample_rate as u64, 20);
+        w.write(self.channels as u64 - 1, 3);
+        w.write(BITS_PER_SAMPLE as u64 - 1, 5);
+        w.write(self.total_samples >> 32, 4);
+        w.write(self.total_samples & 0xFFFF_FFFF, 32);
+        // MD5 imzası: 0 = hesaplanmadı.
+        for _ in 0..4 {
+            w.write(0, 32);
+        }
+        w.bytes
+    }
+
+    /// İç içe örnekleri ekler ve tamamlanan blokları kodlanmış çerçeveler olarak döndürür.
+    pub fn encode(&mut self, interleaved: &[i16]) -> Vec<u8> {
+        self.pending.extend_from_slice(interleaved);
+        let block = BLOCK_SIZE * self.channels;
+        let mut out = Vec::new();
+        while self.pending.len() >= block {
+            let samples: Vec<i16> = self.pending.drain(..block).collect();
+            out.extend(self.encode_frame(&samples));
+        }
+        out
+    }
+
+    /// Kalan (kısa) bloğu kodlar.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let rest = self.pending.len() - self.pending.len() % self.channels;
+        if rest == 0 {
+            return Vec::new();
+        }
+        let samples: Vec<i16> = self.pending.drain(..rest).collect();
+        self.pending.clear();
+        self.encode_frame(&samples)
+    }
+
+    fn encode_frame(&mut self, interleaved: &[i16]) -> Vec<u8> {
+        let block_len = interleaved.len() / self.channels;
+        let mut w = BitWriter::new();
+        w.write(0x3FFE, 14);
+        w.write(0, 1);
+        w.write(0, 1); // Sabit blok boyu
+        if block_len == BLOCK_SIZE {
+            w.write(12, 4);
+        } else {
+            w.write(7, 4); // Blok boyu başlığın sonunda 16 bit
+        }
+        w.write(self.rate_code, 4);
+        w.write(self.channels as u64 - 1, 4); // Bağımsız kanallar
+        w.write(0b100, 3); // 16 bit
+        w.write(0, 1);
+        w.bytes.extend(utf8_number(self.frame_number));
+        if block_len != BLOCK_SIZE {
+            w.write(block_len as u64 - 1, 16);
+        }
+        let header_crc = crc8(&w.bytes);
+        w.write(header_crc as u64, 8);
+
+        for channel in 0..self.channels {
+            let samples: Vec<i32> = interleaved
+                .iter()
+                .skip(channel)
+                .step_by(self.channels)
+                .map(|&s| s as i32)
+                .collect();
+            write_subframe(&mut w, &samples);
+        }
+        w.align();
+        let frame_crc = crc16(&w.bytes);
+        w.bytes.extend_from_slice(&frame_crc.to_be_bytes());
+
+        let frame_len = w.bytes.len() as u32;
+        self.min_frame_len = if self.frame_number == 0 {
+            frame_len
+        } else {
+            self.min_frame_len.min(frame_len)
+        };
+        self.max_frame_len = self.max_frame_len.max(frame_len);
+        self.frame_number += 1;
+        self.total_samples += block_len as u64;
+        w.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_silence_and_tone_compress() {
+        let mut encoder = FlacEncoder::new(8000, 2).unwrap();
+        assert_eq!(encoder.header().len(), FLAC_HEADER_LEN);
+
+        // 1 sn sessizlik + 1 sn ton (stereo).
+        let mut pcm = vec![0i16; 16000];
+        for n in 0..8000 {
+            let s =
+                (8000.0 * (2.0 * std::f64::consts::PI * 440.0 * n as f64 / 8000.0).sin()) as i16;
+            pcm.extend_from_slice(&[s, s / 2]);
+        }
+        let mut encoded = encoder.encode(&pcm);
+        encoded.extend(encoder.finish());
+
+        assert!(encoded.len() < pcm.len() * 2 / 3);
+        assert_eq!(&encoded[..2], &[0xFF, 0xF8]);
+        let header = encoder.header();
+        assert_eq!(&header[..4], b"fLaC");
+        // STREAMINFO'daki toplam örnek sayısı (36 bitin alt 32 biti).
+        assert_eq!(&header[22..26], &16000u32.to_be_bytes());
+        assert_eq!(decode(&encoder, &encoded), pcm);
+
+        // Kısa son blok ve blok sınırları: tek örnek, bir eksik, tam blok, iki blok + 7.
+        for frames in [1, BLOCK_SIZE - 1, BLOCK_SIZE, 2 * BLOCK_SIZE + 7] {
+            let mut encoder = FlacEncoder::new(16000, 1).unwrap();
+            let pcm: Vec<i16> = (0..frames)
+                .map(|n| (n as i32 * 7919 % 65536 - 32768) as i16)
+                .collect();
+            let mut encoded: Vec<u8> = pcm.chunks(1000).flat_map(|c| encoder.encode(c)).collect();
+            encoded.extend(encoder.finish());
+            assert_eq!(decode(&encoder, &encoded), pcm, "{} örnek", frames);
+        }
+    }
+
+    fn decode(encoder: &FlacEncoder, frames: &[u8]) -> Vec<i16> {
+        let mut file = encoder.header();
+        file.extend_from_slice(frames);
+        let mut reader = claxon::FlacReader::new(std::io::Cursor::new(file)).unwrap();
+        let total = reader.streaminfo().samples.unwrap() as usize;
+        let samples: Vec<i16> = reader.samples().map(|s| s.unwrap() as i16).collect();
+        assert_eq!(samples.len(), total * reader.streaminfo().channels as usize);
+        samples
+    }
+}
@@ -0,0 +1,401 @@
+// Dosya: src/rtp/jitter.rs
+use sentiric_rtp_core::{RtpHeader, RtpPacket};
+use std::collections::BTreeMap;
+use tokio::time::Instant;
+
+// RFC 3550 A.1: Bu kadar sıra atlaması "yeni akış / kaynak yeniden başladı" kabul edilir.
+const MAX_DROPOUT: u64 = 3000;
+// Genişletilmiş sıra numarası 0'ın altına düşmesin diye başlangıç ofseti.
+const EXT_SEQ_BASE: u64 = 1 << 32;
+const CLOCK_RATE: f64 = 8000.0;
+const DEFAULT_SAMPLES_PER_PACKET: u32 = 160;
+// Hedef derinlik = jitter * çarpan + bir paket süresi.
+const JITTER_DEPTH_MULTIPLIER: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Accepted,
+    Reordered,
+    Duplicate,
+    Late,
+    Resynced,
+}
+
+#[derive(Debug)]
+pub enum Playout {
+    Packet(RtpPacket),
+    Lost {
+        samples: usize,
+    },
+    /// Sırası ses dışı bir pakete (RFC 4733 telephone-event) ait; kayıp değildir.
+    NonAudio {
+        samples: usize,
+    },
+}
+
+/// RTP sıra numarası ve zaman damgasına göre çalışan adaptif jitter buffer.
+/// Hedef derinlik, RFC 3550 interarrival jitter tahminine göre
+/// `min_depth` ile `max_depth` (paket) arasında ayarlanır.
+pub struct JitterBuffer {
+    // `None`: aynı sıra uzayını paylaşan ses dışı paket.
+    packets: BTreeMap<u64, Option<RtpPacket>>,
+    ssrc: Option<u32>,
+    highest_ext_seq: Option<u64>,
+    // RFC 3550 A.1 `bad_seq`: büyük sıçramadan sonra beklenen sıra; gelirse akış yeniden başlamıştır.
+    bad_seq: Option<u16>,
+    highest_ts: u32,
+    highest_is_audio: bool,
+    next_play_seq: Option<u64>,
+    last_transit: Option<f64>,
+    jitter: f64,
+    samples_per_packet: u32,
+    min_depth: usize,
+    max_depth: usize,
+    target_depth: usize,
+    buffering: bool,
+    epoch: Instant,
+}
+
+impl JitterBuffer {
+    pub fn new(min_depth: usize, max_depth: usize) -> Self {
+        let min_depth = min_depth.max(1);
+        let max_depth = max_depth.max(min_depth);
+        Self {
+            packets: BTreeMap::new(),
+            ssrc: None,
+            highest_ext_seq: None,
+            bad_seq: None,
+            highest_ts: 0,
+            highest_is_audio: false,
+            next_play_seq: None,
+            last_transit: None,
+            jitter: 0.0,
+            samples_per_packet: DEFAULT_SAMPLES_PER_PACKET,
+            min_depth,
+            max_depth,
+            target_depth: min_depth,
+            buffering: true,
+            epoch: Instant::now(),
+        }
+    }
+
+    /// Milisaniye cinsinden sınırlardan, 20ms'lik paketler varsayılarak buffer kurar.
+    pub fn from_millis(min_ms: u64, max_ms: u64) -> Self {
+        Self::new(min_ms.div_ceil(20) as usize, max_ms.div_ceil(20) as usize)
+    }
+
+    pub fn push(&mut self, packet: RtpPacket, arrival: Instant) -> PushOutcome {
+        let header = &packet.header;
+        let (seq, ssrc, ts) = (header.sequence_number, header.ssrc, header.timestamp);
+        self.push_slot(seq, ssrc, ts, Some(packet), arrival)
+    }
+
+    /// Ses taşımayan ama sıra numarası uzayını paylaşan paketi (RFC 4733) kaydeder. Sırası
+    /// tüketilir ki kayıp sayılmasın; jitter tahminine ve paket süresine katılmaz.
+    pub fn push_non_audio(&mut self, header: &RtpHeader, arrival: Instant) -> PushOutcome {
+        let (seq, ssrc, ts) = (header.sequence_number, header.ssrc, header.timestamp);
+        self.push_slot(seq, ssrc, ts, None, arrival)
+    }
+
+    fn push_slot(
+        &mut self,
+        seq: u16,
+        ssrc: u32,
+        ts: u32,
+        packet: Option<RtpPacket>,
+        arrival: Instant,
+    ) -> PushOutcome {
+        let mut outcome = PushOutcome::Accepted;
+        if self.ssrc != Some(ssrc) {
+            if self.ssrc.is_some() {
+                outcome = PushOutcome::Resynced;
+            }
+            self.reset(ssrc);
+        }
+
+        let ext_seq = match self.highest_ext_seq {
+            None => EXT_SEQ_BASE + seq as u64,
+            Some(highest) => {
+                let delta = seq.wrapping_sub(highest as u16) as i16 as i64;
+                (highest as i64 + delta) as u64
+            }
+        };
+
+        if let Some(highest) = self.highest_ext_seq {
+            if ext_seq.abs_diff(highest) > MAX_DROPOUT {
+                // Tek başıboş paket akışı sıfırlamaz; ancak ardından sıradaki de gelirse kaynak
+                // yeniden başlamış sayılır (RFC 3550 A.1).
+                if self.bad_seq != Some(seq) {
+                    self.bad_seq = Some(seq.wrapping_add(1));
+                    return PushOutcome::Late;
+                }
+                self.reset(ssrc);
+                outcome = PushOutcome::Resynced;
+                return self.insert(EXT_SEQ_BASE + seq as u64, ts, packet, arrival, outcome);
+            }
+        }
+
+        if let Some(next) = self.next_play_seq {
+            if ext_seq < next {
+                return PushOutcome::Late;
+            }
+        }
+
+        if self.packets.contains_key(&ext_seq) {
+            return PushOutcome::Duplicate;
+        }
+
+        if outcome == PushOutcome::Accepted
+            && self
+                .highest_ext_seq
+                .is_some_and(|highest| ext_seq < highest)
+        {
+            outcome = PushOutcome::Reordered;
+        }
+
+        self.insert(ext_seq, ts, packet, arrival, outcome)
+    }
+
+    fn insert(
+        &mut self,
+        ext_seq: u64,
+        ts: u32,
+        packet: Option<RtpPacket>,
+        arrival: Instant,
+        outcome: PushOutcome,
+    ) -> PushOutcome {
+        // telephone-event zaman damgası olayın başlangıcında sabit kalır; jitter'ı bozmasın.
+        let is_audio = packet.is_some();
+        if is_audio {
+            self.update_jitter(ts, arrival);
+        }
+
+        if self.highest_ext_seq.is_none_or(|highest| ext_seq > highest) {
+            if let Some(highest) = self.highest_ext_seq {
+                if is_audio && self.highest_is_audio && ext_seq == highest + 1 {
+                    let ts_step = ts.wrapping_sub(self.highest_ts);
+                    if ts_step > 0 && ts_step <= CLOCK_RATE as u32 {
+                        self.samples_per_packet = ts_step;
+                    }
+                }
+            }
+            self.highest_ext_seq = Some(ext_seq);
+            self.highest_is_audio = is_audio;
+            if is_audio {
+                self.highest_ts = ts;
+            }
+        }
+
+        self.packets.insert(ext_seq, packet);
+        self.update_target_depth();
+        outcome
+    }
+
+    // RFC 3550 6.4.1: J(i) = J(i-1) + (|D(i-1,i)| - J(i-1)) / 16
+    fn update_jitter(&mut self, ts: u32, arrival: Instant) {
+        let arrival_units = arrival.duration_since(self.epoch).as_secs_f64() * CLOCK_RATE;
+        let transit = arrival_units - ts as f64;
+        if let Some(last) = self.last_transit {
+            let d = (transit - last).abs();
+            // Zaman damgası sarması (u32 wrap) tek seferlik dev bir sıçrama üretir; tahmine katılmaz.
+            if d < u32::MAX as f64 / 2.0 {
+                self.jitter += (d - self.jitter) / 16.0;
+            }
+        }
+        self.last_transit = Some(transit);
+    }
+
+    fn update_target_depth(&mut self) {
+        let spp = self.samples_per_packet.max(1) as f64;
+        let wanted = (self.jitter * JITTER_DEPTH_MULTIPLIER + spp) / spp;
+        self.target_depth = (wanted.ceil() as usize).clamp(self.min_depth, self.max_depth);
+    }
+
+    /// Oynatma sırası gelen paketi döndürür. `None`: buffer dolduruluyor (pre-roll) veya boş.
+    pub fn pop(&mut self) -> Option<Playout> {
+        if self.packets.is_empty() {
+            self.buffering = true;
+            return None;
+        }
+
+        if self.buffering {
+            if self.packets.len() < self.target_depth {
+                return None;
+            }
+            self.buffering = false;
+            let first = *self.packets.keys().next()?;
+            if self.next_play_seq.is_none_or(|next| next < first) {
+                self.next_play_seq = Some(first);
+            }
+        }
+
+        let next = self.next_play_seq?;
+        self.next_play_seq = Some(next + 1);
+        let samples = self.samples_per_packet as usize;
+        match self.packets.remove(&next) {
+            Some(Some(packet)) => Some(Playout::Packet(packet)),
+            Some(None) => Some(Playout::NonAudio { samples }),
+            None => Some(Playout::Lost { samples }),
+        }
+    }
+
+    /// Buffer `max_depth`'in iki katını aşarsa en eski paketleri atıp hedef derinliğe iner.
+    /// Atılan paket sayısını döndürür.
+    pub fn trim_overflow(&mut self) -> usize {
+        if self.packets.len() <= self.max_depth * 2 {
+            return 0;
+        }
+        let excess = self.packets.len() - self.target_depth;
+        for _ in 0..excess {
+            self.packets.pop_first();
+        }
+        if let Some(first) = self.packets.keys().next() {
+            self.next_play_seq = Some(*first);
+        }
+        excess
+    }
+
+    fn reset(&mut self, ssrc: u32) {
+        self.packets.clear();
+        self.ssrc = Some(ssrc);
+        self.highest_ext_seq = None;
+        self.bad_seq = None;
+        self.next_play_seq = None;
+        self.last_transit = None;
+        self.buffering = true;
+    }
+
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    pub fn target_depth(&self) -> usize {
+        self.target_depth
+    }
+
+    pub fn highest_ext_seq(&self) -> Option<u64> {
+        self.highest_ext_seq
+    }
+
+    /// RFC 3550 interarrival jitter tahmini (8 kHz zaman damgası birimi).
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sentiric_rtp_core::RtpHeader;
+    use tokio::time::Duration;
+
+    fn packet(seq: u16, ts: u32) -> RtpPacket {
+        RtpPacket {
+            header: RtpHeader::new(0, seq, ts, 0xABCD),
+            payload: vec![seq as u8],
+        }
+    }
+
+    fn played_seqs(jb: &mut JitterBuffer) -> Vec<Option<u16>> {
+        let mut out = Vec::new();
+        while let Some(p) = jb.pop() {
+            out.push(match p {
+                Playout::Packet(p) => Some(p.header.sequence_number),
+                Playout::Lost { .. } | Playout::NonAudio { .. } => None,
+            });
+        }
+        out
+    }
+
+    #[test]
+    fn test_reorder_and_duplicate() {
+        let mut jb = JitterBuffer::new(3, 10);
+        let now = Instant::now();
+        assert_eq!(jb.push(packet(10, 1600), now), PushOutcome::Accepted);
+        assert_eq!(jb.push(packet(12, 1920), now), PushOutcome::Accepted);
+        assert_eq!(jb.push(packet(11, 1760), now), PushOutcome::Reordered);
+        assert_eq!(jb.push(packet(11, 1760), now), PushOutcome::Duplicate);
+        assert_eq!(played_seqs(&mut jb), vec![Some(10), Some(11), Some(12)]);
+        assert_eq!(jb.push(packet(9, 1440), now), PushOutcome::Late);
+    }
+
+    #[test]
+    fn test_sequence_wraparound() {
+        let mut jb = JitterBuffer::new(2, 10);
+        let now = Instant::now();
+        jb.push(packet(65534, 0), now);
+        jb.push(packet(1, 480), now);
+        jb.push(packet(65535, 160), now);
+        jb.push(packet(0, 320), now);
+        assert_eq!(
+            played_seqs(&mut jb),
+            vec![Some(65534), Some(65535), Some(0), Some(1)]
+        );
+    }
+
+    #[test]
+    fn test_telephone_events_are_not_loss() {
+        let mut jb = JitterBuffer::new(2, 10);
+        let now = Instant::now();
+        jb.push(packet(10, 0), now);
+        let mut event = RtpHeader::new(101, 11, 160, 0xABCD);
+        assert_eq!(jb.push_non_audio(&event, now), PushOutcome::Accepted);
+        event.sequence_number = 12;
+        jb.push_non_audio(&event, now);
+        jb.push(packet(13, 480), now);
+
+        let mut kinds = Vec::new();
+        while let Some(p) = jb.pop() {
+            kinds.push(match p {
+                Playout::Packet(p) => format!("audio {}", p.header.sequence_number),
+                Playout::NonAudio { samples } => format!("event {}", samples),
+                Playout::Lost { .. } => "lost".to_string(),
+            });
+        }
+        assert_eq!(kinds, ["audio 10", "event 160", "event 160", "audio 13"]);
+    }
+
+    #[test]
+    fn test_single_stray_packet_does_not_resync() {
+        let mut jb = JitterBuffer::new(1, 10);
+        let now = Instant::now();
+        jb.push(packet(100, 0), now);
+        assert_eq!(jb.push(packet(40000, 160), now), PushOutcome::Late);
+        assert_eq!(jb.push(packet(101, 160), now), PushOutcome::Accepted);
+        assert_eq!(played_seqs(&mut jb), vec![Some(100), Some(101)]);
+
+        // İki ardışık paket aynı yeni sırayı doğrularsa tek bir yeniden senkron olur.
+        assert_eq!(jb.push(packet(20000, 320), now), PushOutcome::Late);
+        assert_eq!(jb.push(packet(20001, 480), now), PushOutcome::Resynced);
+        assert_eq!(jb.push(packet(20002, 640), now), PushOutcome::Accepted);
+        assert_eq!(played_seqs(&mut jb), vec![Some(20001), Some(20002)]);
+    }
+
+    #[test]
+    fn test_loss_produces_concealment() {
+        let mut jb = JitterBuffer::new(2, 10);
+        let now = Instant::now();
+        jb.push(packet(100, 0), now);
+        jb.push(packet(102, 320), now);
+        assert_eq!(played_seqs(&mut jb), vec![Some(100), None, Some(102)]);
+    }
+
+    #[test]
+    fn test_target_depth_adapts_to_jitter() {
+        let mut jb = JitterBuffer::new(2, 10);
+        let start = Instant::now();
+        for i in 0..200u16 {
+            // Her paket ±40ms sapmayla geliyor.
+            let skew = if i % 2 == 0 { 0 } else { 40 };
+            let arrival = start + Duration::from_millis(i as u64 * 20 + skew);
+            jb.push(packet(i, i as u32 * 160), arrival);
+            jb.pop();
+        }
+        assert!(jb.target_depth() > 2);
+        assert!(jb.target_depth() <= 10);
+    }
+}
@@ -0,0 +1,532 @@
+// Dosya: src/rtp/live_stream.rs
+use crate::metrics::LIVE_STREAM_DROPPED_FRAMES_TOTAL;
+use crate::rtp::command::AudioFrame;
+use crate::rtp::resampler::Resampler;
+use anyhow::{anyhow, Result};
+use audiopus::coder::Encoder as OpusEncoder;
+use audiopus::{Application, Channels, SampleRate};
+use bytes::Bytes;
+use metrics::counter;
+use sentiric_rtp_core::{CodecFactory, CodecType, Encoder};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tonic::Status;
+use tracing::warn;
+
+/// RecordAudio canlı akışının desteklediği çıkış örnekleme hızları.
+pub const LIVE_STREAM_SAMPLE_RATES: [u32; 3] = [8000, 16000, 24000];
+pub const LIVE_STREAM_DEFAULT_SAMPLE_RATE: u32 = 16000;
+// RFC 7587 üst sınırı 1275 bayt; tek 20ms'lik paket için fazlasıyla yeterli.
+const OPUS_MAX_PACKET_BYTES: usize = 1500;
+
+/// Abonenin hangi yönün sesini aldığı.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveStreamChannel {
+    /// Arayan (rx_frame).
+    Caller,
+    /// Karşı tarafa gerçekten gönderilen bot sesi (tx_frame).
+    Bot,
+    /// Arayan + bot, mono toplanmış.
+    Mixed,
+    /// Sol: arayan, sağ: bot; örnekler iç içe (interleaved).
+    Stereo,
+}
+
+impl LiveStreamChannel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "caller" | "rx" => Some(Self::Caller),
+            "bot" | "tx" => Some(Self::Bot),
+            "mixed" => Some(Self::Mixed),
+            "stereo" | "both" => Some(Self::Stereo),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Caller => "caller",
+            Self::Bot => "bot",
+            Self::Mixed => "mixed",
+            Self::Stereo => "stereo",
+        }
+    }
+
+    pub fn channels(&self) -> u16 {
+        match self {
+            Self::Stereo => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Abonenin aldığı frame'lerin kodlaması.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveStreamEncoding {
+    /// Little-endian 16-bit PCM.
+    L16,
+    Pcmu,
+    Pcma,
+    /// Her frame tek bir 20ms'lik Opus paketidir.
+    Opus,
+}
+
+impl LiveStreamEncoding {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "l16" | "pcm" | "linear16" => Some(Self::L16),
+            "pcmu" | "ulaw" | "mulaw" | "g711u" => Some(Self::Pcmu),
+            "pcma" | "alaw" | "g711a" => Some(Self::Pcma),
+            "opus" => Some(Self::Opus),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::L16 => "L16",
+            Self::Pcmu => "PCMU",
+            Self::Pcma => "PCMA",
+            Self::Opus => "opus",
+        }
+    }
+
+    /// G.711 sadece 8 kHz tanımlıdır.
+    pub fn supports_sample_rate(&self, rate: u32) -> bool {
+        match self {
+            Self::Pcmu | Self::Pcma => rate == 8000,
+            Self::L16 | Self::Opus => LIVE_STREAM_SAMPLE_RATES.contains(&rate),
+        }
+    }
+
+    pub fn default_sample_rate(&self) -> u32 {
+        match self {
+            Self::Pcmu | Self::Pcma => 8000,
+            Self::L16 | Self::Opus => LIVE_STREAM_DEFAULT_SAMPLE_RATE,
+        }
+    }
+}
+
+/// Abonenin `x-live-stream-frame-tags` ile açtığı, ses frame'lerinin `media_type`'ına eklenen
+/// etiketler. Varsayılan `media_type` sabit kalır; etiket istemeyen tüketiciler etkilenmez.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LiveStreamFrameTags {
+    /// `;rtp_ts=N`: tick'in giden RTP zaman damgası.
+    pub rtp_ts: bool,
+    /// `;vad=speech|silence` (VAD_ENABLED açıksa).
+    pub vad: bool,
+}
+
+impl LiveStreamFrameTags {
+    /// Virgülle ayrılmış etiket listesi, örn. `rtp_ts,vad`.
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut tags = Self::default();
+        for tag in value.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            match tag.to_ascii_lowercase().as_str() {
+                "rtp_ts" => tags.rtp_ts = true,
+                "vad" => tags.vad = true,
+                _ => return None,
+            }
+        }
+        Some(tags)
+    }
+}
+
+enum FrameEncoder {
+    L16,
+    G711(Box<dyn Encoder>),
+    Opus {
+        encoder: OpusEncoder,
+        frame_samples: usize,
+        pending: Vec<i16>,
+    },
+}
+
+impl std::fmt::Debug for FrameEncoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::L16 => write!(f, "L16"),
+            Self::G711(encoder) => write!(f, "G711({:?})", encoder.get_type()),
+            Self::Opus { frame_samples, .. } => write!(f, "Opus({frame_samples})"),
+        }
+    }
+}
+
+impl FrameEncoder {
+    fn new(encoding: LiveStreamEncoding, sample_rate: u32, channels: u16) -> Result<Self> {
+        Ok(match encoding {
+            LiveStreamEncoding::L16 => Self::L16,
+            LiveStreamEncoding::Pcmu => Self::G711(CodecFactory::create_encoder(CodecType::PCMU)),
+            LiveStreamEncoding::Pcma => Self::G711(CodecFactory::create_encoder(CodecType::PCMA)),
+            LiveStreamEncoding::Opus => {
+                let rate = SampleRate::try_from(sample_rate as i32)
+                    .map_err(|e| anyhow!("Opus sample rate {}: {}", sample_rate, e))?;
+                let layout = if channels == 2 {
+                    Channels::Stereo
+                } else {
+                    Channels::Mono
+                };
+                let encoder = OpusEncoder::new(rate, layout, Application::Voip)
+                    .map_err(|e| anyhow!("Opus encoder oluşturulamadı: {}", e))?;
+                Self::Opus {
+                    encoder,
+                    frame_samples: sample_rate as usize / 50 * channels as usize,
+                    pending: Vec::new(),
+                }
+            }
+        })
+    }
+
+    /// Opus tam bir 20ms'lik frame birikene kadar `None` döndürür.
+    fn encode(&mut self, pcm: &[i16]) -> Option<Vec<u8>> {
+        match self {
+            Self::L16 => {
+                let mut data = Vec::with_capacity(pcm.len() * 2);
+                for s in pcm {
+                    data.extend_from_slice(&s.to_le_bytes());
+                }
+                Some(data)
+            }
+            Self::G711(encoder) => Some(encoder.encode(pcm)),
+            Self::Opus {
+                encoder,
+                frame_samples,
+                pending,
+            } => {
+                pending.extend_from_slice(pcm);
+                if pending.len() < *frame_samples {
+                    return None;
+                }
+                let frame: Vec<i16> = pending.drain(..*frame_samples).collect();
+                let mut packet = vec![0u8; OPUS_MAX_PACKET_BYTES];
+                match encoder.encode(&frame, &mut packet) {
+                    Ok(len) => {
+                        packet.truncate(len);
+                        Some(packet)
+                    }
+                    Err(e) => {
+                        warn!(
+                            event = "LIVE_STREAM_OPUS_ENCODE_FAILED",
+                            error = %e,
+                            "Opus frame kodlanamadı."
+                        );
+                        None
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LiveStreamSubscriber {
+    pub id: String,
+    pub sender: mpsc::Sender<Result<AudioFrame, Status>>,
+    pub sample_rate: u32,
+    pub channel: LiveStreamChannel,
+    pub encoding: LiveStreamEncoding,
+    pub frame_tags: LiveStreamFrameTags,
+    resampler: Resampler,
+    // Stereo aboneler için sağ (bot) kanalın dönüştürücüsü.
+    tx_resampler: Option<Resampler>,
+    encoder: FrameEncoder,
+    pub dropped_frames: u64,
+}
+
+impl LiveStreamSubscriber {
+    pub fn new(
+        id: String,
+        sender: mpsc::Sender<Result<AudioFrame, Status>>,
+        sample_rate: u32,
+        channel: LiveStreamChannel,
+        encoding: LiveStreamEncoding,
+        frame_tags: LiveStreamFrameTags,
+    ) -> Result<Self> {
+        if !encoding.supports_sample_rate(sample_rate) {
+            return Err(anyhow!(
+                "{} kodlaması {} Hz desteklemiyor",
+                encoding.as_str(),
+                sample_rate
+            ));
+        }
+        Ok(Self {
+            id,
+            sender,
+            sample_rate,
+            channel,
+            encoding,
+            frame_tags,
+            // Oturum içi ses her zaman 8 kHz'dir.
+            resampler: Resampler::new(8000, sample_rate),
+            tx_resampler: (channel == LiveStreamChannel::Stereo)
+                .then(|| Resampler::new(8000, sample_rate)),
+            encoder: FrameEncoder::new(encoding, sample_rate, channel.channels())?,
+            dropped_frames: 0,
+        })
+    }
+
+    pub fn media_type(&self) -> String {
+        let mut media_type = format!("audio/{};rate={}", self.encoding.as_str(), self.sample_rate);
+        if self.channel == LiveStreamChannel::Stereo {
+            media_type.push_str(";channels=2");
+        }
+        media_type
+    }
+
+    /// Tick'in 8 kHz sesini abonenin kanal düzenine ve hızına çevirir.
+    /// `None`: bu tick'te aboneye gidecek ses yok (sadece arayan dinleniyor ve arayan sessiz).
+    fn render(&mut self, rx: Option<&[i16]>, tx: &[i16]) -> Option<Vec<i16>> {
+        let rx_or_silence = |i: usize| rx.and_then(|rx| rx.get(i)).copied().unwrap_or(0);
+        match self.channel {
+            LiveStreamChannel::Caller => rx.map(|rx| self.resampler.process(rx)),
+            LiveStreamChannel::Bot => Some(self.resampler.process(tx)),
+            LiveStreamChannel::Mixed => {
+                let mixed: Vec<i16> = (0..tx.len())
+                    .map(|i| tx[i].saturating_add(rx_or_silence(i)))
+                    .collect();
+                Some(self.resampler.process(&mixed))
+            }
+            LiveStreamChannel::Stereo => {
+                let left_8k: Vec<i16> = (0..tx.len()).map(rx_or_silence).collect();
+                let left = self.resampler.process(&left_8k);
+                let right = self.tx_resampler.as_mut()?.process(tx);
+                Some(
+                    left.into_iter()
+                        .zip(right)
+                        .flat_map(|(l, r)| [l, r])
+                        .collect(),
+                )
+            }
+        }
+    }
+
+    /// Abonenin kendi kanalına bloklamadan gönderir. Dolu kanal sadece bu abonenin frame'ini
+    /// düşürür; diğer aboneler etkilenmez. `false`: abone bağlantıyı kapatmış.
+    fn try_send(&mut self, frame: AudioFrame) -> bool {
+        match self.sender.try_send(Ok(frame)) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                self.dropped_frames += 1;
+                counter!(LIVE_STREAM_DROPPED_FRAMES_TOTAL, "channel" => self.channel.as_str())
+                    .increment(1);
+                true
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
+/// Bir çağrının canlı akış abonelerine (STT, analiz, dinleyici) fan-out.
+#[derive(Debug, Default)]
+pub struct LiveStreamHub {
+    subscribers: Vec<LiveStreamSubscriber>,
+}
+
+impl LiveStreamHub {
+    /// Aynı ID ile yeniden abone olunursa eski akış değiştirilir (eski akış kapanır).
+    pub fn add(&mut self, subscriber: LiveStreamSubscriber) {
+        self.subscribers.retain(|s| s.id != subscriber.id);
+        self.subscribers.push(subscriber);
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.subscribers.len();
+        self.subscribers.retain(|s| s.id != id);
+        self.subscribers.len() != before
+    }
+
+    pub fn clear(&mut self) {
+        self.subscribers.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subscribers.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    /// Bir tick'in sesini her abonenin kanal/hız/kodlama tercihine göre dağıtır.
+    /// `rx`: bu tick'te arayandan ses geldiyse. `rtp_timestamp`: tick'in giden RTP zaman damgası
+    /// (8 kHz); iki yönün frame'leri aynı damgayı taşır. Bağlantısı kopan aboneleri çıkarıp döndürür.
+    pub fn publish_audio(
+        &mut self,
+        rx: Option<&[i16]>,
+        tx: &[i16],
+        rx_is_speech: Option<bool>,
+        rtp_timestamp: u32,
+    ) -> Vec<LiveStreamSubscriber> {
+        let mut disconnected = Vec::new();
+        let mut index = 0;
+        while index < self.subscribers.len() {
+            let subscriber = &mut self.subscribers[index];
+            let mut connected = !subscriber.sender.is_closed();
+            let data = if connected {
+                subscriber
+                    .render(rx, tx)
+                    .and_then(|pcm| subscriber.encoder.encode(&pcm))
+            } else {
+                None
+            };
+            if let Some(data) = data {
+                let mut media_type = subscriber.media_type();
+                if subscriber.frame_tags.rtp_ts {
+                    media_type.push_str(&format!(";rtp_ts={}", rtp_timestamp));
+                }
+                // İsteyen abonelerde arayan sesi içeren frame'ler işaretlenir; STT sessizliği atlayabilir.
+                if subscriber.frame_tags.vad && subscriber.channel != LiveStreamChannel::Bot {
+                    match rx_is_speech {
+                        Some(true) => media_type.push_str(";vad=speech"),
+                        Some(false) => media_type.push_str(";vad=silence"),
+                        None => {}
+                    }
+                }
+                connected = subscriber.try_send(AudioFrame {
+                    data: data.into(),
+                    media_type,
+                });
+            }
+
+            if connected {
+                index += 1;
+            } else {
+                disconnected.push(self.subscribers.remove(index));
+            }
+        }
+        disconnected
+    }
+
+    /// Ses dışı olayları (örn. DTMF) arayan sesini alan abonelere iletir.
+    pub fn publish_caller_event(&mut self, media_type: &str, data: Bytes) {
+        for subscriber in self
+            .subscribers
+            .iter_mut()
+            .filter(|s| s.channel != LiveStreamChannel::Bot)
+        {
+            subscriber.try_send(AudioFrame {
+                data: data.clone(),
+                media_type: media_type.to_string(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fan_out_backpressure_and_disconnect() {
+        let mut hub = LiveStreamHub::default();
+        let (fast_tx, mut fast_rx) = mpsc::channel(8);
+        let (slow_tx, mut slow_rx) = mpsc::channel(1);
+        let (gone_tx, gone_rx) = mpsc::channel(8);
+        hub.add(
+            LiveStreamSubscriber::new(
+                "stt".into(),
+                fast_tx,
+                16000,
+                LiveStreamChannel::Caller,
+                LiveStreamEncoding::L16,
+                LiveStreamFrameTags::parse("rtp_ts, vad").unwrap(),
+            )
+            .unwrap(),
+        );
+        hub.add(
+            LiveStreamSubscriber::new(
+                "qa".into(),
+                slow_tx,
+                8000,
+                LiveStreamChannel::Bot,
+                LiveStreamEncoding::L16,
+                LiveStreamFrameTags::default(),
+            )
+            .unwrap(),
+        );
+        hub.add(
+            LiveStreamSubscriber::new(
+                "gone".into(),
+                gone_tx,
+                8000,
+                LiveStreamChannel::Mixed,
+                LiveStreamEncoding::L16,
+                LiveStreamFrameTags::default(),
+            )
+            .unwrap(),
+        );
+        drop(gone_rx);
+
+        let rx = vec![100i16; 160];
+        let tx = vec![200i16; 160];
+        let removed = hub.publish_audio(Some(&rx), &tx, None, 160);
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].id, "gone");
+        hub.publish_audio(Some(&rx), &tx, Some(true), 320);
+
+        // Yavaş abone sadece kendi frame'ini kaybeder.
+        assert!(fast_rx.try_recv().is_ok());
+        let second = fast_rx.try_recv().unwrap().unwrap();
+        assert_eq!(
+            second.media_type,
+            "audio/L16;rate=16000;rtp_ts=320;vad=speech"
+        );
+        assert_eq!(second.data.len(), 320 * 2);
+
+        let bot = slow_rx.try_recv().unwrap().unwrap();
+        assert_eq!(bot.media_type, "audio/L16;rate=8000");
+        assert_eq!(&bot.data[..2], &200i16.to_le_bytes());
+        assert!(slow_rx.try_recv().is_err());
+        assert_eq!(hub.len(), 2);
+    }
+
+    #[test]
+    fn test_g711_and_stereo_frames() {
+        let (tx, mut rx) = mpsc::channel(4);
+        assert!(LiveStreamSubscriber::new(
+            "x".into(),
+            tx.clone(),
+            16000,
+            LiveStreamChannel::Caller,
+            LiveStreamEncoding::Pcma,
+            LiveStreamFrameTags::default(),
+        )
+        .is_err());
+
+        let (stereo_tx, mut stereo_rx) = mpsc::channel(4);
+        let mut hub = LiveStreamHub::default();
+        hub.add(
+            LiveStreamSubscriber::new(
+                "g711".into(),
+                tx,
+                8000,
+                LiveStreamChannel::Caller,
+                LiveStreamEncoding::parse("ulaw").unwrap(),
+                LiveStreamFrameTags::default(),
+            )
+            .unwrap(),
+        );
+        hub.add(
+            LiveStreamSubscriber::new(
+                "qa".into(),
+                stereo_tx,
+                8000,
+                LiveStreamChannel::parse("stereo").unwrap(),
+                LiveStreamEncoding::L16,
+                LiveStreamFrameTags::default(),
+            )
+            .unwrap(),
+        );
+        // Etiket istemeyen abonelere VAD durumu eklenmez.
+        hub.publish_audio(Some(&[100i16; 160]), &[200i16; 160], Some(true), 42);
+        assert!(LiveStreamFrameTags::parse("vad,bogus").is_none());
+
+        let frame = rx.try_recv().unwrap().unwrap();
+        assert_eq!(frame.media_type, "audio/PCMU;rate=8000");
+        assert_eq!(frame.data.len(), 160);
+
+        let frame = stereo_rx.try_recv().unwrap().unwrap();
+        assert_eq!(frame.media_type, "audio/L16;rate=8000;channels=2");
+        assert_eq!(frame.data.len(), 160 * 2 * 2);
+        assert_eq!(&frame.data[..4], &[100, 0, 200, 0]);
+    }
+}
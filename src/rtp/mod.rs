@@ -1,9 +1,23 @@
 // sentiric-media-service/src/rtp/mod.rs
+pub mod aec;
 pub mod codecs;
 pub mod command;
+pub mod dsp;
+pub mod dtmf;
+pub mod encryption;
+pub mod flac;
 pub mod handlers;
+pub mod jitter;
+pub mod live_stream;
 pub mod processing;
+pub mod recording;
+pub mod resampler;
+pub mod rtcp;
 pub mod session;
 pub mod session_handlers;
 pub mod session_utils;
+pub mod spool;
+pub mod stats;
+pub mod store;
+pub mod vad;
 pub mod writers; // YENİ EKLENDİ
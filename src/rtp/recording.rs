@@ -0,0 +1,509 @@
+// Dosya: src/rtp/recording.rs
+use crate::rtp::encryption::RecordingEncryptor;
+use crate::rtp::flac::FlacEncoder;
+use crate::rtp::spool::RecordingSpool;
+use crate::rtp::writers::{
+    wav_header, RecordingTarget, StreamingUpload, UploadOutcome, WAV_STREAMING_DATA_LEN,
+};
+use anyhow::{anyhow, Result};
+use audiopus::coder::Encoder as OpusEncoder;
+use audiopus::{Application, Bitrate, Channels, SampleRate};
+use hound::WavSpec;
+use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+use std::sync::Arc;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+const OPUS_MAX_PACKET_BYTES: usize = 1500;
+// Opus granül pozisyonu her zaman 48 kHz örnek cinsindendir.
+const OPUS_GRANULE_RATE: u64 = 48000;
+// Kodlayıcıya kanal başına ~1 sn'lik bloklar hâlinde gönderilir.
+const CHUNK_MS: usize = 1000;
+// Kodlayıcı en fazla bu kadar blok (~30 sn) geride kalabilir. Yükleme kodlayıcıyı
+// bekletmediği için (depo yavaşsa parçalar spool'a akar) bu yalnızca CPU yetmezliğinde dolar.
+const MAX_QUEUED_CHUNKS: usize = 30;
+
+/// Kayıt dosya biçimi. `output_uri` uzantısından veya `?format=` parametresinden seçilir.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingFormat {
+    Wav,
+    Flac,
+    OggOpus,
+}
+
+impl RecordingFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "wav" => Some(Self::Wav),
+            "flac" => Some(Self::Flac),
+            "opus" | "ogg" => Some(Self::OggOpus),
+            _ => None,
+        }
+    }
+
+    /// Yol uzantısına göre biçim. Uzantı yoksa `Ok(None)`, bilinmeyen uzantıda `Err(uzantı)`.
+    pub fn from_path(path: &str) -> Result<Option<Self>, String> {
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        match file_name.rsplit_once('.') {
+            Some((_, ext)) => Self::parse(ext).map(Some).ok_or_else(|| ext.to_string()),
+            None => Ok(None),
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Flac => "flac",
+            Self::OggOpus => "opus",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Wav => "audio/wav",
+            Self::Flac => "audio/flac",
+            Self::OggOpus => "audio/ogg",
+        }
+    }
+}
+
+/// Ogg kapsayıcısında Opus (RFC 7845): OpusHead ve OpusTags sayfaları, ardından 20 ms'lik
+/// paketler. Son paket akışı kapatabilmek için bir sonraki paket gelene kadar bekletilir.
+struct OggOpusEncoder {
+    encoder: OpusEncoder,
+    writer: PacketWriter<Vec<u8>>,
+    serial: u32,
+    channels: usize,
+    frame_samples: usize,
+    granule_step: u64,
+    pre_skip: u64,
+    input_rate: u64,
+    input_samples: u64,
+    granule: u64,
+    pending: Vec<i16>,
+    held: Option<(Vec<u8>, u64)>,
+    packets_since_page: usize,
+}
+
+impl std::fmt::Debug for OggOpusEncoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OggOpusEncoder")
+            .field("channels", &self.channels)
+            .field("granule", &self.granule)
+            .finish()
+    }
+}
+
+impl OggOpusEncoder {
+    fn new(spec: &WavSpec) -> Result<Self> {
+        let rate = SampleRate::try_from(spec.sample_rate as i32)
+            .map_err(|e| anyhow!("Opus için geçersiz hız {}: {}", spec.sample_rate, e))?;
+        let channels = if spec.channels == 2 {
+            Channels::Stereo
+        } else {
+            Channels::Mono
+        };
+        let mut encoder = OpusEncoder::new(rate, channels, Application::Voip)
+            .map_err(|e| anyhow!("Opus kodlayıcı oluşturulamadı: {}", e))?;
+        // Uzun süre saklanacak konuşma kaydı: kanal başına 16 kbps yeterli.
+        encoder
+            .set_bitrate(Bitrate::BitsPerSecond(16000 * spec.channels as i32))
+            .map_err(|e| anyhow!("Opus bit hızı ayarlanamadı: {}", e))?;
+        let lookahead = encoder
+            .lookahead()
+            .map_err(|e| anyhow!("Opus lookahead okunamadı: {}", e))?;
+        let pre_skip = lookahead as u64 * OPUS_GRANULE_RATE / spec.sample_rate as u64;
+
+        Ok(Self {
+            encoder,
+            writer: PacketWriter::new(Vec::new()),
+            serial: rand::random(),
+            channels: spec.channels as usize,
+            frame_samples: spec.sample_rate as usize / 50,
+            granule_step: OPUS_GRANULE_RATE / 50,
+            pre_skip,
+            input_rate: spec.sample_rate as u64,
+            input_samples: 0,
+            granule: 0,
+            pending: Vec::new(),
+            held: None,
+            packets_since_page: 0,
+        })
+    }
+
+    fn header(&mut self) -> Result<Vec<u8>> {
+        let mut head = Vec::with_capacity(19);
+        head.extend_from_slice(b"OpusHead");
+        head.push(1);
+        head.push(self.channels as u8);
+        head.extend_from_slice(&(self.pre_skip as u16).to_le_bytes());
+        head.extend_from_slice(&(self.input_rate as u32).to_le_bytes());
+        head.extend_from_slice(&0i16.to_le_bytes());
+        head.push(0);
+        self.writer.write_packet(
+            head.into_boxed_slice(),
+            self.serial,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+
+        let vendor = concat!("sentiric-media-service ", env!("CARGO_PKG_VERSION"));
+        let mut tags = Vec::new();
+        tags.extend_from_slice(b"OpusTags");
+        tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        tags.extend_from_slice(vendor.as_bytes());
+        tags.extend_from_slice(&0u32.to_le_bytes());
+        self.writer.write_packet(
+            tags.into_boxed_slice(),
+            self.serial,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )?;
+        Ok(std::mem::take(self.writer.inner_mut()))
+    }
+
+    fn encode(&mut self, interleaved: &[i16]) -> Result<Vec<u8>> {
+        self.input_samples += (interleaved.len() / self.channels) as u64;
+        self.pending.extend_from_slice(interleaved);
+        let frame_len = self.frame_samples * self.channels;
+        while self.pending.len() >= frame_len {
+            let frame: Vec<i16> = self.pending.drain(..frame_len).collect();
+            self.encode_frame(&frame)?;
+        }
+        Ok(std::mem::take(self.writer.inner_mut()))
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>> {
+        // Kodlayıcı gecikmesi (pre-skip) dahil tüm girişin çözülebilmesi için sessizlikle doldurulur.
+        let end_granule = self.pre_skip + self.input_samples * OPUS_GRANULE_RATE / self.input_rate;
+        let frame_len = self.frame_samples * self.channels;
+        while !self.pending.is_empty() || self.granule < end_granule {
+            let mut frame: Vec<i16> = self
+                .pending
+                .drain(..frame_len.min(self.pending.len()))
+                .collect();
+            frame.resize(frame_len, 0);
+            self.encode_frame(&frame)?;
+        }
+        if let Some((packet, _)) = self.held.take() {
+            self.writer.write_packet(
+                packet.into_boxed_slice(),
+                self.serial,
+                PacketWriteEndInfo::EndStream,
+                end_granule,
+            )?;
+        }
+        Ok(std::mem::take(self.writer.inner_mut()))
+    }
+
+    fn encode_frame(&mut self, frame: &[i16]) -> Result<()> {
+        let mut packet = vec![0u8; OPUS_MAX_PACKET_BYTES];
+        let len = self
+            .encoder
+            .encode(frame, &mut packet)
+            .map_err(|e| anyhow!("Opus kodlama hatası: {}", e))?;
+        packet.truncate(len);
+        self.granule += self.granule_step;
+
+        if let Some((previous, granule)) = self.held.replace((packet, self.granule)) {
+            // Sayfalar ~1 sn'de bir kapatılır; yarım kalan kayıpta en fazla bu kadar ses gider.
+            self.packets_since_page += 1;
+            let end = if self.packets_since_page >= 50 {
+                self.packets_since_page = 0;
+                PacketWriteEndInfo::EndPage
+            } else {
+                PacketWriteEndInfo::NormalPacket
+            };
+            self.writer
+                .write_packet(previous.into_boxed_slice(), self.serial, end, granule)?;
+        }
+        Ok(())
+    }
+}
+
+/// Seçilen biçimde PCM kodlayıcı. İlk baytlar `header`, son baytlar `finish` ile alınır;
+/// uzunluk bilgisi taşıyan başlıklar (WAV, FLAC STREAMINFO) aynı boyda `final_header` ile
+/// yeniden üretilir ve akışın başına yamalanır.
+#[derive(Debug)]
+enum RecordingEncoder {
+    Wav { spec: WavSpec, data_len: u64 },
+    Flac(FlacEncoder),
+    OggOpus(Box<OggOpusEncoder>),
+}
+
+impl RecordingEncoder {
+    fn new(format: RecordingFormat, spec: WavSpec) -> Result<Self> {
+        Ok(match format {
+            RecordingFormat::Wav => Self::Wav { spec, data_len: 0 },
+            RecordingFormat::Flac => Self::Flac(FlacEncoder::new(spec.sample_rate, spec.channels)?),
+            RecordingFormat::OggOpus => Self::OggOpus(Box::new(OggOpusEncoder::new(&spec)?)),
+        })
+    }
+
+    fn header(&mut self) -> Result<Vec<u8>> {
+        match self {
+            Self::Wav { spec, .. } => Ok(wav_header(spec, WAV_STREAMING_DATA_LEN).to_vec()),
+            Self::Flac(encoder) => Ok(encoder.header()),
+            Self::OggOpus(encoder) => encoder.header(),
+        }
+    }
+
+    fn encode(&mut self, interleaved: &[i16]) -> Result<Vec<u8>> {
+        match self {
+            Self::Wav { data_len, .. } => {
+                *data_len += interleaved.len() as u64 * 2;
+                Ok(interleaved.iter().flat_map(|s| s.to_le_bytes()).collect())
+            }
+            Self::Flac(encoder) => Ok(encoder.encode(interleaved)),
+            Self::OggOpus(encoder) => encoder.encode(interleaved),
+        }
+    }
+
+    fn finish(&mut self) -> Result<Vec<u8>> {
+        match self {
+            Self::Wav { .. } => Ok(Vec::new()),
+            Self::Flac(encoder) => Ok(encoder.finish()),
+            Self::OggOpus(encoder) => encoder.finish(),
+        }
+    }
+
+    fn final_header(&self) -> Result<Option<Vec<u8>>> {
+        match self {
+            Self::Wav { spec, data_len } => {
+                let data_len = u32::try_from(*data_len)
+                    .map_err(|_| anyhow!("Kayıt WAV sınırını (4 GiB) aşıyor"))?;
+                Ok(Some(wav_header(spec, data_len).to_vec()))
+            }
+            Self::Flac(encoder) => Ok(Some(encoder.header())),
+            Self::OggOpus(_) => Ok(None),
+        }
+    }
+
+    /// WAV için kodlama yalnızca bayt kopyasıdır; diğerleri RTP runtime'ını meşgul etmemek
+    /// için blocking havuzunda çalıştırılır.
+    fn is_cpu_bound(&self) -> bool {
+        !matches!(self, Self::Wav { .. })
+    }
+}
+
+/// Kaydın kodlama + yükleme hattı. Oturum döngüsü yalnızca PCM biriktirir; dolan bloklar
+/// kanal üzerinden kayda özel bir göreve aktarılır, orada kodlanıp hedefe akıtılır.
+#[derive(Debug)]
+pub struct RecordingPipeline {
+    call_id: String,
+    format: RecordingFormat,
+    uri: String,
+    sidecar: RecordingTarget,
+    key_id: Option<String>,
+    chunk_len: usize,
+    pending: Vec<i16>,
+    chunks_tx: Option<mpsc::Sender<Vec<i16>>>,
+    worker: JoinHandle<Result<(u64, UploadOutcome)>>,
+}
+
+impl RecordingPipeline {
+    pub fn start(
+        target: RecordingTarget,
+        format: RecordingFormat,
+        spec: WavSpec,
+        call_id: String,
+        encryptor: Option<RecordingEncryptor>,
+        spool: Option<Arc<RecordingSpool>>,
+    ) -> Result<Self> {
+        let encoder = RecordingEncoder::new(format, spec)?;
+        let uri = target.uri();
+        let sidecar = target.sidecar();
+        let key_id = encryptor.as_ref().map(|e| e.key_id().to_string());
+        // Şifreli nesne doğrudan çalınamaz; ses türü metadata'dan okunur.
+        let content_type = match encryptor {
+            Some(_) => "application/octet-stream",
+            None => format.content_type(),
+        };
+        let upload = StreamingUpload::new(target, content_type, call_id.clone(), encryptor, spool);
+        let (chunks_tx, chunks_rx) = mpsc::channel(MAX_QUEUED_CHUNKS);
+        let worker = tokio::spawn(run_encoder(encoder, upload, chunks_rx, call_id.clone()));
+        let chunk_len = spec.sample_rate as usize * spec.channels as usize * CHUNK_MS / 1000;
+        Ok(Self {
+            call_id,
+            format,
+            uri,
+            sidecar,
+            key_id,
+            chunk_len,
+            pending: Vec::with_capacity(chunk_len),
+            chunks_tx: Some(chunks_tx),
+            worker,
+        })
+    }
+
+    pub fn format(&self) -> RecordingFormat {
+        self.format
+    }
+
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Kaydın yanına yazılacak metadata nesnesinin hedefi.
+    pub fn sidecar(&self) -> &RecordingTarget {
+        &self.sidecar
+    }
+
+    /// Zarf şifrelemesinde kullanılan kiracı anahtarının kimliği.
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    /// İç içe (interleaved) PCM ekler. RTP döngüsünü bloklamaz.
+    pub fn push(&mut self, interleaved: &[i16]) {
+        if self.chunks_tx.is_none() {
+            return;
+        }
+        self.pending.extend_from_slice(interleaved);
+        if self.pending.len() >= self.chunk_len {
+            let chunk = std::mem::replace(&mut self.pending, Vec::with_capacity(self.chunk_len));
+            self.send(chunk);
+        }
+    }
+
+    fn send(&mut self, chunk: Vec<i16>) {
+        let Some(tx) = &self.chunks_tx else {
+            return;
+        };
+        match tx.try_send(chunk) {
+            // Görev hata verip durduysa gönderim başarısız olur; hata `finish`te döner.
+            Ok(()) | Err(TrySendError::Closed(_)) => {}
+            // Kodlayıcı yetişemiyor: kayıt burada kesilir, o ana kadarki kısım kapatılıp yüklenir.
+            Err(TrySendError::Full(_)) => {
+                error!(event = "RECORDING_TRUNCATED", sip.call_id = %self.call_id, "🔥 Kayıt kodlayıcısı geride kaldı, kayıt bu noktada kesildi!");
+                self.chunks_tx = None;
+            }
+        }
+    }
+
+    /// Kalanı kodlar, başlığı yamalar ve yüklemeyi tamamlar. Yazılan toplam baytı döndürür;
+    /// hiç ses gelmediyse 0 döner ve hedefe hiçbir şey yazılmaz.
+    pub async fn finish(mut self) -> Result<(u64, UploadOutcome)> {
+        if let Some(tx) = self.chunks_tx.take() {
+            if !self.pending.is_empty() {
+                let _ = tx.send(std::mem::take(&mut self.pending)).await;
+            }
+        }
+        self.worker
+            .await
+            .map_err(|e| anyhow!("Kayıt kodlayıcı görevi çöktü: {}", e))?
+    }
+}
+
+async fn encode_chunk(
+    mut encoder: RecordingEncoder,
+    chunk: Vec<i16>,
+) -> Result<(RecordingEncoder, Vec<u8>)> {
+    if !encoder.is_cpu_bound() {
+        let bytes = encoder.encode(&chunk)?;
+        return Ok((encoder, bytes));
+    }
+    tokio::task::spawn_blocking(move || {
+        let bytes = encoder.encode(&chunk)?;
+        Ok((encoder, bytes))
+    })
+    .await
+    .map_err(|e| anyhow!("Kodlama görevi çöktü: {}", e))?
+}
+
+async fn run_encoder(
+    mut encoder: RecordingEncoder,
+    mut upload: StreamingUpload,
+    mut chunks_rx: mpsc::Receiver<Vec<i16>>,
+    call_id: String,
+) -> Result<(u64, UploadOutcome)> {
+    let mut started = false;
+    let encoded = async {
+        while let Some(chunk) = chunks_rx.recv().await {
+            if !started {
+                upload.write(&encoder.header()?);
+                started = true;
+            }
+            let (next, bytes) = encode_chunk(encoder, chunk).await?;
+            encoder = next;
+            upload.write(&bytes);
+        }
+        if started {
+            let (done, tail) = tokio::task::spawn_blocking(move || {
+                let tail = encoder.finish();
+                (encoder, tail)
+            })
+            .await
+            .map_err(|e| anyhow!("Kodlama görevi çöktü: {}", e))?;
+            upload.write(&tail?);
+            return done.final_header();
+        }
+        Ok(None)
+    }
+    .await;
+
+    match encoded {
+        Ok(_) if !started => {
+            debug!(event = "RECORDING_EMPTY", sip.call_id = %call_id, "Kayda hiç ses yazılmadı.");
+            Ok((0, UploadOutcome::Stored))
+        }
+        Ok(header) => {
+            let len = upload.len();
+            let outcome = upload.finish(header.as_deref()).await?;
+            Ok((len, outcome))
+        }
+        Err(e) => {
+            warn!(event = "RECORDING_ENCODE_FAILED", sip.call_id = %call_id, error = %e, "❌ Kayıt kodlanamadı, yükleme iptal ediliyor.");
+            upload.abort().await;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_uri() {
+        assert_eq!(
+            RecordingFormat::from_path("calls/a.b/kayit.FLAC"),
+            Ok(Some(RecordingFormat::Flac))
+        );
+        assert_eq!(RecordingFormat::from_path("calls/a.b/kayit"), Ok(None));
+        assert_eq!(
+            RecordingFormat::from_path("kayit.mp3"),
+            Err("mp3".to_string())
+        );
+        assert_eq!(
+            RecordingFormat::parse("ogg"),
+            Some(RecordingFormat::OggOpus)
+        );
+    }
+
+    #[test]
+    fn test_ogg_opus_length_survives_pre_skip_and_padding() {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        // Çerçeve (160) katı olmayan uzunluk: son paket dolgulu kapanır.
+        let pcm: Vec<i16> = (0..8037).map(|i| ((i % 40) as i16 - 20) * 500).collect();
+        let mut encoder = OggOpusEncoder::new(&spec).unwrap();
+        let mut file = encoder.header().unwrap();
+        for chunk in pcm.chunks(1000) {
+            file.extend(encoder.encode(chunk).unwrap());
+        }
+        file.extend(encoder.finish().unwrap());
+
+        let pre_skip = u16::from_le_bytes([file[28 + 10], file[28 + 11]]) as u64;
+        assert_eq!(pre_skip, encoder.pre_skip);
+        assert!(pre_skip > 0);
+        let (decoded, rate, channels) = crate::audio::decode_ogg_opus(file).unwrap();
+        assert_eq!((rate, channels), (48000, 1));
+        assert_eq!(decoded.len(), pcm.len() * 6);
+    }
+}
@@ -0,0 +1,181 @@
+// Dosya: src/rtp/resampler.rs
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+// Düşük hızın Nyquist'ine göre filtrenin bir yanındaki sinc sıfır geçişi sayısı.
+const ZERO_CROSSINGS: usize = 16;
+// Geçiş bandı: kesim frekansı düşük Nyquist'in bu oranında (aliasing payı).
+const ROLLOFF: f64 = 0.92;
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Polifaz pencereli-sinc (Blackman) rasyonel örnekleme hızı dönüştürücü.
+/// `simple_resample`'ın aksine bant sınırlıdır (aliasing/imaging üretmez) ve frame'ler
+/// arasında durum tutar; 20ms'lik parçalar kesintisiz birleşir.
+#[derive(Debug)]
+pub struct Resampler {
+    up: u64,
+    down: u64,
+    // phases[p][k] = h[p + k * up]
+    phases: Vec<Vec<f32>>,
+    history: VecDeque<f32>,
+    input_count: u64,
+    output_count: u64,
+}
+
+impl Resampler {
+    pub fn new(from_rate: u32, to_rate: u32) -> Self {
+        let g = gcd(from_rate, to_rate).max(1);
+        let up = (to_rate / g).max(1) as usize;
+        let down = (from_rate / g).max(1) as usize;
+
+        if up == down {
+            return Self {
+                up: 1,
+                down: 1,
+                phases: vec![vec![1.0]],
+                history: VecDeque::from(vec![0.0]),
+                input_count: 0,
+                output_count: 0,
+            };
+        }
+
+        let factor = up.max(down) as f64;
+        let taps_per_phase =
+            ((2 * ZERO_CROSSINGS) as f64 * factor / (up as f64 * ROLLOFF)).ceil() as usize;
+        let len = taps_per_phase * up;
+        // Yukarı örneklenmiş alanda normalize kesim frekansı (cycle/örnek).
+        let cutoff = 0.5 * ROLLOFF / factor;
+        let center = (len - 1) as f64 / 2.0;
+
+        let taps: Vec<f64> = (0..len)
+            .map(|n| {
+                let x = n as f64 - center;
+                let sinc = if x == 0.0 {
+                    2.0 * cutoff
+                } else {
+                    (2.0 * PI * cutoff * x).sin() / (PI * x)
+                };
+                let phase = 2.0 * PI * n as f64 / (len - 1) as f64;
+                let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+                // Araya sıfır eklemenin (zero-stuffing) kaybettirdiği kazancı geri ver.
+                sinc * window * up as f64
+            })
+            .collect();
+
+        let phases = (0..up)
+            .map(|p| {
+                (0..taps_per_phase)
+                    .map(|k| taps[p + k * up] as f32)
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            up: up as u64,
+            down: down as u64,
+            phases,
+            history: std::iter::repeat_n(0.0, taps_per_phase).collect(),
+            input_count: 0,
+            output_count: 0,
+        }
+    }
+
+    /// Filtrenin çıkış örneği cinsinden grup gecikmesi.
+    pub fn delay(&self) -> usize {
+        let len = self.phases.len() * self.phases[0].len();
+        ((len - 1) as u64 / 2 / self.down) as usize
+    }
+
+    pub fn process(&mut self, input: &[i16]) -> Vec<i16> {
+        let capacity = (input.len() as u64 * self.up / self.down) as usize + 1;
+        let mut output = Vec::with_capacity(capacity);
+        for &sample in input {
+            self.history.pop_front();
+            self.history.push_back(sample as f32);
+            self.input_count += 1;
+
+            // Taban indeksi bu girdi olan tüm çıkışları üret.
+            loop {
+                let position = self.output_count * self.down;
+                if position / self.up + 1 != self.input_count {
+                    break;
+                }
+                let phase = &self.phases[(position % self.up) as usize];
+                let acc: f32 = phase
+                    .iter()
+                    .zip(self.history.iter().rev())
+                    .map(|(h, x)| h * x)
+                    .sum();
+                output.push(acc.round().clamp(i16::MIN as f32, i16::MAX as f32) as i16);
+                self.output_count += 1;
+            }
+        }
+        output
+    }
+}
+
+/// Tek seferlik dönüşüm: filtre gecikmesi telafi edilir, çıkış uzunluğu `len * to / from` olur.
+pub fn resample(input: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16> {
+    if from_rate == to_rate || input.is_empty() {
+        return input.to_vec();
+    }
+    let mut resampler = Resampler::new(from_rate, to_rate);
+    let expected = (input.len() as u64 * to_rate as u64 / from_rate as u64) as usize;
+    let delay = resampler.delay();
+
+    let mut output = resampler.process(input);
+    let tail_inputs = (delay as u64 * from_rate as u64 / to_rate as u64) as usize + 2;
+    output.extend(resampler.process(&vec![0; tail_inputs]));
+    output.drain(..delay.min(output.len()));
+    output.truncate(expected);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(freq: f64, rate: u32, len: usize) -> Vec<i16> {
+        (0..len)
+            .map(|n| (10000.0 * (2.0 * PI * freq * n as f64 / rate as f64).sin()) as i16)
+            .collect()
+    }
+
+    fn rms(samples: &[i16]) -> f64 {
+        (samples.iter().map(|&s| (s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn test_streaming_matches_rate_and_preserves_passband() {
+        for to_rate in [16000, 24000] {
+            let mut resampler = Resampler::new(8000, to_rate);
+            let input = tone(1000.0, 8000, 8000);
+            let mut output = Vec::new();
+            for chunk in input.chunks(160) {
+                output.extend(resampler.process(chunk));
+            }
+            assert_eq!(output.len(), 8000 * (to_rate as usize / 8000));
+            let ratio = rms(&output[2000..]) / rms(&input[1000..]);
+            assert!((ratio - 1.0).abs() < 0.02, "{to_rate}: {ratio}");
+        }
+    }
+
+    #[test]
+    fn test_downsampling_rejects_aliases() {
+        // 16k -> 8k: 6 kHz ton, 8 kHz çıkışta 2 kHz'e katlanmamalı.
+        let output = resample(&tone(6000.0, 16000, 16000), 16000, 8000);
+        assert_eq!(output.len(), 8000);
+        assert!(rms(&output[500..7500]) < 10000.0 * 0.01);
+
+        let output = resample(&tone(1000.0, 44100, 44100), 44100, 8000);
+        assert_eq!(output.len(), 8000);
+        assert!((rms(&output[500..7500]) / (10000.0 / 2f64.sqrt()) - 1.0).abs() < 0.02);
+    }
+}
@@ -0,0 +1,513 @@
+// Dosya: src/rtp/rtcp.rs
+use crate::rtp::stats::{IntervalMark, ReceptionStats};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::time::{Duration, Instant};
+
+pub const PT_SR: u8 = 200;
+pub const PT_RR: u8 = 201;
+pub const PT_SDES: u8 = 202;
+pub const PT_BYE: u8 = 203;
+
+const SDES_CNAME: u8 = 1;
+// 1900-01-01 (NTP epoch) ile 1970-01-01 (Unix epoch) arası saniye.
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportBlock {
+    pub ssrc: u32,
+    pub fraction_lost: u8,
+    pub cumulative_lost: i32,
+    pub highest_seq: u32,
+    pub jitter: u32,
+    pub lsr: u32,
+    pub dlsr: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SenderInfo {
+    pub ntp_sec: u32,
+    pub ntp_frac: u32,
+    pub rtp_timestamp: u32,
+    pub packet_count: u32,
+    pub octet_count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RtcpPacket {
+    SenderReport {
+        ssrc: u32,
+        info: SenderInfo,
+        reports: Vec<ReportBlock>,
+    },
+    ReceiverReport {
+        ssrc: u32,
+        reports: Vec<ReportBlock>,
+    },
+    SourceDescription {
+        chunks: Vec<(u32, Option<String>)>,
+    },
+    Bye {
+        ssrcs: Vec<u32>,
+        reason: Option<String>,
+    },
+    Other {
+        packet_type: u8,
+    },
+}
+
+/// RFC 5761 4: RTP ve RTCP aynı portta (rtcp-mux) gelirse ikinci bayt 192-223 aralığındadır.
+pub fn is_rtcp(data: &[u8]) -> bool {
+    data.len() >= 8 && (data[0] >> 6) == 2 && (192..=223).contains(&data[1])
+}
+
+/// Compound RTCP paketini ayrıştırır. Bozuk bir alt paket görülürse o noktaya kadar okunanlar döner.
+pub fn parse_compound(data: &[u8]) -> Vec<RtcpPacket> {
+    let mut packets = Vec::new();
+    let mut offset = 0;
+
+    while offset + 4 <= data.len() {
+        let header = &data[offset..];
+        if (header[0] >> 6) != 2 {
+            break;
+        }
+        let count = (header[0] & 0x1F) as usize;
+        let packet_type = header[1];
+        let len = (u16::from_be_bytes([header[2], header[3]]) as usize + 1) * 4;
+        if offset + len > data.len() {
+            break;
+        }
+
+        let mut body = &data[offset + 4..offset + len];
+        if header[0] & 0x20 != 0 {
+            // Padding: son bayt padding uzunluğudur.
+            let pad = *body.last().unwrap_or(&0) as usize;
+            body = &body[..body.len().saturating_sub(pad)];
+        }
+
+        let parsed = match packet_type {
+            PT_SR => parse_sender_report(body, count),
+            PT_RR => parse_receiver_report(body, count),
+            PT_SDES => Some(parse_sdes(body, count)),
+            PT_BYE => Some(parse_bye(body, count)),
+            other => Some(RtcpPacket::Other { packet_type: other }),
+        };
+        match parsed {
+            Some(p) => packets.push(p),
+            None => break,
+        }
+        offset += len;
+    }
+    packets
+}
+
+fn read_u32(b: &[u8], at: usize) -> Option<u32> {
+    b.get(at..at + 4)
+        .map(|s| u32::from_be_bytes([s[0], s[1], s[2], s[3]]))
+}
+
+fn parse_report_blocks(body: &[u8], count: usize) -> Option<Vec<ReportBlock>> {
+    let mut reports = Vec::with_capacity(count);
+    for i in 0..count {
+        let b = body.get(i * 24..i * 24 + 24)?;
+        // 24-bit işaretli kümülatif kayıp
+        let lost_raw = ((b[5] as i32) << 16) | ((b[6] as i32) << 8) | b[7] as i32;
+        let cumulative_lost = (lost_raw << 8) >> 8;
+        reports.push(ReportBlock {
+            ssrc: read_u32(b, 0)?,
+            fraction_lost: b[4],
+            cumulative_lost,
+            highest_seq: read_u32(b, 8)?,
+            jitter: read_u32(b, 12)?,
+            lsr: read_u32(b, 16)?,
+            dlsr: read_u32(b, 20)?,
+        });
+    }
+    Some(reports)
+}
+
+fn parse_sender_report(body: &[u8], count: usize) -> Option<RtcpPacket> {
+    let info = SenderInfo {
+        ntp_sec: read_u32(body, 4)?,
+        ntp_frac: read_u32(body, 8)?,
+        rtp_timestamp: read_u32(body, 12)?,
+        packet_count: read_u32(body, 16)?,
+        octet_count: read_u32(body, 20)?,
+    };
+    Some(RtcpPacket::SenderReport {
+        ssrc: read_u32(body, 0)?,
+        info,
+        reports: parse_report_blocks(body.get(24..)?, count)?,
+    })
+}
+
+fn parse_receiver_report(body: &[u8], count: usize) -> Option<RtcpPacket> {
+    Some(RtcpPacket::ReceiverReport {
+        ssrc: read_u32(body, 0)?,
+        reports: parse_report_blocks(body.get(4..)?, count)?,
+    })
+}
+
+fn parse_sdes(body: &[u8], count: usize) -> RtcpPacket {
+    let mut chunks = Vec::with_capacity(count);
+    let mut offset = 0;
+    for _ in 0..count {
+        let Some(ssrc) = read_u32(body, offset) else {
+            break;
+        };
+        offset += 4;
+        let mut cname = None;
+        // Öğeler END (0) baytı ile biter, chunk 32-bit sınırına hizalanır.
+        while offset < body.len() && body[offset] != 0 {
+            let item_type = body[offset];
+            let Some(&item_len) = body.get(offset + 1) else {
+                break;
+            };
+            let start = offset + 2;
+            let end = (start + item_len as usize).min(body.len());
+            if item_type == SDES_CNAME {
+                cname = Some(String::from_utf8_lossy(&body[start..end]).into_owned());
+            }
+            offset = end;
+        }
+        offset = (offset + 4) & !3;
+        chunks.push((ssrc, cname));
+    }
+    RtcpPacket::SourceDescription { chunks }
+}
+
+fn parse_bye(body: &[u8], count: usize) -> RtcpPacket {
+    let ssrcs: Vec<u32> = (0..count).filter_map(|i| read_u32(body, i * 4)).collect();
+    let reason_at = count * 4;
+    let reason = body.get(reason_at).and_then(|&len| {
+        body.get(reason_at + 1..reason_at + 1 + len as usize)
+            .map(|r| String::from_utf8_lossy(r).into_owned())
+    });
+    RtcpPacket::Bye { ssrcs, reason }
+}
+
+fn push_header(out: &mut Vec<u8>, count: u8, packet_type: u8, body_len: usize) {
+    out.push(0x80 | (count & 0x1F));
+    out.push(packet_type);
+    out.extend_from_slice(&((body_len / 4) as u16).to_be_bytes());
+}
+
+fn push_report_block(out: &mut Vec<u8>, rb: &ReportBlock) {
+    out.extend_from_slice(&rb.ssrc.to_be_bytes());
+    out.push(rb.fraction_lost);
+    out.extend_from_slice(
+        &rb.cumulative_lost
+            .clamp(-0x80_0000, 0x7F_FFFF)
+            .to_be_bytes()[1..],
+    );
+    out.extend_from_slice(&rb.highest_seq.to_be_bytes());
+    out.extend_from_slice(&rb.jitter.to_be_bytes());
+    out.extend_from_slice(&rb.lsr.to_be_bytes());
+    out.extend_from_slice(&rb.dlsr.to_be_bytes());
+}
+
+fn push_sdes_cname(out: &mut Vec<u8>, ssrc: u32, cname: &str) {
+    let cname = &cname.as_bytes()[..cname.len().min(255)];
+    // ssrc + tip + uzunluk + metin + END, 4 bayta yuvarlanmış
+    let chunk_len = (4 + 2 + cname.len() + 1).div_ceil(4) * 4;
+    push_header(out, 1, PT_SDES, chunk_len);
+    let start = out.len();
+    out.extend_from_slice(&ssrc.to_be_bytes());
+    out.push(SDES_CNAME);
+    out.push(cname.len() as u8);
+    out.extend_from_slice(cname);
+    out.resize(start + chunk_len, 0);
+}
+
+/// SR (veya RR) + SDES CNAME içeren compound paket üretir; `bye` verilirse sonuna BYE eklenir.
+pub fn build_compound(
+    ssrc: u32,
+    sender_info: Option<&SenderInfo>,
+    report: Option<&ReportBlock>,
+    cname: &str,
+    bye: bool,
+) -> Vec<u8> {
+    let mut out = Vec::with_capacity(128);
+    let rc = report.is_some() as u8;
+    let blocks_len = rc as usize * 24;
+
+    match sender_info {
+        Some(info) => {
+            push_header(&mut out, rc, PT_SR, 24 + blocks_len);
+            out.extend_from_slice(&ssrc.to_be_bytes());
+            out.extend_from_slice(&info.ntp_sec.to_be_bytes());
+            out.extend_from_slice(&info.ntp_frac.to_be_bytes());
+            out.extend_from_slice(&info.rtp_timestamp.to_be_bytes());
+            out.extend_from_slice(&info.packet_count.to_be_bytes());
+            out.extend_from_slice(&info.octet_count.to_be_bytes());
+        }
+        None => {
+            push_header(&mut out, rc, PT_RR, 4 + blocks_len);
+            out.extend_from_slice(&ssrc.to_be_bytes());
+        }
+    }
+    if let Some(rb) = report {
+        push_report_block(&mut out, rb);
+    }
+
+    push_sdes_cname(&mut out, ssrc, cname);
+
+    if bye {
+        push_header(&mut out, 1, PT_BYE, 4);
+        out.extend_from_slice(&ssrc.to_be_bytes());
+    }
+    out
+}
+
+pub fn ntp_now() -> (u32, u32) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = (now.as_secs() + NTP_UNIX_OFFSET) as u32;
+    let frac = ((now.subsec_nanos() as u64) << 32) / 1_000_000_000;
+    (secs, frac as u32)
+}
+
+/// NTP zaman damgasının orta 32 biti (LSR/DLSR hesapları için).
+pub fn ntp_middle(secs: u32, frac: u32) -> u32 {
+    (secs << 16) | (frac >> 16)
+}
+
+fn duration_to_ntp_short(d: Duration) -> u32 {
+    (d.as_secs_f64() * 65536.0) as u32
+}
+
+/// RFC 3550 6.4.1: RTT = A - LSR - DLSR (1/65536 saniye biriminde).
+pub fn round_trip_time(block: &ReportBlock, arrival_middle: u32) -> Option<Duration> {
+    if block.lsr == 0 {
+        return None;
+    }
+    let rtt = arrival_middle
+        .wrapping_sub(block.lsr)
+        .wrapping_sub(block.dlsr);
+    // Saat kaymasından kaynaklı negatif (sarmış) değerleri at.
+    if rtt > 0x8000_0000 {
+        return None;
+    }
+    Some(Duration::from_secs_f64(rtt as f64 / 65536.0))
+}
+
+/// RFC 3550 6.3.1: rapor aralığı [0.5, 1.5] ile rastgeleleştirilir.
+pub fn next_report_interval() -> Duration {
+    REPORT_INTERVAL.mul_f64(0.5 + rand::random::<f64>())
+}
+
+#[derive(Debug, Default)]
+pub struct RtcpReceiveOutcome {
+    pub rtt: Option<Duration>,
+    pub bye: bool,
+    pub bye_reason: Option<String>,
+    pub remote_cname: Option<String>,
+}
+
+/// Oturum başına RTCP durumu: karşı tarafın son SR'ı, ölçülen RTT ve
+/// kendi raporlarımız için kayıp aralığı işareti.
+pub struct RtcpSession {
+    ssrc: u32,
+    cname: String,
+    remote_ssrc: Option<u32>,
+    last_sr_middle: u32,
+    last_sr_arrival: Option<Instant>,
+    report_mark: IntervalMark,
+    last_rtt: Option<Duration>,
+}
+
+impl RtcpSession {
+    pub fn new(ssrc: u32, cname: String) -> Self {
+        Self {
+            ssrc,
+            cname,
+            remote_ssrc: None,
+            last_sr_middle: 0,
+            last_sr_arrival: None,
+            report_mark: IntervalMark::default(),
+            last_rtt: None,
+        }
+    }
+
+    pub fn set_remote_ssrc(&mut self, ssrc: u32) {
+        self.remote_ssrc = Some(ssrc);
+    }
+
+    pub fn last_rtt(&self) -> Option<Duration> {
+        self.last_rtt
+    }
+
+    pub fn handle_incoming(&mut self, data: &[u8]) -> RtcpReceiveOutcome {
+        let (secs, frac) = ntp_now();
+        let arrival_middle = ntp_middle(secs, frac);
+        let mut outcome = RtcpReceiveOutcome::default();
+
+        for packet in parse_compound(data) {
+            let reports = match packet {
+                RtcpPacket::SenderReport {
+                    ssrc,
+                    info,
+                    reports,
+                } => {
+                    // Karşı tarafın SSRC'si yalnızca RTP akışından öğrenilir; RTCP onu belirleyemez.
+                    if self.remote_ssrc == Some(ssrc) {
+                        self.last_sr_middle = ntp_middle(info.ntp_sec, info.ntp_frac);
+                        self.last_sr_arrival = Some(Instant::now());
+                    }
+                    reports
+                }
+                RtcpPacket::ReceiverReport { reports, .. } => reports,
+                RtcpPacket::SourceDescription { chunks } => {
+                    outcome.remote_cname = chunks.into_iter().find_map(|(_, cname)| cname);
+                    continue;
+                }
+                RtcpPacket::Bye { ssrcs, reason } => {
+                    // SSRC bilinmeden veya başka bir kaynağa ait BYE oturumu kapatmaz.
+                    if self.remote_ssrc.is_some_and(|r| ssrcs.contains(&r)) {
+                        outcome.bye = true;
+                        outcome.bye_reason = reason;
+                    }
+                    continue;
+                }
+                RtcpPacket::Other { .. } => continue,
+            };
+
+            for block in reports.iter().filter(|b| b.ssrc == self.ssrc) {
+                if let Some(rtt) = round_trip_time(block, arrival_middle) {
+                    self.last_rtt = Some(rtt);
+                    outcome.rtt = Some(rtt);
+                }
+            }
+        }
+        outcome
+    }
+
+    /// Periyodik SR/RR üretir. `sent` = Some((rtp_ts, paket, oktet)) ise SR, aksi halde RR gönderilir.
+    pub fn build_report(
+        &mut self,
+        stats: &ReceptionStats,
+        jitter: f64,
+        sent: Option<(u32, u32, u32)>,
+        bye: bool,
+    ) -> Vec<u8> {
+        let sender_info = sent.map(|(rtp_timestamp, packet_count, octet_count)| {
+            let (ntp_sec, ntp_frac) = ntp_now();
+            SenderInfo {
+                ntp_sec,
+                ntp_frac,
+                rtp_timestamp,
+                packet_count,
+                octet_count,
+            }
+        });
+
+        let report = match self.remote_ssrc {
+            Some(remote_ssrc) if stats.expected() > 0 => {
+                let interval = stats.interval(&mut self.report_mark);
+                let dlsr = self
+                    .last_sr_arrival
+                    .map(|t| duration_to_ntp_short(t.elapsed()))
+                    .unwrap_or(0);
+                Some(ReportBlock {
+                    ssrc: remote_ssrc,
+                    fraction_lost: (interval.fraction_lost * 256.0).min(255.0) as u8,
+                    cumulative_lost: stats.lost().min(0x7F_FFFF) as i32,
+                    highest_seq: stats.max_ext_seq() as u32,
+                    jitter: jitter as u32,
+                    lsr: self.last_sr_middle,
+                    dlsr,
+                })
+            }
+            _ => None,
+        };
+
+        build_compound(
+            self.ssrc,
+            sender_info.as_ref(),
+            report.as_ref(),
+            &self.cname,
+            bye,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sender_report_roundtrip() {
+        let info = SenderInfo {
+            ntp_sec: 1,
+            ntp_frac: 2,
+            rtp_timestamp: 3,
+            packet_count: 4,
+            octet_count: 5,
+        };
+        let block = ReportBlock {
+            ssrc: 0xBEEF,
+            fraction_lost: 12,
+            cumulative_lost: -3,
+            highest_seq: 70_000,
+            jitter: 40,
+            lsr: 0x1234_5678,
+            dlsr: 0x0001_0000,
+        };
+        let data = build_compound(0xCAFE, Some(&info), Some(&block), "media@test", true);
+        assert!(is_rtcp(&data));
+        assert_eq!(data.len() % 4, 0);
+
+        let packets = parse_compound(&data);
+        assert_eq!(
+            packets[0],
+            RtcpPacket::SenderReport {
+                ssrc: 0xCAFE,
+                info,
+                reports: vec![block]
+            }
+        );
+        assert_eq!(
+            packets[1],
+            RtcpPacket::SourceDescription {
+                chunks: vec![(0xCAFE, Some("media@test".to_string()))]
+            }
+        );
+        assert_eq!(
+            packets[2],
+            RtcpPacket::Bye {
+                ssrcs: vec![0xCAFE],
+                reason: None
+            }
+        );
+    }
+
+    #[test]
+    fn test_bye_requires_known_matching_ssrc() {
+        let mut session = RtcpSession::new(1, "media@test".into());
+        let bye = build_compound(0xCAFE, None, None, "peer@test", true);
+        assert!(!session.handle_incoming(&bye).bye);
+
+        session.set_remote_ssrc(0xBEEF);
+        assert!(!session.handle_incoming(&bye).bye);
+
+        session.set_remote_ssrc(0xCAFE);
+        assert!(session.handle_incoming(&bye).bye);
+    }
+
+    #[test]
+    fn test_round_trip_time() {
+        // LSR=1.0s, DLSR=0.5s, varış=1.75s -> RTT=0.25s
+        let block = ReportBlock {
+            ssrc: 1,
+            fraction_lost: 0,
+            cumulative_lost: 0,
+            highest_seq: 0,
+            jitter: 0,
+            lsr: 0x0001_0000,
+            dlsr: 0x0000_8000,
+        };
+        let rtt = round_trip_time(&block, 0x0001_C000).unwrap();
+        assert_eq!(rtt, Duration::from_millis(250));
+    }
+}
@@ -1,8 +1,16 @@
 // Dosya: sentiric-media-service/src/rtp/session.rs
 use crate::config::AppConfig;
+use crate::rtp::aec::EchoCanceller;
 use crate::rtp::codecs::AudioCodec;
-use crate::rtp::command::{RecordingSession, RtpCommand};
-use crate::rtp::session_handlers;
+use crate::rtp::command::{RecordingQos, RecordingSession, RtpCommand};
+use crate::rtp::dsp::{AutomaticGainControl, NoiseSuppressor};
+use crate::rtp::dtmf::{DtmfDigit, DtmfOutput, InbandDetector, Rfc4733Detector};
+use crate::rtp::jitter::{JitterBuffer, Playout, PushOutcome};
+use crate::rtp::live_stream::LiveStreamHub;
+use crate::rtp::rtcp::{self, RtcpSession};
+use crate::rtp::session_handlers::{self, BargeInMode};
+use crate::rtp::stats::{jitter_to_millis, IntervalMark, ReceptionStats};
+use crate::rtp::vad::{VadConfig, VadEvent, VoiceActivityDetector};
 use crate::state::AppState;
 use std::collections::VecDeque;
 use std::net::SocketAddr;
@@ -11,8 +19,12 @@ use tokio::sync::{mpsc, Mutex};
 use tokio::time::{Duration, Instant, MissedTickBehavior};
 use tracing::{debug, error, info, instrument, warn};
 
-use crate::metrics::{ACTIVE_SESSIONS, RECORDING_BUFFER_BYTES};
-use metrics::gauge;
+use crate::metrics::{
+    ACTIVE_SESSIONS, BARGE_IN_TOTAL, RTP_JITTER_BUFFER_DEPTH, RTP_JITTER_MS,
+    RTP_PACKETS_DUPLICATE_TOTAL, RTP_PACKETS_LOST_TOTAL, RTP_PACKETS_OUT_OF_ORDER_TOTAL,
+    RTP_PACKET_LOSS_RATIO, RTP_RTT_MS, RTP_SESSION_LOSS_RATIO,
+};
+use metrics::{counter, gauge, histogram};
 
 use sentiric_rtp_core::{
     AudioProfile, CodecFactory, Decoder, Encoder, RtpEndpoint, RtpHeader, RtpPacket,
@@ -120,6 +132,117 @@ impl RtpSession {
         Some(RtpPacket { header, payload })
     }
 
+    /// Kayıt duraklatılmışken (`masked`, örn. kart bilgisi tuşlanırken) rakamın kendisi loglara,
+    /// olaylara ve canlı akışa yazılmaz; yalnızca tuşlandığı bilgisi yayınlanır.
+    async fn on_dtmf_digit(
+        &self,
+        digit: DtmfDigit,
+        masked: bool,
+        config: &RtpSessionConfig,
+        live_streams: &crate::rtp::command::SharedLiveStreams,
+    ) {
+        if masked {
+            info!(
+                event = "DTMF_RECEIVED",
+                sip.call_id = %self.call_id,
+                dtmf.masked = true,
+                dtmf.duration_ms = digit.duration_ms,
+                dtmf.source = digit.source.as_str(),
+                "☎️ DTMF alındı (kayıt duraklatılmış, rakam gizlendi)."
+            );
+        } else {
+            info!(
+                event = "DTMF_RECEIVED",
+                sip.call_id = %self.call_id,
+                dtmf.digit = %digit.digit,
+                dtmf.duration_ms = digit.duration_ms,
+                dtmf.source = digit.source.as_str(),
+                "☎️ DTMF alındı."
+            );
+        }
+
+        let mut payload = serde_json::json!({
+            "callId": self.call_id,
+            "durationMs": digit.duration_ms,
+            "source": digit.source.as_str(),
+        });
+        if masked {
+            payload["masked"] = true.into();
+        } else {
+            payload["digit"] = digit.digit.to_string().into();
+        }
+        crate::rtp::session_utils::spawn_media_event(
+            &config.app_state,
+            &config.app_config.tenant_id,
+            &self.trace_id,
+            "call.media.dtmf.received",
+            payload,
+        );
+
+        // Canlı akış dinleyicisine (STT) rakamı ayrı bir media_type ile ilet.
+        if !masked {
+            live_streams.lock().await.publish_caller_event(
+                "audio/telephone-event",
+                digit.digit.to_string().into_bytes().into(),
+            );
+        }
+    }
+
+    fn on_vad_event(&self, vad_event: VadEvent, config: &RtpSessionConfig) {
+        let (event_type, payload) = match vad_event {
+            VadEvent::SpeechStarted => {
+                debug!(event = "VAD_SPEECH_STARTED", sip.call_id = %self.call_id, "🗣️ Konuşma başladı.");
+                (
+                    "call.media.speech.started",
+                    serde_json::json!({ "callId": self.call_id }),
+                )
+            }
+            VadEvent::SpeechEnded { duration_ms } => {
+                debug!(
+                    event = "VAD_SPEECH_ENDED",
+                    sip.call_id = %self.call_id,
+                    vad.duration_ms = duration_ms,
+                    "🤫 Konuşma bitti."
+                );
+                (
+                    "call.media.speech.ended",
+                    serde_json::json!({ "callId": self.call_id, "durationMs": duration_ms }),
+                )
+            }
+        };
+
+        crate::rtp::session_utils::spawn_media_event(
+            &config.app_state,
+            &config.app_config.tenant_id,
+            &self.trace_id,
+            event_type,
+            payload,
+        );
+    }
+
+    fn on_barge_in(&self, mode: BargeInMode, flushed_samples: usize, config: &RtpSessionConfig) {
+        info!(
+            event = "BARGE_IN",
+            sip.call_id = %self.call_id,
+            barge_in.mode = mode.as_str(),
+            flushed_samples = flushed_samples,
+            "✋ Arayan bot sesinin üzerine konuştu (barge-in)."
+        );
+        counter!(BARGE_IN_TOTAL, "mode" => mode.as_str()).increment(1);
+
+        crate::rtp::session_utils::spawn_media_event(
+            &config.app_state,
+            &config.app_config.tenant_id,
+            &self.trace_id,
+            "call.media.barge_in",
+            serde_json::json!({
+                "callId": self.call_id,
+                "mode": mode.as_str(),
+                "flushedMs": flushed_samples / 8,
+            }),
+        );
+    }
+
     #[instrument(skip_all, fields(port = self.port, call_id = %self.call_id, trace_id = %self.trace_id))]
     async fn run(
         self: Arc<Self>,
@@ -133,43 +256,130 @@ impl RtpSession {
             event = "RTP_SESSION_START",
             resource.service.name = "media-service",
             sip.call_id = %self.call_id,
-            "🎧 RTP Oturumu Başlatıldı (Fluid Ingress Queue Devrede)"
+            "🎧 RTP Oturumu Başlatıldı (Adaptif Jitter Buffer Devrede)"
         );
 
-        let live_stream_sender: crate::rtp::command::SharedLiveStreamSender =
-            Arc::new(Mutex::new(None));
+        let live_streams: crate::rtp::command::SharedLiveStreams =
+            Arc::new(Mutex::new(LiveStreamHub::default()));
         let recording_session: Arc<Mutex<Option<RecordingSession>>> = Arc::new(Mutex::new(None));
         let endpoint = RtpEndpoint::new(None);
 
-        let mut ingress_queue: VecDeque<i16> = VecDeque::with_capacity(32000);
+        // Jitter buffer'dan çıkan paketler çözülüp bu kuyruğa yazılır; ticker 160'lık frame'ler çeker.
+        let mut ingress_queue: VecDeque<i16> = VecDeque::with_capacity(1600);
         let mut egress_queue: VecDeque<i16> = VecDeque::with_capacity(32000);
 
-        // [CRITICAL FIX] Jitter Buffer durum yöneticisi
-        let mut is_buffering = true;
+        let mut jitter_buffer = JitterBuffer::from_millis(
+            self.app_state.port_manager.config.jitter_buffer_min_ms,
+            self.app_state.port_manager.config.jitter_buffer_max_ms,
+        );
 
-        // ... (packet_loss_count vb. tanımlamalar aynı kalacak) ...
-        let packet_loss_count = 0u64; // mut kaldırılmıştı hatırlarsanız
+        let mut reception_stats = ReceptionStats::new();
+        let mut qos_mark = IntervalMark::default();
+        let mut qos_reported_dup = 0u64;
+        let mut qos_reported_ooo = 0u64;
         let mut total_packets_rx = 0u64;
         let mut echo_tx_count = 0u64;
+        let mut tx_octets = 0u64;
         let mut known_target: Option<SocketAddr> = None;
-        let mut echo_mode = false;
+        let mut controls = session_handlers::MediaControls::default();
+        if self.app_state.port_manager.config.aec_enabled {
+            controls.echo_canceller = Some(EchoCanceller::from_app_config(
+                &self.app_state.port_manager.config,
+            ));
+        }
+        if self.app_state.port_manager.config.ns_enabled {
+            controls.noise_suppressor = Some(NoiseSuppressor::from_app_config(
+                &self.app_state.port_manager.config,
+            ));
+        }
+        if self.app_state.port_manager.config.agc_enabled {
+            controls.agc = Some(AutomaticGainControl::from_app_config(
+                &self.app_state.port_manager.config,
+            ));
+        }
 
         let server_ssrc: u32 = rand::random();
         let mut tx_seq: u16 = rand::random();
         let mut tx_ts: u32 = rand::random();
 
         let (rtp_packet_tx, mut rtp_packet_rx) = mpsc::channel::<(Vec<u8>, SocketAddr)>(2048);
+        // (veri, kaynak, rtcp-mux ile RTP portundan mı geldi)
+        let (rtcp_packet_tx, mut rtcp_packet_rx) = mpsc::channel::<(Vec<u8>, SocketAddr, bool)>(64);
 
         tokio::spawn({
             let socket = socket.clone();
+            let rtcp_packet_tx = rtcp_packet_tx.clone();
             async move {
                 let mut buf = [0u8; 2048];
                 while let Ok((len, addr)) = socket.recv_from(&mut buf).await {
-                    let _ = rtp_packet_tx.try_send((buf[..len].to_vec(), addr));
+                    // [ARCH-COMPLIANCE] RFC 5761: RTP portuna gelen (rtcp-mux) RTCP paketleri decoder'a girmez.
+                    if rtcp::is_rtcp(&buf[..len]) {
+                        let _ = rtcp_packet_tx.try_send((buf[..len].to_vec(), addr, true));
+                    } else {
+                        let _ = rtp_packet_tx.try_send((buf[..len].to_vec(), addr));
+                    }
                 }
             }
         });
 
+        // RTCP: PortManager sadece çift portları dağıttığı için rtp+1 bu oturuma ayrılmıştır.
+        let rtcp_bind_addr = format!(
+            "{}:{}",
+            self.app_state.port_manager.config.rtp_listen_ip,
+            self.port + 1
+        );
+        // Alım görevi soketin bir kopyasını tutar; kapanışta iptal edilmezse rtp+1 portu bir
+        // sonraki oturuma kapalı kalır.
+        let (rtcp_socket, rtcp_recv_task) = match tokio::net::UdpSocket::bind(&rtcp_bind_addr).await
+        {
+            Ok(s) => {
+                let s = Arc::new(s);
+                let task = tokio::spawn({
+                    let s = s.clone();
+                    async move {
+                        let mut buf = [0u8; 1500];
+                        while let Ok((len, addr)) = s.recv_from(&mut buf).await {
+                            if let Err(mpsc::error::TrySendError::Closed(_)) =
+                                rtcp_packet_tx.try_send((buf[..len].to_vec(), addr, false))
+                            {
+                                break;
+                            }
+                        }
+                    }
+                });
+                (Some(s), Some(task))
+            }
+            Err(e) => {
+                warn!(event = "RTCP_BIND_FAIL", sip.call_id = %self.call_id, error = %e, addr = %rtcp_bind_addr, "RTCP portu açılamadı, sadece rtcp-mux desteklenecek.");
+                (None, None)
+            }
+        };
+        let mut rtcp_session = RtcpSession::new(
+            server_ssrc,
+            format!(
+                "media-{:08x}@{}",
+                server_ssrc, self.app_state.port_manager.config.node_hostname
+            ),
+        );
+        let mut rtcp_target: Option<(SocketAddr, bool)> = None;
+
+        let dtmf_payload_type = self.app_state.port_manager.config.dtmf_payload_type;
+        let mut dtmf_detector = Rfc4733Detector::new();
+        let mut inband_detector = self
+            .app_state
+            .port_manager
+            .config
+            .dtmf_inband_detection
+            .then(InbandDetector::new);
+        let mut detected_digits: Vec<DtmfDigit> = Vec::new();
+        // Dedektör barge-in için her zaman hazır tutulur; olay/işaret yayını VAD_ENABLED'a bağlıdır.
+        let vad_enabled = self.app_state.port_manager.config.vad_enabled;
+        let mut vad = VoiceActivityDetector::new(VadConfig::from_app_config(
+            &self.app_state.port_manager.config,
+        ));
+        let duck_gain = 10f32.powf(self.app_state.port_manager.config.barge_in_duck_db / 20.0);
+        let mut next_rtcp_at = Instant::now() + rtcp::next_report_interval();
+
         let mut playback_queue: std::collections::VecDeque<session_handlers::PlaybackJob> =
             std::collections::VecDeque::new();
         let mut is_playing = false;
@@ -218,47 +428,91 @@ impl RtpSession {
                     }
 
                     if let Some(packet) = Self::parse_rtp_packet(data) {
-                        // [ARCH-COMPLIANCE] Baresip gibi istemcilerden sızan RTCP (192-205) paketlerini yoksay.
-                        // Aksi halde ses decoder'ına girip kuyruğu bozabilir.
-                        if packet.header.payload_type >= 192 && packet.header.payload_type <= 205 {
-                            continue; // Bu paketi yoksay ve döngüye devam et
-                        }
-
-                        // Kodek Güncelleme
-                        if active_payload_type != Some(packet.header.payload_type) {
+                        if packet.header.payload_type == dtmf_payload_type {
+                            // Ses ile aynı sıra uzayını paylaşır; kayıp sayılmasın diye sırası tüketilir.
+                            let outcome = jitter_buffer.push_non_audio(&packet.header, Instant::now());
+                            reception_stats.record(outcome, jitter_buffer.highest_ext_seq());
+                            detected_digits.extend(dtmf_detector.on_packet(packet.header.timestamp, &packet.payload));
+                            // Karşı taraf RFC 4733 kullanıyorsa in-band dedektöre gerek yok (çift raporu önler).
+                            inband_detector = None;
+                            controls.peer_sent_telephone_event = true;
+                        } else {
+                            rtcp_session.set_remote_ssrc(packet.header.ssrc);
+
+                            // Paket burada çözülmez; sıra numarasına göre jitter buffer'a alınır.
+                            let outcome = jitter_buffer.push(packet, Instant::now());
+                            reception_stats.record(outcome, jitter_buffer.highest_ext_seq());
+                            match outcome {
+                                PushOutcome::Late => {
+                                    debug!(event = "RTP_LATE_PACKET_DROPPED", sip.call_id = %self.call_id, "Oynatma anı geçmiş paket atıldı.");
+                                }
+                                PushOutcome::Resynced => {
+                                    debug!(event = "RTP_STREAM_RESYNC", sip.call_id = %self.call_id, "RTP akışı yeniden senkronize edildi (SSRC/sıra sıçraması).");
+                                }
+                                _ => {}
+                            }
 
-                            if let Ok(codec) = AudioCodec::from_rtp_payload_type(packet.header.payload_type) {
-                                active_decoder = Some(CodecFactory::create_decoder(codec.to_core_type()));
-                                active_encoder = Some(CodecFactory::create_encoder(codec.to_core_type()));
-                                active_payload_type = Some(packet.header.payload_type);
+                            // [ARCH-COMPLIANCE FIX] OOM Protection & Jitter Reset
+                            let dropped = jitter_buffer.trim_overflow();
+                            if dropped > 0 {
+                                warn!(event = "INGRESS_QUEUE_OVERFLOW", sip.call_id = %self.call_id, dropped = dropped, "Jitter buffer taştı, AI gecikmesi önleniyor (Catch-up).");
                             }
                         }
+                    }
 
-                        // Gelen paketi ANINDA çöz ve Ingress Kuyruğuna At!
-                        if packet.header.payload_type != 101 {
-                            if let Some(ref mut decoder) = active_decoder {
-                                let raw_pcm = decoder.decode(&packet.payload);
-                                if !raw_pcm.is_empty() {
-                                    if (gain_multiplier - 1.0).abs() > f32::EPSILON {
-                                        ingress_queue.extend(raw_pcm.into_iter().map(|s| {
-                                            ((s as f32 * gain_multiplier) as i32).clamp(i16::MIN as i32, i16::MAX as i32) as i16
-                                        }));
-                                    } else {
-                                        ingress_queue.extend(raw_pcm);
-                                    }
-
-                                    // [ARCH-COMPLIANCE FIX] OOM Protection & Jitter Reset
-                                    if ingress_queue.len() > 16000 {
-                                        tracing::warn!(event="INGRESS_QUEUE_OVERFLOW", sip.call_id=%self.call_id, "Ingress kuyruğu taştı, AI gecikmesi önleniyor (Catch-up).");
-                                        let drain_count = ingress_queue.len() - 8000;
-                                        ingress_queue.drain(0..drain_count);
-                                    }
-                                }
-                            }
+                    if !detected_digits.is_empty() {
+                        let masked = recording_session.lock().await.as_ref().is_some_and(RecordingSession::is_paused);
+                        for digit in detected_digits.drain(..) {
+                            self.on_dtmf_digit(digit, masked, &session_config, &live_streams).await;
                         }
                     }
                 },
 
+                Some((data, addr, is_mux)) = rtcp_packet_rx.recv() => {
+                    // RTCP yalnızca kilitlenen medya kaynağından kabul edilir; aksi halde herhangi
+                    // bir adresten gelen BYE çağrıyı düşürebilir. rtcp-mux'ta port da eşleşmelidir.
+                    let peer = endpoint.get_target().or(known_target);
+                    let from_peer = peer.is_some_and(|p| if is_mux { p == addr } else { p.ip() == addr.ip() });
+                    if !from_peer {
+                        debug!(event = "RTCP_FOREIGN_SOURCE", sip.call_id = %self.call_id, peer.ip = %addr.ip(), peer.port = addr.port(), "Kilitli medya kaynağından gelmeyen RTCP paketi yok sayıldı.");
+                        continue;
+                    }
+                    rtcp_target = Some((addr, is_mux));
+
+                    let outcome = rtcp_session.handle_incoming(&data);
+                    if let Some(rtt) = outcome.rtt {
+                        histogram!(RTP_RTT_MS).record(rtt.as_secs_f64() * 1000.0);
+                        debug!(event = "RTCP_RTT", sip.call_id = %self.call_id, rtt_ms = rtt.as_secs_f64() * 1000.0, "RTCP RTT ölçüldü.");
+                    }
+                    if let Some(cname) = outcome.remote_cname {
+                        debug!(event = "RTCP_SDES", sip.call_id = %self.call_id, cname = %cname, "Karşı taraf CNAME bilgisi alındı.");
+                    }
+                    if outcome.bye {
+                        info!(event = "RTCP_BYE_RECEIVED", sip.call_id = %self.call_id, reason = ?outcome.bye_reason, "👋 Karşı taraf RTCP BYE gönderdi, oturum sonlandırılıyor.");
+                        break;
+                    }
+                },
+
+                _ = tokio::time::sleep_until(next_rtcp_at) => {
+                    next_rtcp_at = Instant::now() + rtcp::next_report_interval();
+                    // rtcp-mux kullanan karşı tarafa raporu RTP soketinden döneriz.
+                    let destination = match rtcp_target {
+                        Some((addr, true)) => Some((addr, &socket)),
+                        Some((addr, false)) => rtcp_socket.as_ref().map(|s| (addr, s)),
+                        None => rtcp_socket.as_ref().and_then(|s| {
+                            known_target.or_else(|| endpoint.get_target()).map(|mut t| {
+                                t.set_port(t.port().wrapping_add(1));
+                                (t, s)
+                            })
+                        }),
+                    };
+                    if let Some((target, out_socket)) = destination {
+                        let sent = (echo_tx_count > 0).then_some((tx_ts, echo_tx_count as u32, tx_octets as u32));
+                        let report = rtcp_session.build_report(&reception_stats, jitter_buffer.jitter(), sent, false);
+                        let _ = out_socket.send_to(&report, target).await;
+                    }
+                },
+
                 Some(pcm_data) = egress_rx.recv() => {
                     last_activity = Instant::now();
                     egress_queue.extend(pcm_data);
@@ -276,8 +530,9 @@ impl RtpSession {
                      if matches!(cmd, RtpCommand::Shutdown) { break; }
 
                      if session_handlers::handle_command(
-                         cmd, &live_stream_sender, &recording_session,
-                         &mut playback_queue, &mut is_playing, &mut echo_mode,
+                         cmd, &live_streams, &recording_session,
+                         &mut playback_queue, &mut is_playing, &mut controls,
+                         &mut egress_queue, &mut egress_rx,
                          &session_config, &self.egress_tx, &finished_tx, &mut known_target, &endpoint, &self.call_id
                      ).await { break; }
                 },
@@ -288,40 +543,105 @@ impl RtpSession {
                     let mut rx_has_audio = false;
 
                     // 1. INGRESS JITTER BUFFER (Müşteriden Gelen Sesi Çek)
-                    if is_buffering {
-                        // 4 paketlik (80ms) avans bekliyoruz
-                        if ingress_queue.len() >= 640 {
-                            is_buffering = false;
+                    while ingress_queue.len() < 160 {
+                        match jitter_buffer.pop() {
+                            Some(Playout::Packet(packet)) => {
+                                // Kodek Güncelleme (oynatma sırasına göre yapılır ki decoder durumu bozulmasın)
+                                if active_payload_type != Some(packet.header.payload_type) {
+                                    if let Ok(codec) = AudioCodec::from_rtp_payload_type(packet.header.payload_type) {
+                                        active_decoder = Some(CodecFactory::create_decoder(codec.to_core_type()));
+                                        active_encoder = Some(CodecFactory::create_encoder(codec.to_core_type()));
+                                        active_payload_type = Some(packet.header.payload_type);
+                                    }
+                                }
+
+                                if let Some(ref mut decoder) = active_decoder {
+                                    let raw_pcm = decoder.decode(&packet.payload);
+                                    if (gain_multiplier - 1.0).abs() > f32::EPSILON {
+                                        ingress_queue.extend(raw_pcm.into_iter().map(|s| {
+                                            ((s as f32 * gain_multiplier) as i32).clamp(i16::MIN as i32, i16::MAX as i32) as i16
+                                        }));
+                                    } else {
+                                        ingress_queue.extend(raw_pcm);
+                                    }
+                                }
+                            }
+                            // Kayıp paket veya telephone-event: zaman çizelgesi kaymasın diye sessizlikle doldur.
+                            Some(Playout::Lost { samples } | Playout::NonAudio { samples }) => {
+                                ingress_queue.extend(std::iter::repeat_n(0i16, samples));
+                            }
+                            None => break,
                         }
                     }
 
-                    if !is_buffering {
-                        if ingress_queue.len() >= 160 {
-                            for item in rx_frame.iter_mut().take(160) {
-                                *item = ingress_queue.pop_front().unwrap_or(0);
-                            }
-                            rx_has_audio = true;
-                        } else {
-                            // [CRITICAL FIX] Starvation (Kuyruk açlığı) oluştu.
-                            // Cızırtı (Crackling) olmaması için sessizce Buffering moduna geri dön.
-                            is_buffering = true;
+                    if ingress_queue.len() >= 160 {
+                        for item in rx_frame.iter_mut().take(160) {
+                            *item = ingress_queue.pop_front().unwrap_or(0);
                         }
+                        rx_has_audio = true;
                     }
 
-                    // 2. SESİ DAĞIT (Echo ve AI için)
+                    // 1.4 AEC -> NS -> AGC: Canlı akış ve kayda gitmeden önce arayan sesini temizle.
                     if rx_has_audio {
-                        if let Some(tx) = &*live_stream_sender.lock().await {
-                            let pcm_16k = sentiric_rtp_core::simple_resample(&rx_frame, 8000, 16000);
-                            let mut b = Vec::with_capacity(pcm_16k.len() * 2);
-                            for s in &pcm_16k { b.extend_from_slice(&s.to_le_bytes()); }
-                            let _ = tx.try_send(Ok(crate::rtp::command::AudioFrame{ data: b.into(), media_type: "audio/L16;rate=16000".into() }));
+                        if let Some(aec) = controls.echo_canceller.as_mut() {
+                            // Yankı yolu: ağ RTT'si (ölçülene kadar AEC_BULK_DELAY_MS) + jitter buffer beklemesi.
+                            let network_ms = rtcp_session.last_rtt().map_or(session_config.app_config.aec_bulk_delay_ms, |rtt| rtt.as_millis() as u32);
+                            aec.set_echo_delay_ms(network_ms + jitter_buffer.target_depth() as u32 * 20);
+                            aec.process(&mut rx_frame);
+                        }
+                        if let Some(ns) = controls.noise_suppressor.as_mut() {
+                            ns.process(&mut rx_frame);
                         }
+                        if let Some(agc) = controls.agc.as_mut() {
+                            agc.process(&mut rx_frame);
+                        }
+                    }
 
-                        if echo_mode {
-                            egress_queue.extend(rx_frame.iter().copied());
+                    // 1.5 IN-BAND DTMF (RFC 4733 göndermeyen santraller için yedek)
+                    if rx_has_audio {
+                        if let Some(det) = inband_detector.as_mut() {
+                            if let Some(digit) = det.process(&rx_frame) {
+                                let masked = recording_session.lock().await.as_ref().is_some_and(RecordingSession::is_paused);
+                                self.on_dtmf_digit(digit, masked, &session_config, &live_streams).await;
+                            }
+                        }
+                    }
+
+                    // 1.6 VAD (Ses gelmeyen tick'ler sessizlik sayılır ki hangover ilerlesin)
+                    let mut rx_is_speech = None;
+                    if vad_enabled || controls.barge_in.is_some() {
+                        let (is_speech, vad_event) = vad.process(&rx_frame);
+                        if vad_enabled {
+                            rx_is_speech = Some(is_speech);
+                        }
+                        match vad_event {
+                            Some(VadEvent::SpeechStarted) => {
+                                // 1.7 BARGE-IN: Bot konuşurken arayan söze girdi.
+                                let bot_speaking = is_playing || egress_queue.len() >= 160;
+                                if let Some(mode) = controls.barge_in.filter(|_| bot_speaking) {
+                                    let flushed = match mode {
+                                        BargeInMode::Flush => session_handlers::stop_audio(
+                                            &mut controls, &mut playback_queue, &mut egress_queue, &mut egress_rx,
+                                        ),
+                                        BargeInMode::Duck => 0,
+                                    };
+                                    controls.barge_in_active = true;
+                                    self.on_barge_in(mode, flushed, &session_config);
+                                }
+                            }
+                            Some(VadEvent::SpeechEnded { .. }) => controls.barge_in_active = false,
+                            None => {}
+                        }
+                        if let Some(vad_event) = vad_event.filter(|_| vad_enabled) {
+                            self.on_vad_event(vad_event, &session_config);
                         }
                     }
 
+                    // 2. ECHO TESTİ
+                    if rx_has_audio && controls.echo_mode {
+                        egress_queue.extend(rx_frame.iter().copied());
+                    }
+
                     // 3. EGRESS (Müşteriye Gidecek Sesi Hazırla)
                     if egress_queue.len() >= 160 {
                         for item in tx_frame.iter_mut().take(160) {
@@ -331,52 +651,199 @@ impl RtpSession {
                         tx_frame.fill(0);
                     }
 
+                    if controls.barge_in_active {
+                        match controls.barge_in {
+                            Some(BargeInMode::Duck) => {
+                                for s in tx_frame.iter_mut() {
+                                    *s = (*s as f32 * duck_gain) as i16;
+                                }
+                            }
+                            // Arayan konuşurken akmaya devam eden bot sesi (StreamAudioToCall) atılır.
+                            Some(BargeInMode::Flush) => {
+                                egress_queue.clear();
+                                tx_frame.fill(0);
+                            }
+                            None => {}
+                        }
+                    }
+
+                    // 3.5 DTMF GÖNDERİMİ: Ton/olay süresince egress sesi yerine DTMF gider.
+                    let mut dtmf_events = None;
+                    if let Some(sender) = controls.dtmf_sender.as_mut() {
+                        match sender.next_frame(tx_ts) {
+                            Some(DtmfOutput::Events { timestamp, marker, payloads }) => {
+                                tx_frame.fill(0);
+                                dtmf_events = Some((timestamp, marker, payloads));
+                            }
+                            Some(DtmfOutput::Tone(tone)) => {
+                                tx_frame.copy_from_slice(&tone);
+                            }
+                            Some(DtmfOutput::Silence) => tx_frame.fill(0),
+                            None => {}
+                        }
+                    }
+                    if controls.dtmf_sender.as_ref().is_some_and(|s| s.is_finished()) {
+                        controls.dtmf_sender = None;
+                        info!(event = "DTMF_SEND_COMPLETE", sip.call_id = %self.call_id, "☎️ DTMF gönderimi tamamlandı.");
+                    }
+
+                    // Kesinleşen egress frame'i, sonraki tick'lerin yankı referansıdır.
+                    if let Some(aec) = controls.echo_canceller.as_mut() {
+                        aec.push_reference(&tx_frame);
+                    }
+
+                    // 3.6 CANLI AKIŞ: Her abone kendi kanalını (arayan/bot/karışık/stereo), hızını ve
+                    // kodlamasını alır. Frame'ler bu tick'in giden RTP zaman damgasıyla etiketlenir.
+                    {
+                        let mut hub = live_streams.lock().await;
+                        if !hub.is_empty() {
+                            let rx = rx_has_audio.then_some(rx_frame.as_slice());
+                            for gone in hub.publish_audio(rx, &tx_frame, rx_is_speech, tx_ts) {
+                                info!(
+                                    event = "LIVE_STREAM_UNSUBSCRIBED",
+                                    sip.call_id = %self.call_id,
+                                    live.subscriber_id = %gone.id,
+                                    live.dropped_frames = gone.dropped_frames,
+                                    "Canlı akış abonesi bağlantıyı kapattı, listeden çıkarıldı."
+                                );
+                            }
+                        }
+                    }
+
                     // 4. SESİ GÖNDER (Müşteriye)
                     if let Some(target) = known_target.or_else(|| endpoint.get_target()) {
                         if let Some(enc) = &mut active_encoder {
-                            let payload = enc.encode(&tx_frame);
-                            let header = RtpHeader::new(enc.get_type() as u8, tx_seq, tx_ts, server_ssrc);
-                            let packet = RtpPacket { header, payload };
-                            let _ = socket.send_to(&packet.to_bytes(), target).await;
-
-                            tx_seq = tx_seq.wrapping_add(1);
-                            tx_ts = tx_ts.wrapping_add(160);
-                            echo_tx_count += 1;
+                            if let Some((timestamp, marker, payloads)) = dtmf_events {
+                                // RFC 4733: aynı olayın paketleri olay başlangıcının zaman damgasını taşır.
+                                for payload in payloads {
+                                    let mut header = RtpHeader::new(dtmf_payload_type, tx_seq, timestamp, server_ssrc);
+                                    header.marker = marker;
+                                    let packet = RtpPacket { header, payload: payload.to_vec() };
+                                    let _ = socket.send_to(&packet.to_bytes(), target).await;
+
+                                    tx_octets += payload.len() as u64;
+                                    tx_seq = tx_seq.wrapping_add(1);
+                                    echo_tx_count += 1;
+                                }
+                            } else {
+                                let payload = enc.encode(&tx_frame);
+                                tx_octets += payload.len() as u64;
+                                let header = RtpHeader::new(enc.get_type() as u8, tx_seq, tx_ts, server_ssrc);
+                                let packet = RtpPacket { header, payload };
+                                let _ = socket.send_to(&packet.to_bytes(), target).await;
+
+                                tx_seq = tx_seq.wrapping_add(1);
+                                echo_tx_count += 1;
+                            }
                         }
                     }
+                    // Hedef kilitlenmeden de örnekleme saati ilerler; canlı akış damgaları tick başına artar.
+                    tx_ts = tx_ts.wrapping_add(160);
 
-                    // 5. S3 STEREO KAYIT
+                    // 5. KAYIT (output_uri hedefine parça parça akıtılır; bellekte tüm çağrı tutulmaz)
                     if let Some(rec) = &mut *recording_session.lock().await {
+                        // 2 saatlik üst sınır; WAV'ın 4 GiB veri sınırının da altında kalır.
                         const MAX_SAMPLES: usize = 57_600_000;
-                        if rec.rx_buffer.len() + 160 <= MAX_SAMPLES {
-                            rec.rx_buffer.extend_from_slice(&rx_frame);
-                            rec.tx_buffer.extend_from_slice(&tx_frame);
-                            gauge!(RECORDING_BUFFER_BYTES).increment(640.0);
+                        if rec.samples_written + 160 <= MAX_SAMPLES {
+                            // Gürültü bastırıcının blok gecikmesi de henüz kayda ulaşmamış arayan sesidir.
+                            let ns_delay = controls.noise_suppressor.as_ref().map_or(0, NoiseSuppressor::delay_samples);
+                            let ingress_backlog = ingress_queue.len() + jitter_buffer.len() * 160 + ns_delay;
+                            rec.write_frame(&rx_frame, &tx_frame, ingress_backlog);
+                            if let Some(codec) = active_payload_type.and_then(|pt| AudioCodec::from_rtp_payload_type(pt).ok()) {
+                                rec.note_wire_codec(codec.as_str());
+                            }
                         } else if !rec.max_reached_warned {
-                            warn!(event = "MAX_RECORDING_REACHED", sip.call_id = %self.call_id, "OOM Koruması aktif.");
+                            warn!(event = "MAX_RECORDING_REACHED", sip.call_id = %self.call_id, "Azami kayıt süresine ulaşıldı, kalan ses kaydedilmeyecek.");
                             rec.max_reached_warned = true;
                         }
                     }
                 },
 
                 _ = stats_ticker.tick() => {
-                    let loss_rate = if total_packets_rx > 0 { (packet_loss_count as f64 / (total_packets_rx + packet_loss_count) as f64) * 100.0 } else { 0.0 };
-                    if total_packets_rx > 0 {
-                        debug!(event = "RTP_QOS", sip.call_id = %self.call_id, loss_pct = loss_rate, rx = total_packets_rx, tx = echo_tx_count, "QoS Report");
+                    let interval = reception_stats.interval(&mut qos_mark);
+                    if interval.expected > 0 {
+                        let codec = active_payload_type
+                            .and_then(|pt| AudioCodec::from_rtp_payload_type(pt).ok())
+                            .map(|c| c.as_str())
+                            .unwrap_or("unknown");
+                        let jitter_ms = jitter_to_millis(jitter_buffer.jitter());
+
+                        histogram!(RTP_PACKET_LOSS_RATIO, "codec" => codec).record(interval.fraction_lost);
+                        histogram!(RTP_JITTER_MS, "codec" => codec).record(jitter_ms);
+                        histogram!(RTP_JITTER_BUFFER_DEPTH, "codec" => codec).record(jitter_buffer.target_depth() as f64);
+                        counter!(RTP_PACKETS_LOST_TOTAL, "codec" => codec).increment(interval.lost);
+                        counter!(RTP_PACKETS_OUT_OF_ORDER_TOTAL, "codec" => codec).increment(reception_stats.reordered() - qos_reported_ooo);
+                        counter!(RTP_PACKETS_DUPLICATE_TOTAL, "codec" => codec).increment(reception_stats.duplicates() - qos_reported_dup);
+                        qos_reported_ooo = reception_stats.reordered();
+                        qos_reported_dup = reception_stats.duplicates();
+                        if let Some(rec) = &mut *recording_session.lock().await {
+                            rec.qos = Some(recording_qos(&reception_stats, &jitter_buffer, &rtcp_session));
+                        }
+
+                        debug!(
+                            event = "RTP_QOS",
+                            sip.call_id = %self.call_id,
+                            codec = codec,
+                            loss_pct = interval.fraction_lost * 100.0,
+                            expected = interval.expected,
+                            received = interval.received,
+                            jitter_ms = jitter_ms,
+                            jb_depth = jitter_buffer.target_depth(),
+                            rtt_ms = rtcp_session.last_rtt().map(|d| d.as_secs_f64() * 1000.0),
+                            out_of_order = reception_stats.reordered(),
+                            duplicates = reception_stats.duplicates(),
+                            rx = total_packets_rx,
+                            tx = echo_tx_count,
+                            "QoS Report"
+                        );
                     }
                 },
 
                 Some(_) = finished_rx.recv() => {
                      is_playing = false;
+                     controls.current_playback = None;
                      if let Some(next) = playback_queue.pop_front() {
                          is_playing = true;
-                         session_handlers::start_playback(next, &session_config, self.egress_tx.clone(), finished_tx.clone(), &self.call_id).await;
+                         controls.current_playback = Some(next.cancellation_token.clone());
+                         session_handlers::start_playback(next, &session_config, self.egress_tx.clone(), finished_tx.clone(), &self.call_id);
                      }
                 }
             }
         }
 
-        if let Some(rec) = recording_session.lock().await.take() {
+        // Kapanışta yarım kalan konuşma turu, dinleyiciler askıda kalmasın diye kapatılır.
+        if let Some(vad_event) = vad.finish().filter(|_| vad_enabled) {
+            self.on_vad_event(vad_event, &session_config);
+        }
+
+        self.app_state.port_manager.remove_session(self.port).await;
+        self.app_state.port_manager.quarantine_port(self.port).await;
+
+        // Karşı tarafa oturumdan ayrıldığımızı bildir (RR/SR + BYE). Kaydın kapanışı (S3 multipart
+        // tamamlama) dakikalar sürebilir; BYE ve port onu beklemez.
+        let bye_destination = match rtcp_target {
+            Some((addr, true)) => Some((addr, &socket)),
+            Some((addr, false)) => rtcp_socket.as_ref().map(|s| (addr, s)),
+            None => None,
+        };
+        if let Some((target, out_socket)) = bye_destination {
+            let sent =
+                (echo_tx_count > 0).then_some((tx_ts, echo_tx_count as u32, tx_octets as u32));
+            let report =
+                rtcp_session.build_report(&reception_stats, jitter_buffer.jitter(), sent, true);
+            let _ = out_socket.send_to(&report, target).await;
+        }
+        if let Some(task) = rtcp_recv_task {
+            task.abort();
+        }
+        drop(rtcp_socket);
+
+        if let Some(mut rec) = recording_session.lock().await.take() {
+            rec.qos = Some(recording_qos(
+                &reception_stats,
+                &jitter_buffer,
+                &rtcp_session,
+            ));
             match crate::rtp::session_utils::finalize_and_save_recording(
                 rec,
                 self.app_state.clone(),
@@ -384,18 +851,51 @@ impl RtpSession {
             .await
             {
                 Ok(_) => {
-                    info!(event="RECORDING_SAVED", sip.call_id=%self.call_id, "💾 Stereo kayıt başarıyla S3'e yüklendi.")
+                    info!(event="RECORDING_SAVED", sip.call_id=%self.call_id, "💾 Kayıt başarıyla hedefe yazıldı.")
                 }
                 Err(e) => {
-                    error!(event="RECORDING_FAIL", sip.call_id=%self.call_id, error=%e, "❌ Kayıt S3'e yazılamadı!")
+                    error!(event="RECORDING_FAIL", sip.call_id=%self.call_id, error=%e, "❌ Kayıt hedefe yazılamadı!")
                 }
             }
         }
 
-        self.app_state.port_manager.remove_session(self.port).await;
-        self.app_state.port_manager.quarantine_port(self.port).await;
+        if reception_stats.expected() > 0 {
+            histogram!(RTP_SESSION_LOSS_RATIO).record(reception_stats.loss_ratio());
+        }
 
         gauge!(ACTIVE_SESSIONS).decrement(1.0);
-        info!(event = "RTP_SESSION_END", sip.call_id = %self.call_id, "🛑 RTP Oturumu Sonlandırıldı.");
+        info!(
+            event = "RTP_SESSION_END",
+            sip.call_id = %self.call_id,
+            rtp.expected = reception_stats.expected(),
+            rtp.received = reception_stats.received(),
+            rtp.lost = reception_stats.lost(),
+            rtp.loss_pct = reception_stats.loss_ratio() * 100.0,
+            rtp.jitter_ms = jitter_to_millis(jitter_buffer.jitter()),
+            rtp.out_of_order = reception_stats.reordered(),
+            rtp.late = reception_stats.late(),
+            rtp.duplicates = reception_stats.duplicates(),
+            rtp.rtt_ms = rtcp_session.last_rtt().map(|d| d.as_secs_f64() * 1000.0),
+            rtp.tx = echo_tx_count,
+            "🛑 RTP Oturumu Sonlandırıldı."
+        );
+    }
+}
+
+/// Kayıt metadatası için kümülatif QoS özeti.
+fn recording_qos(
+    stats: &ReceptionStats,
+    jitter_buffer: &JitterBuffer,
+    rtcp_session: &RtcpSession,
+) -> RecordingQos {
+    RecordingQos {
+        expected: stats.expected(),
+        received: stats.received(),
+        lost: stats.lost(),
+        loss_pct: stats.loss_ratio() * 100.0,
+        jitter_ms: jitter_to_millis(jitter_buffer.jitter()),
+        out_of_order: stats.reordered(),
+        duplicates: stats.duplicates(),
+        rtt_ms: rtcp_session.last_rtt().map(|d| d.as_secs_f64() * 1000.0),
     }
 }
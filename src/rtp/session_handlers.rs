@@ -1,27 +1,78 @@
 // Dosya: sentiric-media-service/src/rtp/session_handlers.rs
+use super::aec::EchoCanceller;
 use super::command::{RecordingSession, RtpCommand};
+use super::dsp::{AutomaticGainControl, NoiseSuppressor};
+use super::dtmf::{DtmfSendMode, DtmfSender};
+use super::live_stream::LiveStreamSubscriber;
 use super::session::RtpSessionConfig;
+use std::collections::VecDeque;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot, Mutex};
-use tracing::{error, info, Instrument};
+use tokio_util::sync::CancellationToken;
+use tonic::Status;
+use tracing::{error, info, warn, Instrument};
 
 #[derive(Debug)]
 pub struct PlaybackJob {
     pub audio_uri: String,
     pub target_addr: SocketAddr,
-    pub cancellation_token: tokio_util::sync::CancellationToken,
+    pub cancellation_token: CancellationToken,
     pub responder: Option<oneshot::Sender<anyhow::Result<()>>>,
 }
 
+/// Arayan konuşmaya başladığında bot sesine ne yapılacağı.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BargeInMode {
+    /// Bot sesi, arayan konuştuğu sürece kısılır.
+    Duck,
+    /// Çalan iş iptal edilir, egress boşaltılır; arayan konuştuğu sürece gelen bot sesi atılır.
+    Flush,
+}
+
+impl BargeInMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "duck" => Some(Self::Duck),
+            "flush" => Some(Self::Flush),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Duck => "duck",
+            Self::Flush => "flush",
+        }
+    }
+}
+
+/// Oturum döngüsünün gRPC komutlarıyla değiştirilebilen medya ayarları.
+#[derive(Debug, Default)]
+pub struct MediaControls {
+    pub echo_mode: bool,
+    pub dtmf_sender: Option<DtmfSender>,
+    pub peer_sent_telephone_event: bool,
+    /// Şu an çalan PlaybackJob'un iptal anahtarı (StopAudio / barge-in için).
+    pub current_playback: Option<CancellationToken>,
+    pub barge_in: Option<BargeInMode>,
+    /// Barge-in tetiklendi ve arayan hâlâ konuşuyor.
+    pub barge_in_active: bool,
+    pub echo_canceller: Option<EchoCanceller>,
+    pub noise_suppressor: Option<NoiseSuppressor>,
+    pub agc: Option<AutomaticGainControl>,
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn handle_command(
     command: RtpCommand,
-    live_stream_sender: &crate::rtp::command::SharedLiveStreamSender,
+    live_streams: &crate::rtp::command::SharedLiveStreams,
     recording_session: &Arc<Mutex<Option<RecordingSession>>>,
-    playback_queue: &mut std::collections::VecDeque<PlaybackJob>,
+    playback_queue: &mut VecDeque<PlaybackJob>,
     is_playing: &mut bool,
-    echo_mode: &mut bool,
+    controls: &mut MediaControls,
+    egress_queue: &mut VecDeque<i16>,
+    egress_rx: &mut mpsc::Receiver<Vec<i16>>,
     config: &RtpSessionConfig,
     egress_tx: &mpsc::Sender<Vec<i16>>,
     finished_tx: &mpsc::Sender<()>,
@@ -51,30 +102,161 @@ pub async fn handle_command(
 
             if !*is_playing {
                 *is_playing = true;
-                start_playback(job, config, egress_tx.clone(), finished_tx.clone(), call_id).await;
+                controls.current_playback = Some(job.cancellation_token.clone());
+                start_playback(job, config, egress_tx.clone(), finished_tx.clone(), call_id);
             } else {
                 playback_queue.push_back(job);
             }
         }
+        RtpCommand::StopAudio => {
+            let flushed = stop_audio(controls, playback_queue, egress_queue, egress_rx);
+            info!(event = "MEDIA_PLAYBACK_STOPPED", sip.call_id = %call_id, flushed_samples = flushed, "⏹️ Oynatma durduruldu, egress kuyruğu boşaltıldı.");
+        }
         RtpCommand::EnableEchoTest => {
             info!(event = "ECHO_MODE_ENABLED", sip.call_id = %call_id, "🔊 Native Echo Reflex AKTİFLEŞTİRİLDİ. Loopback başlıyor.");
-            *echo_mode = true;
+            controls.echo_mode = true;
         }
         RtpCommand::DisableEchoTest => {
             info!(event = "ECHO_MODE_DISABLED", sip.call_id = %call_id, "🔇 Native Echo Reflex KAPATILDI.");
-            *echo_mode = false;
+            controls.echo_mode = false;
         }
-        RtpCommand::StartLiveAudioStream { stream_sender, .. } => {
-            let mut guard = live_stream_sender.lock().await;
-            *guard = Some(stream_sender);
+        RtpCommand::EnableBargeIn { mode } => {
+            info!(event = "BARGE_IN_ENABLED", sip.call_id = %call_id, barge_in.mode = mode.as_str(), "✋ Barge-in modu AKTİFLEŞTİRİLDİ.");
+            controls.barge_in = Some(mode);
         }
-        RtpCommand::StopLiveAudioStream => {
-            let mut guard = live_stream_sender.lock().await;
-            *guard = None;
+        RtpCommand::DisableBargeIn => {
+            info!(event = "BARGE_IN_DISABLED", sip.call_id = %call_id, "Barge-in modu KAPATILDI.");
+            controls.barge_in = None;
+            controls.barge_in_active = false;
+        }
+        RtpCommand::EnableEchoCancellation => {
+            if controls.echo_canceller.is_none() {
+                info!(event = "AEC_ENABLED", sip.call_id = %call_id, "🎧 Yankı giderici (AEC) AKTİFLEŞTİRİLDİ.");
+                controls.echo_canceller = Some(EchoCanceller::from_app_config(&config.app_config));
+            }
+        }
+        RtpCommand::DisableEchoCancellation => {
+            info!(event = "AEC_DISABLED", sip.call_id = %call_id, "Yankı giderici (AEC) KAPATILDI.");
+            controls.echo_canceller = None;
+        }
+        RtpCommand::SetIngressDsp {
+            noise_suppression,
+            agc,
+        } => {
+            match noise_suppression {
+                Some(true) if controls.noise_suppressor.is_none() => {
+                    controls.noise_suppressor =
+                        Some(NoiseSuppressor::from_app_config(&config.app_config));
+                }
+                Some(false) => controls.noise_suppressor = None,
+                _ => {}
+            }
+            match agc {
+                Some(true) if controls.agc.is_none() => {
+                    controls.agc = Some(AutomaticGainControl::from_app_config(&config.app_config));
+                }
+                Some(false) => controls.agc = None,
+                _ => {}
+            }
+            info!(
+                event = "INGRESS_DSP_UPDATED",
+                sip.call_id = %call_id,
+                dsp.noise_suppression = controls.noise_suppressor.is_some(),
+                dsp.agc = controls.agc.is_some(),
+                "🎚️ Ingress DSP ayarları güncellendi."
+            );
+        }
+        RtpCommand::SendDtmf {
+            digits,
+            tone_duration,
+            inter_digit_gap,
+            mode,
+        } => {
+            let inband = match mode {
+                DtmfSendMode::Rfc4733 => false,
+                DtmfSendMode::InBand => true,
+                DtmfSendMode::Auto => !controls.peer_sent_telephone_event,
+            };
+            // Tuşlanan rakamlar PIN/kart numarası olabilir; loga yalnızca sayısı yazılır.
+            info!(event = "DTMF_SEND_START", sip.call_id = %call_id, dtmf.count = digits.chars().count(), dtmf.mode = ?mode, dtmf.inband = inband, "☎️ DTMF gönderimi kuyruğa alındı.");
+            match controls.dtmf_sender.as_mut() {
+                Some(sender) if !sender.is_finished() => {
+                    sender.enqueue(&digits, tone_duration, inter_digit_gap, inband)
+                }
+                _ => {
+                    controls.dtmf_sender = Some(DtmfSender::new(
+                        &digits,
+                        tone_duration,
+                        inter_digit_gap,
+                        inband,
+                    ))
+                }
+            }
+        }
+        RtpCommand::StartLiveAudioStream {
+            subscriber_id,
+            stream_sender,
+            target_sample_rate,
+            channel,
+            encoding,
+            frame_tags,
+        } => {
+            let sample_rate = target_sample_rate.unwrap_or(encoding.default_sample_rate());
+            let subscriber = match LiveStreamSubscriber::new(
+                subscriber_id.clone(),
+                stream_sender.clone(),
+                sample_rate,
+                channel,
+                encoding,
+                frame_tags,
+            ) {
+                Ok(subscriber) => subscriber,
+                Err(e) => {
+                    warn!(
+                        event = "LIVE_STREAM_SUBSCRIBE_FAILED",
+                        sip.call_id = %call_id,
+                        live.subscriber_id = %subscriber_id,
+                        error = %e,
+                        "Canlı ses akışı aboneliği oluşturulamadı."
+                    );
+                    let _ = stream_sender
+                        .send(Err(Status::invalid_argument(e.to_string())))
+                        .await;
+                    return false;
+                }
+            };
+            let mut hub = live_streams.lock().await;
+            hub.add(subscriber);
+            info!(
+                event = "LIVE_STREAM_SUBSCRIBED",
+                sip.call_id = %call_id,
+                live.subscriber_id = %subscriber_id,
+                live.sample_rate = sample_rate,
+                live.channel = channel.as_str(),
+                live.encoding = encoding.as_str(),
+                live.subscribers = hub.len(),
+                "🎙️ Canlı ses akışına abone eklendi."
+            );
+        }
+        RtpCommand::StopLiveAudioStream { subscriber_id } => {
+            let mut hub = live_streams.lock().await;
+            match subscriber_id {
+                Some(id) => {
+                    hub.remove(&id);
+                }
+                None => hub.clear(),
+            }
         }
-        RtpCommand::StartPermanentRecording(session) => {
+        RtpCommand::StartPermanentRecording { session, responder } => {
             let mut guard = recording_session.lock().await;
-            *guard = Some(session);
+            let result = match session.install(&mut guard) {
+                true => Ok(()),
+                false => {
+                    warn!(event = "RECORDING_ALREADY_ACTIVE", sip.call_id = %call_id, "⚠️ Çağrıda kayıt zaten sürüyor, yeni kayıt isteği reddedildi.");
+                    Err("Recording already active".to_string())
+                }
+            };
+            let _ = responder.send(result);
         }
         RtpCommand::StopPermanentRecording { responder } => {
             let mut guard = recording_session.lock().await;
@@ -96,16 +278,61 @@ pub async fn handle_command(
                 );
             }
         }
+        RtpCommand::SetRecordingPaused { paused, responder } => {
+            let mut guard = recording_session.lock().await;
+            let result = match guard.as_mut() {
+                Some(rec) => {
+                    let changed = if paused { rec.pause() } else { rec.resume() };
+                    if changed && paused {
+                        info!(event = "RECORDING_PAUSED", sip.call_id = %call_id, recording.position_ms = rec.samples_written / 8, "⏸️ Kayıt duraklatıldı, sessizlik yazılıyor.");
+                    } else if changed {
+                        info!(event = "RECORDING_RESUME_REQUESTED", sip.call_id = %call_id, recording.position_ms = rec.samples_written / 8, "▶️ Kayıt sürdürülüyor (tampondaki arayan sesi tükenince).");
+                    }
+                    Ok(changed)
+                }
+                None => Err("No active recording".to_string()),
+            };
+            let _ = responder.send(result);
+        }
         RtpCommand::Shutdown => return true,
         RtpCommand::SetTargetAddress { target } => {
             *known_target = Some(target);
         }
-        _ => {}
     }
     false
 }
 
-pub async fn start_playback(
+/// Çalan işi iptal eder, bekleyen işleri reddeder ve henüz gönderilmemiş egress sesini atar.
+/// Atılan örnek sayısını döndürür.
+pub fn stop_audio(
+    controls: &mut MediaControls,
+    playback_queue: &mut VecDeque<PlaybackJob>,
+    egress_queue: &mut VecDeque<i16>,
+    egress_rx: &mut mpsc::Receiver<Vec<i16>>,
+) -> usize {
+    if let Some(token) = controls.current_playback.take() {
+        token.cancel();
+    }
+
+    for mut job in playback_queue.drain(..) {
+        job.cancellation_token.cancel();
+        if let Some(tx) = job.responder.take() {
+            let _ = tx.send(Err(anyhow::anyhow!("Playback cancelled")));
+        }
+    }
+
+    // Kanalda bekleyen chunk'lar (Playback veya StreamAudioToCall) da bot sesidir.
+    let mut flushed = egress_queue.len();
+    egress_queue.clear();
+    while let Ok(chunk) = egress_rx.try_recv() {
+        flushed += chunk.len();
+    }
+    flushed
+}
+
+/// Oynatma görevini başlatır; anonsun indirilmesi ve çözülmesi de görevin içindedir, RTP döngüsü
+/// yalnızca görevi kuyruğa alır. `responder` ses hazır olunca (veya hata ile) yanıtlanır.
+pub fn start_playback(
     mut job: PlaybackJob,
     config: &RtpSessionConfig,
     egress_tx: mpsc::Sender<Vec<i16>>,
@@ -113,76 +340,96 @@ pub async fn start_playback(
     call_id: &str,
 ) {
     let responder = job.responder.take();
-    let uri = job.audio_uri.clone();
+    let uri = crate::audio::display_uri(&job.audio_uri).into_owned();
     let call_id_owned = call_id.to_string();
     let app_state = config.app_state.clone();
+    let app_config = config.app_config.clone();
     let tenant_id_owned = config.app_config.tenant_id.clone();
     let span = tracing::Span::current();
 
-    match crate::rtp::session_utils::load_and_resample_samples_from_uri(
-        &uri,
-        &config.app_state,
-        &config.app_config,
-    )
-    .await
-    {
-        Ok(samples) => {
-            if let Some(tx) = responder {
-                let _ = tx.send(Ok(()));
+    tokio::spawn(async move {
+        // İndirme sürerken gelen StopAudio beklenmez.
+        let loaded = tokio::select! {
+            _ = job.cancellation_token.cancelled() => None,
+            loaded = crate::rtp::session_utils::load_and_resample_samples_from_uri(
+                &job.audio_uri,
+                &app_state,
+                &app_config,
+            ) => Some(loaded),
+        };
+        let samples = match loaded {
+            Some(Ok(samples)) => {
+                if let Some(tx) = responder {
+                    let _ = tx.send(Ok(()));
+                }
+                samples
             }
+            Some(Err(e)) => {
+                error!(event = "MEDIA_PLAYBACK_ERROR", sip.call_id = %call_id_owned, uri = %uri, error = %e, "Medya oynatma hatası");
+                if let Some(tx) = responder {
+                    // `context` altta yatan hatayı korur; gRPC katmanı türüne göre eşler.
+                    let _ = tx.send(Err(e.context("Playback error")));
+                }
+                let _ = finished_tx.try_send(());
+                return;
+            }
+            None => {
+                tracing::info!(event = "MEDIA_PLAYBACK_CANCELLED", sip.call_id = %call_id_owned, uri = %uri, "⏹️ Medya oynatma iptal edildi.");
+                if let Some(tx) = responder {
+                    let _ = tx.send(Err(anyhow::anyhow!("Playback cancelled")));
+                }
+                let _ = finished_tx.try_send(());
+                return;
+            }
+        };
 
-            tokio::spawn(async move {
-                tracing::info!(event = "MEDIA_PLAYBACK_START", sip.call_id = %call_id_owned, uri = %uri, "🚀 Medya PCM chunk'ları Egress kanalına basılıyor.");
-
-                let mut res_ok = true;
-                let mut interval = tokio::time::interval(std::time::Duration::from_millis(20));
-                interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
-
-                let mut pre_buffer = 10;
+        tracing::info!(event = "MEDIA_PLAYBACK_START", sip.call_id = %call_id_owned, uri = %uri, "🚀 Medya PCM chunk'ları Egress kanalına basılıyor.");
 
-                for chunk in samples.chunks(160) {
-                    if job.cancellation_token.is_cancelled() { break; }
+        let mut res_ok = true;
+        let mut interval = tokio::time::interval(std::time::Duration::from_millis(20));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
 
-                    if pre_buffer > 0 {
-                        pre_buffer -= 1;
-                    } else {
-                        interval.tick().await;
-                    }
+        let mut pre_buffer = 10;
 
-                    if let Err(e) = egress_tx.send(chunk.to_vec()).await {
-                        tracing::debug!(event = "EGRESS_SEND_ERROR", error = %e, "Egress channel closed");
-                        res_ok = false;
-                        break;
-                    }
-                }
+        for chunk in samples.chunks(160) {
+            if job.cancellation_token.is_cancelled() { break; }
 
-                if res_ok {
-                    if let Some(mq_client) = &app_state.rabbitmq_publisher {
-                        let json_payload = serde_json::json!({ "callId": call_id_owned, "uri": uri }).to_string();
-                        let event = sentiric_contracts::sentiric::event::v1::GenericEvent {
-                            event_type: "call.media.playback.finished".to_string(),
-                            trace_id: call_id_owned.clone(),
-                            timestamp: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
-                            tenant_id: tenant_id_owned,
-                            payload_json: json_payload,
-                        };
-                        use prost::Message;
-                        let _ = mq_client.publish_with_confirm(
-                            "call.media.playback.finished",
-                            &event.encode_to_vec()
-                        ).await;
-                    }
+            if pre_buffer > 0 {
+                pre_buffer -= 1;
+            } else {
+                tokio::select! {
+                    _ = job.cancellation_token.cancelled() => break,
+                    _ = interval.tick() => {}
                 }
+            }
 
-                let _ = finished_tx.try_send(());
-            }.instrument(span));
+            if let Err(e) = egress_tx.send(chunk.to_vec()).await {
+                tracing::debug!(event = "EGRESS_SEND_ERROR", error = %e, "Egress channel closed");
+                res_ok = false;
+                break;
+            }
         }
-        Err(e) => {
-            error!(event = "MEDIA_PLAYBACK_ERROR", error = %e, "Medya oynatma hatası");
-            if let Some(tx) = responder {
-                let _ = tx.send(Err(anyhow::anyhow!("Playback error: {}", e)));
+
+        if job.cancellation_token.is_cancelled() {
+            tracing::info!(event = "MEDIA_PLAYBACK_CANCELLED", sip.call_id = %call_id_owned, uri = %uri, "⏹️ Medya oynatma iptal edildi.");
+        } else if res_ok {
+            if let Some(mq_client) = &app_state.rabbitmq_publisher {
+                let json_payload = serde_json::json!({ "callId": call_id_owned, "uri": uri }).to_string();
+                let event = sentiric_contracts::sentiric::event::v1::GenericEvent {
+                    event_type: "call.media.playback.finished".to_string(),
+                    trace_id: call_id_owned.clone(),
+                    timestamp: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
+                    tenant_id: tenant_id_owned,
+                    payload_json: json_payload,
+                };
+                use prost::Message;
+                let _ = mq_client.publish_with_confirm(
+                    "call.media.playback.finished",
+                    &event.encode_to_vec()
+                ).await;
             }
-            let _ = finished_tx.try_send(());
         }
-    }
+
+        let _ = finished_tx.try_send(());
+    }.instrument(span));
 }
@@ -1,130 +1,275 @@
 // Dosya: src/rtp/session_utils.rs
 use super::command::RecordingSession;
-use crate::metrics::RECORDING_BUFFER_BYTES;
-use crate::rtp::writers;
+use super::spool::SpooledEvent;
+use super::writers::{RecordingTarget, UploadOutcome};
 use crate::state::AppState;
 use anyhow::{anyhow, Result};
 use chrono::Datelike;
-use hound::{SampleFormat, WavSpec, WavWriter};
-use metrics::gauge;
+use lapin::types::{AMQPValue, FieldTable};
 use prost::Message;
-use std::io::Cursor;
-use tokio::task::spawn_blocking;
-use tracing::{error, info, instrument};
+use std::time::SystemTime;
+use tracing::{error, info, instrument, warn};
 
-use sentiric_contracts::sentiric::event::v1::CallRecordingAvailableEvent;
+use sentiric_contracts::sentiric::event::v1::{CallRecordingAvailableEvent, GenericEvent};
+
+/// Varsayılan kayıt anahtarı: `recordings/YYYY/MM/DD/{call_id}.{extension}`.
+pub fn default_recording_key(call_id: &str, extension: &str) -> String {
+    let now = chrono::Utc::now();
+    format!(
+        "recordings/{}/{:02}/{:02}/{}.{}",
+        now.year(),
+        now.month(),
+        now.day(),
+        call_id,
+        extension
+    )
+}
 
 #[instrument(skip_all, fields(call_id = %session.call_id))]
 pub async fn finalize_and_save_recording(
-    session: RecordingSession,
+    mut session: RecordingSession,
     app_state: AppState,
 ) -> Result<()> {
-    let rx_len = session.rx_buffer.len();
-    let tx_len = session.tx_buffer.len();
-    let max_len = rx_len.max(tx_len);
-
-    gauge!(RECORDING_BUFFER_BYTES).decrement(((rx_len + tx_len) * 2) as f64);
-
-    if max_len == 0 {
+    session.close_pauses();
+    let mut metadata = recording_metadata(&session, SystemTime::now());
+    let sidecar = session.pipeline.sidecar().clone();
+    let s3_uri = session.pipeline.uri().to_string();
+    // Kalan ses kodlanır, başlık yamalanır ve yükleme tamamlanır.
+    let (size_bytes, outcome) = session.pipeline.finish().await?;
+    if size_bytes == 0 {
+        // Hiç ses yazılmadıysa S3'te multipart da başlamamıştır; atılacak bir şey yok.
         info!(event = "RECORDING_SKIPPED", sip.call_id = %session.call_id, "Boş kayıt, işlem atlanıyor.");
         return Ok(());
     }
+    metadata["sizeBytes"] = size_bytes.into();
 
-    let now = chrono::Utc::now();
-    let s3_key = format!(
-        "recordings/{}/{:02}/{:02}/{}.wav",
-        now.year(),
-        now.month(),
-        now.day(),
-        session.call_id
-    );
-
-    let spec = WavSpec {
-        channels: 2,
-        sample_rate: 8000,
-        bits_per_sample: 16,
-        sample_format: SampleFormat::Int,
-    };
+    if let UploadOutcome::Spooled(mut entry) = outcome {
+        // Olay, nesne S3'e ulaştığında spool görevi tarafından yayınlanır.
+        let spool = app_state
+            .recording_spool
+            .as_ref()
+            .ok_or_else(|| anyhow!("Spool yapılandırılmamış"))?;
+        entry.event = Some(SpooledEvent {
+            call_id: session.call_id.clone(),
+            trace_id: session.trace_id,
+            recording_uri: s3_uri,
+            metadata,
+        });
+        spool.commit(&entry).await?;
+        warn!(event = "RECORDING_SPOOLED", sip.call_id = %session.call_id, spool.id = %entry.id, "📦 Kayıt S3'e yüklenemedi, spool'a alındı; olay yükleme sonrası yayınlanacak.");
+        return Ok(());
+    }
 
-    let rx_buffer = session.rx_buffer;
-    let tx_buffer = session.tx_buffer;
+    if let Err(e) = announce_recording(
+        &app_state,
+        &sidecar,
+        &session.call_id,
+        &session.trace_id,
+        &s3_uri,
+        metadata,
+    )
+    .await
+    {
+        error!(event = "RECORDING_EVENT_PUBLISH_FAIL", sip.call_id = %session.call_id, error = %e, "🔥 Kayıt hedefe yazıldı ama RabbitMQ'ya olay atılamadı!");
+    }
 
-    let wav_data = spawn_blocking(move || -> Result<Vec<u8>> {
-        let mut buffer = Cursor::new(Vec::with_capacity(max_len * 4 + 44));
-        let mut writer = WavWriter::new(&mut buffer, spec)?;
+    Ok(())
+}
 
-        for i in 0..max_len {
-            let left = if i < rx_buffer.len() { rx_buffer[i] } else { 0 };
-            let right = if i < tx_buffer.len() { tx_buffer[i] } else { 0 };
-            writer.write_sample(left)?;
-            writer.write_sample(right)?;
+/// Hedefe ulaşmış kaydın sidecar dosyasını yazar ve `call.recording.available` olayını yayınlar.
+/// Olay yayınlanamazsa hata döner (spool bu durumda yeniden dener).
+pub async fn announce_recording(
+    app_state: &AppState,
+    sidecar: &RecordingTarget,
+    call_id: &str,
+    trace_id: &str,
+    recording_uri: &str,
+    mut metadata: serde_json::Value,
+) -> Result<()> {
+    // Sidecar yazılamazsa kayıt yine de duyurulur; metadata olayın başlığında da taşınır.
+    match sidecar
+        .put(
+            "application/json",
+            serde_json::to_vec_pretty(&metadata)?,
+            call_id,
+        )
+        .await
+    {
+        Ok(()) => metadata["sidecarUri"] = sidecar.uri().into(),
+        Err(e) => {
+            warn!(event = "RECORDING_SIDECAR_FAIL", sip.call_id = %call_id, error = %e, "⚠️ Kayıt metadata dosyası yazılamadı.")
         }
-
-        writer.finalize()?;
-        Ok(buffer.into_inner())
-    })
-    .await??;
-
-    let s3_config = app_state
-        .port_manager
-        .config
-        .s3_config
-        .clone()
-        .ok_or_else(|| anyhow!("S3 config eksik"))?;
-    let s3_client = app_state
-        .s3_client
-        .clone()
-        .ok_or_else(|| anyhow!("S3 client eksik"))?;
-
-    writers::upload_to_s3_with_retry(
-        s3_client,
-        &s3_config.bucket_name,
-        &s3_key,
-        wav_data,
-        &session.call_id,
-    )
-    .await?;
+    }
 
     if let Some(mq_client) = &app_state.rabbitmq_publisher {
-        let s3_uri = format!("s3://{}/{}", s3_config.bucket_name, s3_key);
         let event = CallRecordingAvailableEvent {
             event_type: "call.recording.available".to_string(),
-            trace_id: session.trace_id,
-            call_id: session.call_id.clone(),
+            trace_id: trace_id.to_string(),
+            call_id: call_id.to_string(),
             timestamp: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
-            recording_uri: s3_uri,
+            recording_uri: recording_uri.to_string(),
             public_url: "".to_string(),
         };
 
-        match mq_client
-            .publish_with_confirm("call.recording.available", &event.encode_to_vec())
-            .await
-        {
-            Ok(_) => {
-                info!(event = "RECORDING_EVENT_PUBLISHED", sip.call_id = %session.call_id, "📩 Stereo kayıt tamamlandı olayı (Confirmed) RabbitMQ'ya iletildi.")
-            }
-            Err(e) => {
-                error!(event = "RECORDING_EVENT_PUBLISH_FAIL", sip.call_id = %session.call_id, error = %e, "🔥 Kayıt S3'e yüklendi ama RabbitMQ'ya olay atılamadı!");
-            }
-        }
+        mq_client
+            .publish_with_headers(
+                "call.recording.available",
+                &event.encode_to_vec(),
+                recording_metadata_headers(&metadata),
+            )
+            .await?;
+        info!(event = "RECORDING_EVENT_PUBLISHED", sip.call_id = %call_id, "📩 Stereo kayıt tamamlandı olayı (Confirmed) RabbitMQ'ya iletildi.");
     }
 
     Ok(())
 }
 
+/// Kayıt metadatası: sidecar dosyasının içeriği ve olayın `x-recording-metadata` başlığı.
+/// Zamanlar (`pauses`, `durationMs`) kayıt zaman çizelgesinde, ms.
+fn recording_metadata(session: &RecordingSession, ended_at: SystemTime) -> serde_json::Value {
+    let pauses: Vec<serde_json::Value> = session
+        .pauses
+        .iter()
+        .map(|p| serde_json::json!({ "startMs": p.start_ms, "endMs": p.end_ms }))
+        .collect();
+    // Şifreli kayıtlar için tüketicinin hangi kiracı anahtarıyla açacağını bilmesi gerekir.
+    let encryption = session
+        .pipeline
+        .key_id()
+        .map(|key_id| serde_json::json!({ "scheme": "AES-256-GCM-ENVELOPE", "keyId": key_id }));
+    let channel_layout: &[&str] = if session.spec.channels == 2 {
+        &["caller", "bot"]
+    } else {
+        &["mixed"]
+    };
+    let qos = session.qos.map(|q| {
+        serde_json::json!({
+            "expected": q.expected,
+            "received": q.received,
+            "lost": q.lost,
+            "lossPct": q.loss_pct,
+            "jitterMs": q.jitter_ms,
+            "outOfOrder": q.out_of_order,
+            "duplicates": q.duplicates,
+            "rttMs": q.rtt_ms,
+        })
+    });
+    let rfc3339 = |t: SystemTime| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339();
+
+    serde_json::json!({
+        "callId": session.call_id,
+        "recordingUri": session.pipeline.uri(),
+        "format": session.pipeline.format().extension(),
+        "sampleRate": session.spec.sample_rate,
+        "channels": session.spec.channels,
+        "channelLayout": channel_layout,
+        "wireCodecs": session.wire_codecs,
+        "durationMs": session.position_ms(),
+        "startedAt": rfc3339(session.started_at),
+        "endedAt": rfc3339(ended_at),
+        "truncated": session.max_reached_warned,
+        "pauses": pauses,
+        "encryption": encryption,
+        "qos": qos,
+    })
+}
+
+fn recording_metadata_headers(metadata: &serde_json::Value) -> FieldTable {
+    let mut headers = FieldTable::default();
+    headers.insert(
+        "x-recording-metadata".into(),
+        AMQPValue::LongString(metadata.to_string().into()),
+    );
+    headers
+}
+
 pub async fn load_and_resample_samples_from_uri(
     uri: &str,
     app_state: &AppState,
     config: &std::sync::Arc<crate::config::AppConfig>,
 ) -> Result<std::sync::Arc<Vec<i16>>> {
-    use crate::audio::load_or_get_from_cache;
+    use crate::audio::{
+        check_remote_source, decode_data_uri, load_or_get_from_cache,
+        load_remote_or_get_from_cache, RemoteAudioLimits,
+    };
     use std::path::PathBuf;
 
     if let Some(path_part) = uri.strip_prefix("file://") {
         let mut final_path = PathBuf::from(&config.assets_base_path);
         final_path.push(path_part.trim_start_matches('/'));
-        let samples_8k = load_or_get_from_cache(&app_state.audio_cache, &final_path).await?;
+        let samples_8k = load_or_get_from_cache(
+            &app_state.audio_cache,
+            &final_path,
+            config.audio_cache_max_bytes,
+        )
+        .await?;
         return Ok(samples_8k);
     }
+    if uri.starts_with("data:") {
+        // Satır içi ses her istekte farklıdır; önbelleğe alınmaz.
+        let max_bytes = config.audio_data_uri_max_bytes;
+        let uri = uri.to_string();
+        let samples = tokio::task::spawn_blocking(move || decode_data_uri(&uri, max_bytes))
+            .await
+            .map_err(|e| anyhow!("Thread join hatası: {}", e))??;
+        return Ok(std::sync::Arc::new(samples));
+    }
+    if ["s3://", "http://", "https://"]
+        .iter()
+        .any(|scheme| uri.starts_with(scheme))
+    {
+        check_remote_source(
+            uri,
+            &config.audio_remote_s3_allow,
+            &config.audio_remote_http_hosts,
+        )?;
+        let sse = config
+            .s3_config
+            .as_ref()
+            .map(|c| c.sse.clone())
+            .unwrap_or_default();
+        let limits = RemoteAudioLimits {
+            max_bytes: config.audio_remote_max_bytes,
+            timeout: config.audio_remote_timeout,
+            revalidate_after: config.audio_remote_revalidate,
+            cache_max_bytes: config.audio_cache_max_bytes,
+        };
+        return load_remote_or_get_from_cache(
+            &app_state.audio_cache,
+            uri,
+            app_state.s3_client.as_deref().map(|client| (client, &sse)),
+            &app_state.http_client,
+            limits,
+        )
+        .await;
+    }
     Err(anyhow!("Desteklenmeyen URI şeması: {}", uri))
 }
+
+/// Medya olaylarını (DTMF, konuşma tespiti vb.) RTP döngüsünü bloklamadan RabbitMQ'ya basar.
+pub fn spawn_media_event(
+    app_state: &AppState,
+    tenant_id: &str,
+    trace_id: &str,
+    event_type: &'static str,
+    payload: serde_json::Value,
+) {
+    let Some(mq_client) = app_state.rabbitmq_publisher.clone() else {
+        return;
+    };
+    let event = GenericEvent {
+        event_type: event_type.to_string(),
+        trace_id: trace_id.to_string(),
+        timestamp: Some(prost_types::Timestamp::from(std::time::SystemTime::now())),
+        tenant_id: tenant_id.to_string(),
+        payload_json: payload.to_string(),
+    };
+    tokio::spawn(async move {
+        if let Err(e) = mq_client
+            .publish_with_confirm(event_type, &event.encode_to_vec())
+            .await
+        {
+            error!(event = "MEDIA_EVENT_PUBLISH_FAIL", event_type = event_type, error = %e, "Medya olayı RabbitMQ'ya iletilemedi.");
+        }
+    });
+}
@@ -0,0 +1,722 @@
+// Dosya: src/rtp/spool.rs
+use crate::metrics::{
+    RECORDING_SPOOL_BYTES, RECORDING_SPOOL_DROPPED_TOTAL, RECORDING_SPOOL_ENTRIES,
+};
+use crate::rtp::store::{is_no_such_upload, RecordingStore, S3Store, UploadedPart};
+use crate::rtp::writers::RecordingTarget;
+use crate::state::AppState;
+use anyhow::{Context, Result};
+use metrics::{counter, gauge};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tracing::{debug, error, info, warn};
+
+/// `media_recording_path` altında spool dizini; `file://` kayıt hedefleri buraya yazamaz.
+pub const RECORDING_SPOOL_DIR: &str = ".spool";
+/// Spool altında çağrı sürerken açık olan multipart yüklemelerin kayıtları.
+const OPEN_UPLOADS_DIR: &str = "uploads";
+const RETRY_BASE: Duration = Duration::from_secs(30);
+const RETRY_MAX: Duration = Duration::from_secs(3600);
+const SCAN_INTERVAL: Duration = Duration::from_secs(15);
+/// Spool bu orana ulaşınca uyarı verilir; sınır aşılınca en eski kayıtlar silinir.
+const NEAR_FULL_RATIO: f64 = 0.8;
+
+/// Spool veri dosyasındaki (`<id>.part`) bir multipart parçası.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpooledPart {
+    pub part_number: i32,
+    pub offset: u64,
+    pub len: u64,
+}
+
+/// Nesne hedefe ulaştıktan sonra yayınlanacak `call.recording.available` olayı.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpooledEvent {
+    pub call_id: String,
+    pub trace_id: String,
+    pub recording_uri: String,
+    pub metadata: serde_json::Value,
+}
+
+/// Spool kaydının manifestosu (`<id>.json`). S3'e zaten yüklenmiş parçalar açık kalan
+/// multipart yüklemede durur; kalanlar yerel veri dosyasındadır.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpoolEntry {
+    pub id: String,
+    pub call_id: String,
+    pub bucket: String,
+    pub key: String,
+    pub content_type: String,
+    /// Hiç açılamadıysa `None`; yeniden denemede açılır.
+    pub upload_id: Option<String>,
+    pub uploaded: Vec<UploadedPart>,
+    pub pending: Vec<SpooledPart>,
+    pub created_at_ms: u64,
+    pub attempts: u32,
+    pub next_attempt_at_ms: u64,
+    /// Nesne yüklendi, yalnızca olay yayını bekliyor.
+    pub landed: bool,
+    pub event: Option<SpooledEvent>,
+}
+
+/// Çağrı sürerken açık olan multipart yükleme (`uploads/<id>.json`). Yükleme kapanınca silinir;
+/// servis çökerse bir sonraki açılışta taranır: ilk parça yüklenmişse kayıt yer tutucu
+/// başlıkla tamamlanır, değilse yükleme iptal edilir.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenUpload {
+    pub id: String,
+    /// Kaydı yazan süreç; tarama yalnızca önceki süreçlerden kalanlara dokunur.
+    pub boot_id: String,
+    pub call_id: String,
+    pub bucket: String,
+    pub key: String,
+    pub upload_id: String,
+    pub uploaded: Vec<UploadedPart>,
+}
+
+/// S3 yüklemesi başarısız olan parçaların yazıldığı yerel veri dosyası.
+#[derive(Debug)]
+pub struct SpoolWriter {
+    id: String,
+    path: PathBuf,
+    file: tokio::fs::File,
+    len: u64,
+    parts: Vec<SpooledPart>,
+}
+
+impl SpoolWriter {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub async fn append(&mut self, part_number: i32, bytes: &[u8]) -> Result<()> {
+        self.file.write_all(bytes).await?;
+        // Diskteki boyut spool sınırına sayılır; yazma arka planda asılı kalmasın.
+        self.file.flush().await?;
+        self.parts.push(SpooledPart {
+            part_number,
+            offset: self.len,
+            len: bytes.len() as u64,
+        });
+        self.len += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Veriyi diske kalıcı yazar ve dosyadaki parçaları döndürür.
+    pub async fn finish(mut self) -> Result<Vec<SpooledPart>> {
+        self.file.sync_all().await?;
+        Ok(std::mem::take(&mut self.parts))
+    }
+
+    pub async fn discard(self) {
+        let _ = tokio::fs::remove_file(&self.path).await;
+    }
+}
+
+/// `media_recording_path` altındaki kalıcı kayıt kuyruğu. S3 erişilemezken kayıtlar burada
+/// bekler; arka plan görevi üstel geri çekilmeyle yüklemeyi yeniden dener. Manifestolar
+/// diskte olduğu için servis yeniden başlasa da kuyruk kaldığı yerden devam eder.
+#[derive(Debug)]
+pub struct RecordingSpool {
+    dir: PathBuf,
+    max_bytes: u64,
+    boot_id: String,
+    /// Şu an yüklenen veya silinen kayıtlar; yeniden deneme ile sınır uygulaması aynı kayda
+    /// aynı anda dokunmaz.
+    claimed: Mutex<HashSet<String>>,
+}
+
+impl RecordingSpool {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        Self {
+            dir,
+            max_bytes,
+            boot_id: uuid::Uuid::new_v4().to_string(),
+            claimed: Mutex::new(HashSet::new()),
+        }
+    }
+
+    fn claim(&self, id: &str) -> bool {
+        self.claimed.lock().unwrap().insert(id.to_string())
+    }
+
+    fn release(&self, id: &str) {
+        self.claimed.lock().unwrap().remove(id);
+    }
+
+    fn data_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.part", id))
+    }
+
+    fn manifest_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn open_upload_path(&self, id: &str) -> PathBuf {
+        self.dir.join(OPEN_UPLOADS_DIR).join(format!("{}.json", id))
+    }
+
+    pub fn open_upload(
+        &self,
+        call_id: &str,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> OpenUpload {
+        OpenUpload {
+            id: uuid::Uuid::new_v4().to_string(),
+            boot_id: self.boot_id.clone(),
+            call_id: call_id.to_string(),
+            bucket: bucket.to_string(),
+            key: key.to_string(),
+            upload_id: upload_id.to_string(),
+            uploaded: Vec::new(),
+        }
+    }
+
+    /// Açık yüklemenin kaydını yazar veya günceller. Yazılamazsa yalnızca uyarı verilir;
+    /// kayıt sürer, çökme olursa yükleme bucket yaşam döngüsü kuralına kalır.
+    pub async fn track_upload(&self, upload: &OpenUpload) {
+        let path = self.open_upload_path(&upload.id);
+        let result = async {
+            tokio::fs::create_dir_all(self.dir.join(OPEN_UPLOADS_DIR)).await?;
+            let tmp = path.with_extension("json.tmp");
+            tokio::fs::write(&tmp, serde_json::to_vec(upload)?).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+        if let Err(e) = result {
+            warn!(event = "RECORDING_UPLOAD_TRACK_FAIL", sip.call_id = %upload.call_id, error = %e, "⚠️ Açık multipart yükleme kaydı yazılamadı.");
+        }
+    }
+
+    pub async fn untrack_upload(&self, id: &str) {
+        let _ = tokio::fs::remove_file(self.open_upload_path(id)).await;
+    }
+
+    pub async fn writer(&self) -> Result<SpoolWriter> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let id = uuid::Uuid::new_v4().to_string();
+        let path = self.data_path(&id);
+        let file = tokio::fs::File::create(&path)
+            .await
+            .with_context(|| format!("Spool dosyası açılamadı: {}", path.display()))?;
+        Ok(SpoolWriter {
+            id,
+            path,
+            file,
+            len: 0,
+            parts: Vec::new(),
+        })
+    }
+
+    /// Manifestoyu yazar (kayıt artık yeniden deneme kuyruğundadır) ve boyut sınırını uygular.
+    pub async fn commit(&self, entry: &SpoolEntry) -> Result<()> {
+        self.save(entry).await?;
+        self.enforce_cap().await;
+        Ok(())
+    }
+
+    async fn save(&self, entry: &SpoolEntry) -> Result<()> {
+        // Yarım yazılmış manifesto okunmasın diye geçici dosya + rename.
+        let tmp = self.dir.join(format!("{}.json.tmp", entry.id));
+        tokio::fs::write(&tmp, serde_json::to_vec(entry)?).await?;
+        tokio::fs::rename(&tmp, self.manifest_path(&entry.id)).await?;
+        Ok(())
+    }
+
+    async fn remove(&self, id: &str) {
+        let _ = tokio::fs::remove_file(self.manifest_path(id)).await;
+        let _ = tokio::fs::remove_file(self.data_path(id)).await;
+    }
+
+    /// Manifestosu olan kayıtlar, en eskisi önce; yanlarında diskte kapladıkları bayt.
+    async fn load_entries(&self) -> Vec<(SpoolEntry, u64)> {
+        let mut entries = Vec::new();
+        let Ok(mut dir) = tokio::fs::read_dir(&self.dir).await else {
+            return entries;
+        };
+        while let Ok(Some(item)) = dir.next_entry().await {
+            let path = item.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let entry = match tokio::fs::read(&path)
+                .await
+                .map(|raw| serde_json::from_slice::<SpoolEntry>(&raw))
+            {
+                Ok(Ok(entry)) => entry,
+                _ => {
+                    warn!(event = "RECORDING_SPOOL_CORRUPT", file.path = %path.display(), "⚠️ Okunamayan spool manifestosu atlanıyor.");
+                    continue;
+                }
+            };
+            let data_len = tokio::fs::metadata(self.data_path(&entry.id))
+                .await
+                .map(|m| m.len())
+                .unwrap_or(0);
+            let manifest_len = item.metadata().await.map(|m| m.len()).unwrap_or(0);
+            entries.push((entry, data_len + manifest_len));
+        }
+        entries.sort_by_key(|(entry, _)| entry.created_at_ms);
+        entries
+    }
+
+    /// Manifestosu henüz yazılmamış veri dosyaları: çağrısı süren `SpoolWriter`'lar.
+    async fn in_flight_bytes(&self) -> u64 {
+        let mut total = 0;
+        let Ok(mut dir) = tokio::fs::read_dir(&self.dir).await else {
+            return total;
+        };
+        while let Ok(Some(item)) = dir.next_entry().await {
+            let path = item.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("part")
+                && !path.with_extension("json").exists()
+            {
+                total += item.metadata().await.map(|m| m.len()).unwrap_or(0);
+            }
+        }
+        total
+    }
+
+    /// Sınır aşılırsa en eski kayıtları siler. Yazılmakta olan spool dosyaları da sınıra sayılır
+    /// ama silinmez; o an yüklenmekte olan kayıt da atlanır. Her silme kalıcı kayıp demektir;
+    /// alarm üretir.
+    pub async fn enforce_cap(&self) {
+        let entries = self.load_entries().await;
+        let mut total: u64 =
+            entries.iter().map(|(_, size)| size).sum::<u64>() + self.in_flight_bytes().await;
+        gauge!(RECORDING_SPOOL_ENTRIES).set(entries.len() as f64);
+
+        for (entry, size) in &entries {
+            if total <= self.max_bytes {
+                break;
+            }
+            if !self.claim(&entry.id) {
+                continue;
+            }
+            error!(event = "RECORDING_SPOOL_EVICTED", sip.call_id = %entry.call_id, s3.key = %entry.key, spool.bytes = total, spool.max_bytes = self.max_bytes, "🔥 Kayıt spool'u dolu, en eski kayıt silindi (kalıcı kayıp)!");
+            counter!(RECORDING_SPOOL_DROPPED_TOTAL, "reason" => "evicted").increment(1);
+            self.remove(&entry.id).await;
+            self.release(&entry.id);
+            total -= size;
+            gauge!(RECORDING_SPOOL_ENTRIES).decrement(1.0);
+        }
+        gauge!(RECORDING_SPOOL_BYTES).set(total as f64);
+        if total as f64 > self.max_bytes as f64 * NEAR_FULL_RATIO {
+            warn!(
+                event = "RECORDING_SPOOL_NEAR_FULL",
+                spool.bytes = total,
+                spool.max_bytes = self.max_bytes,
+                "⚠️ Kayıt spool'u dolmak üzere, S3 erişimini kontrol edin."
+            );
+        }
+    }
+
+    /// Manifestosu olmayan veri dosyaları (çağrı sürerken çöken servisten kalan) yüklenemez.
+    async fn remove_orphans(&self) {
+        let Ok(mut dir) = tokio::fs::read_dir(&self.dir).await else {
+            return;
+        };
+        while let Ok(Some(item)) = dir.next_entry().await {
+            let path = item.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("part")
+                && !path.with_extension("json").exists()
+            {
+                warn!(event = "RECORDING_SPOOL_ORPHAN_REMOVED", file.path = %path.display(), "Manifestosu olmayan yarım spool dosyası silindi.");
+                counter!(RECORDING_SPOOL_DROPPED_TOTAL, "reason" => "orphaned").increment(1);
+                let _ = tokio::fs::remove_file(&path).await;
+            }
+        }
+    }
+
+    /// Arka plan yeniden deneme görevi; servis ömrü boyunca çalışır.
+    pub async fn run_retry_worker(self: Arc<Self>, app_state: AppState) {
+        self.remove_orphans().await;
+        let mut ticker = tokio::time::interval(SCAN_INTERVAL);
+        loop {
+            ticker.tick().await;
+            self.sweep_open_uploads(&app_state).await;
+            let now = now_ms();
+            for (mut entry, _) in self.load_entries().await {
+                if entry.next_attempt_at_ms > now || !self.claim(&entry.id) {
+                    continue;
+                }
+                // Liste alındıktan sonra sınır uygulaması kaydı silmiş olabilir.
+                if self.manifest_path(&entry.id).exists() {
+                    self.retry(&mut entry, &app_state).await;
+                }
+                self.release(&entry.id);
+            }
+            self.enforce_cap().await;
+        }
+    }
+
+    /// Önceki süreçlerden kalan açık yüklemeleri kapatır: ilk parçası yüklenmiş olanlar
+    /// (yer tutucu başlıklı, şifresiz) yüklenen parçalarla tamamlanır, diğerleri iptal edilir.
+    /// Kurtarılan kayıt için `call.recording.available` yayınlanmaz; olay `RECORDING_RECOVERED`
+    /// logundadır. S3'e ulaşılamazsa kayıt bir sonraki taramaya kalır.
+    async fn sweep_open_uploads(&self, app_state: &AppState) {
+        let Ok(mut dir) = tokio::fs::read_dir(self.dir.join(OPEN_UPLOADS_DIR)).await else {
+            return;
+        };
+        while let Ok(Some(item)) = dir.next_entry().await {
+            let path = item.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let upload = match tokio::fs::read(&path)
+                .await
+                .map(|raw| serde_json::from_slice::<OpenUpload>(&raw))
+            {
+                Ok(Ok(upload)) => upload,
+                _ => {
+                    warn!(event = "RECORDING_SPOOL_CORRUPT", file.path = %path.display(), "⚠️ Okunamayan açık yükleme kaydı siliniyor.");
+                    let _ = tokio::fs::remove_file(&path).await;
+                    continue;
+                }
+            };
+            if upload.boot_id == self.boot_id {
+                continue;
+            }
+            let Some(store) = s3_store(app_state, &upload.bucket) else {
+                return;
+            };
+            let mut parts = upload.uploaded.clone();
+            parts.sort_by_key(|p| p.part_number);
+            if parts.first().is_some_and(|p| p.part_number == 1) {
+                match store
+                    .complete_multipart(&upload.key, &upload.upload_id, &parts, &upload.call_id)
+                    .await
+                {
+                    Ok(()) => {
+                        warn!(event = "RECORDING_RECOVERED", sip.call_id = %upload.call_id, s3.key = %upload.key, parts = parts.len(), "⚠️ Servis çöktüğünde yarım kalan kayıt yer tutucu başlıkla tamamlandı.");
+                    }
+                    Err(e) if is_no_such_upload(&e) => {
+                        warn!(event = "RECORDING_RECOVERY_EXPIRED", sip.call_id = %upload.call_id, s3.key = %upload.key, "Yarım kalan kaydın multipart yüklemesi artık yok.");
+                    }
+                    Err(e) => {
+                        warn!(event = "RECORDING_RECOVERY_FAILED", sip.call_id = %upload.call_id, s3.key = %upload.key, error = %e, "⚠️ Yarım kalan kayıt tamamlanamadı, daha sonra tekrar denenecek.");
+                        continue;
+                    }
+                }
+            } else {
+                store
+                    .abort_multipart(&upload.key, &upload.upload_id, &upload.call_id)
+                    .await;
+            }
+            let _ = tokio::fs::remove_file(&path).await;
+        }
+    }
+
+    async fn retry(&self, entry: &mut SpoolEntry, app_state: &AppState) {
+        let Some(store) = s3_store(app_state, &entry.bucket) else {
+            return;
+        };
+        if !entry.landed && !entry.pending.is_empty() && !self.data_path(&entry.id).exists() {
+            error!(event = "RECORDING_SPOOL_UNRECOVERABLE", sip.call_id = %entry.call_id, s3.key = %entry.key, "🔥 Spool veri dosyası kayıp, kayıt kurtarılamadı!");
+            counter!(RECORDING_SPOOL_DROPPED_TOTAL, "reason" => "missing_data").increment(1);
+            self.remove(&entry.id).await;
+            return;
+        }
+        let result = async {
+            if !entry.landed {
+                self.upload(entry, store.as_ref()).await?;
+                entry.landed = true;
+                self.save(entry).await?;
+                info!(event = "RECORDING_SPOOL_UPLOADED", sip.call_id = %entry.call_id, s3.key = %entry.key, attempts = entry.attempts + 1, "✅ Spool'daki kayıt S3'e yüklendi.");
+            }
+            if let Some(event) = &entry.event {
+                crate::rtp::session_utils::announce_recording(
+                    app_state,
+                    &RecordingTarget::new(store.clone(), entry.key.clone()).sidecar(),
+                    &event.call_id,
+                    &event.trace_id,
+                    &event.recording_uri,
+                    event.metadata.clone(),
+                )
+                .await?;
+            }
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => self.remove(&entry.id).await,
+            // Yüklenmiş parçalar süresi dolan multipart ile birlikte gitti; geri getirilemez.
+            Err(e) if is_no_such_upload(&e) && !entry.uploaded.is_empty() => {
+                error!(event = "RECORDING_SPOOL_UNRECOVERABLE", sip.call_id = %entry.call_id, s3.key = %entry.key, error = %e, "🔥 S3 multipart yüklemesi artık yok, kayıt kurtarılamadı!");
+                counter!(RECORDING_SPOOL_DROPPED_TOTAL, "reason" => "upload_expired").increment(1);
+                self.remove(&entry.id).await;
+            }
+            Err(e) => {
+                if is_no_such_upload(&e) {
+                    entry.upload_id = None;
+                }
+                entry.attempts += 1;
+                let backoff = RETRY_BASE
+                    .saturating_mul(2u32.saturating_pow(entry.attempts - 1))
+                    .min(RETRY_MAX);
+                entry.next_attempt_at_ms = now_ms() + backoff.as_millis() as u64;
+                warn!(event = "RECORDING_SPOOL_RETRY_FAILED", sip.call_id = %entry.call_id, attempts = entry.attempts, backoff_ms = backoff.as_millis(), error = %e, "⚠️ Spool'daki kayıt yüklenemedi, daha sonra tekrar denenecek.");
+                if let Err(e) = self.save(entry).await {
+                    error!(event = "RECORDING_SPOOL_SAVE_FAIL", sip.call_id = %entry.call_id, error = %e, "Spool manifestosu güncellenemedi.");
+                }
+            }
+        }
+    }
+
+    /// Bekleyen parçaları açık (veya yeni açılan) multipart yüklemeye gönderip tamamlar.
+    /// Her parçadan sonra manifesto güncellenir; kesinti olursa kalan parçalardan devam edilir.
+    async fn upload(&self, entry: &mut SpoolEntry, store: &dyn RecordingStore) -> Result<()> {
+        let key = entry.key.clone();
+        let upload_id = match &entry.upload_id {
+            Some(id) => id.clone(),
+            None => {
+                let id = store.create_multipart(&key, &entry.content_type).await?;
+                entry.upload_id = Some(id.clone());
+                self.save(entry).await?;
+                id
+            }
+        };
+
+        let mut file = tokio::fs::File::open(self.data_path(&entry.id)).await?;
+        while let Some(part) = entry.pending.first().cloned() {
+            let mut data = vec![0u8; part.len as usize];
+            file.seek(SeekFrom::Start(part.offset)).await?;
+            file.read_exact(&mut data).await?;
+            let e_tag = store
+                .upload_part(
+                    &key,
+                    &upload_id,
+                    part.part_number,
+                    data.into(),
+                    &entry.call_id,
+                )
+                .await?;
+            entry.pending.remove(0);
+            // İlk parça hem yer tutucu başlıkla hem yamalı hâliyle gelmiş olabilir; son yüklenen
+            // geçerlidir.
+            entry.uploaded.retain(|p| p.part_number != part.part_number);
+            entry.uploaded.push(UploadedPart {
+                part_number: part.part_number,
+                e_tag,
+            });
+            self.save(entry).await?;
+            debug!(event = "RECORDING_SPOOL_PART_UPLOADED", sip.call_id = %entry.call_id, part = part.part_number, "Spool parçası yüklendi.");
+        }
+
+        let mut parts = entry.uploaded.clone();
+        parts.sort_by_key(|p| p.part_number);
+        store
+            .complete_multipart(&key, &upload_id, &parts, &entry.call_id)
+            .await
+    }
+}
+
+fn s3_store(app_state: &AppState, bucket: &str) -> Option<Arc<S3Store>> {
+    let client = app_state.s3_client.clone()?;
+    let sse = app_state
+        .port_manager
+        .config
+        .s3_config
+        .as_ref()
+        .map(|c| c.sse.clone())
+        .unwrap_or_default();
+    Some(Arc::new(S3Store::new(client, bucket.to_string(), sse)))
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtp::store::MemoryStore;
+    use std::sync::atomic::Ordering;
+
+    #[tokio::test]
+    async fn test_cap_evicts_oldest_first() {
+        let dir = std::env::temp_dir().join(format!("sentiric-spool-{}", std::process::id()));
+        let spool = RecordingSpool::new(dir.clone(), 5000);
+        for (created_at_ms, call_id) in ["eski", "orta", "yeni"].into_iter().enumerate() {
+            let mut writer = spool.writer().await.unwrap();
+            writer.append(1, &[0u8; 2000]).await.unwrap();
+            let entry = SpoolEntry {
+                id: writer.id().to_string(),
+                call_id: call_id.to_string(),
+                bucket: "b".into(),
+                key: format!("{}.wav", call_id),
+                content_type: "audio/wav".into(),
+                upload_id: None,
+                uploaded: Vec::new(),
+                pending: writer.finish().await.unwrap(),
+                created_at_ms: created_at_ms as u64,
+                attempts: 0,
+                next_attempt_at_ms: 0,
+                landed: false,
+                event: None,
+            };
+            spool.commit(&entry).await.unwrap();
+        }
+        // Üç kayıt (~6 KB) 5 KB sınırını aşar; yalnızca en eskisi silinir.
+        let left: Vec<String> = spool
+            .load_entries()
+            .await
+            .into_iter()
+            .map(|(e, _)| e.call_id)
+            .collect();
+        assert_eq!(left, ["orta", "yeni"]);
+
+        // Çağrısı süren (manifestosuz) spool dosyası da sınıra sayılır ama silinmez.
+        let mut writer = spool.writer().await.unwrap();
+        writer.append(1, &[0u8; 2000]).await.unwrap();
+        spool.enforce_cap().await;
+        let left: Vec<String> = spool
+            .load_entries()
+            .await
+            .into_iter()
+            .map(|(e, _)| e.call_id)
+            .collect();
+        assert_eq!(left, ["yeni"]);
+        assert!(spool.data_path(writer.id()).exists());
+        writer.discard().await;
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    /// `fail_part` numaralı parçayı bir kez yüklemeyen depo.
+    #[derive(Debug)]
+    struct FlakyStore {
+        inner: MemoryStore,
+        fail_part: std::sync::atomic::AtomicI32,
+    }
+
+    #[async_trait::async_trait]
+    impl RecordingStore for FlakyStore {
+        fn uri(&self, key: &str) -> String {
+            self.inner.uri(key)
+        }
+
+        async fn put(
+            &self,
+            key: &str,
+            content_type: &str,
+            body: Vec<u8>,
+            call_id: &str,
+        ) -> Result<()> {
+            self.inner.put(key, content_type, body, call_id).await
+        }
+
+        async fn create_multipart(&self, key: &str, content_type: &str) -> Result<String> {
+            self.inner.create_multipart(key, content_type).await
+        }
+
+        async fn upload_part(
+            &self,
+            key: &str,
+            upload_id: &str,
+            part_number: i32,
+            data: bytes::Bytes,
+            call_id: &str,
+        ) -> Result<String> {
+            if self
+                .fail_part
+                .compare_exchange(part_number, 0, Ordering::SeqCst, Ordering::SeqCst)
+                .is_ok()
+            {
+                anyhow::bail!("bağlantı koptu");
+            }
+            self.inner
+                .upload_part(key, upload_id, part_number, data, call_id)
+                .await
+        }
+
+        async fn complete_multipart(
+            &self,
+            key: &str,
+            upload_id: &str,
+            parts: &[UploadedPart],
+            call_id: &str,
+        ) -> Result<()> {
+            self.inner
+                .complete_multipart(key, upload_id, parts, call_id)
+                .await
+        }
+
+        async fn abort_multipart(&self, key: &str, upload_id: &str, call_id: &str) {
+            self.inner.abort_multipart(key, upload_id, call_id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upload_resumes_from_manifest_and_detects_expired_upload() {
+        let dir =
+            std::env::temp_dir().join(format!("sentiric-spool-resume-{}", std::process::id()));
+        let spool = RecordingSpool::new(dir.clone(), u64::MAX);
+        let store = FlakyStore {
+            inner: MemoryStore::new(),
+            fail_part: 3.into(),
+        };
+
+        let spooled = |call_id: &str, pending| SpoolEntry {
+            id: String::new(),
+            call_id: call_id.to_string(),
+            bucket: "b".into(),
+            key: format!("{}.wav", call_id),
+            content_type: "audio/wav".into(),
+            upload_id: None,
+            uploaded: Vec::new(),
+            pending,
+            created_at_ms: 0,
+            attempts: 0,
+            next_attempt_at_ms: 0,
+            landed: false,
+            event: None,
+        };
+        // Başlık (1) en son spool'a yazılır.
+        let mut writer = spool.writer().await.unwrap();
+        for (n, data) in [(2, &b"iki"[..]), (3, b"uc"), (1, b"bir")] {
+            writer.append(n, data).await.unwrap();
+        }
+        let mut entry = SpoolEntry {
+            id: writer.id().to_string(),
+            ..spooled("kesik", writer.finish().await.unwrap())
+        };
+        spool.commit(&entry).await.unwrap();
+
+        // 3. parçada kopan deneme, yüklenenleri ve açık yüklemeyi manifestoya yazmış olmalı.
+        assert!(spool.upload(&mut entry, &store).await.is_err());
+        let (mut saved, _) = spool.load_entries().await.remove(0);
+        assert!(saved.upload_id.is_some());
+        assert_eq!(saved.uploaded.len(), 1);
+        assert_eq!(saved.pending.len(), 2);
+
+        spool.upload(&mut saved, &store).await.unwrap();
+        assert!(saved.pending.is_empty());
+        assert_eq!(store.inner.object("kesik.wav").unwrap().body, b"birikiuc");
+        spool.remove(&saved.id).await;
+
+        // Depoda artık olmayan yükleme tipli hatayla ayırt edilir.
+        let mut writer = spool.writer().await.unwrap();
+        writer.append(2, b"iki").await.unwrap();
+        let mut entry = SpoolEntry {
+            id: writer.id().to_string(),
+            upload_id: Some("silinmis".into()),
+            ..spooled("suresi-dolmus", writer.finish().await.unwrap())
+        };
+        spool.commit(&entry).await.unwrap();
+        let err = spool.upload(&mut entry, &store).await.unwrap_err();
+        assert!(is_no_such_upload(&err));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+// Dosya: src/rtp/stats.rs
+use crate::rtp::jitter::PushOutcome;
+
+/// RFC 3550 A.3 tarzı alım istatistikleri: beklenen/alınan paket, kayıp,
+/// sıra dışı ve tekrar eden paket sayaçları.
+#[derive(Debug, Default)]
+pub struct ReceptionStats {
+    base_ext_seq: Option<u64>,
+    max_ext_seq: u64,
+    received: u64,
+    duplicates: u64,
+    reordered: u64,
+    late: u64,
+}
+
+/// Periyodik raporlar (QoS tick, RTCP) için bir önceki raporun sayaç durumu.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IntervalMark {
+    expected: u64,
+    received: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct IntervalReport {
+    pub expected: u64,
+    pub received: u64,
+    pub lost: u64,
+    pub fraction_lost: f64,
+}
+
+impl ReceptionStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Jitter buffer'a yapılan her push sonrası çağrılır.
+    /// `highest_ext_seq`: push sonrası buffer'ın gördüğü en yüksek genişletilmiş sıra numarası.
+    pub fn record(&mut self, outcome: PushOutcome, highest_ext_seq: Option<u64>) {
+        let Some(highest) = highest_ext_seq else {
+            return;
+        };
+
+        match outcome {
+            PushOutcome::Duplicate => {
+                self.duplicates += 1;
+                return;
+            }
+            PushOutcome::Reordered => self.reordered += 1,
+            PushOutcome::Late => {
+                self.reordered += 1;
+                self.late += 1;
+            }
+            PushOutcome::Resynced => {
+                // Yeni akışta sayaçlar devam eder, sadece sıra tabanı kaydırılır.
+                let expected_so_far = self.expected();
+                self.base_ext_seq = Some(highest.saturating_sub(expected_so_far));
+            }
+            PushOutcome::Accepted => {}
+        }
+
+        if self.base_ext_seq.is_none() {
+            self.base_ext_seq = Some(highest);
+        }
+        self.max_ext_seq = highest;
+        self.received += 1;
+    }
+
+    pub fn expected(&self) -> u64 {
+        match self.base_ext_seq {
+            Some(base) => self.max_ext_seq.saturating_sub(base) + 1,
+            None => 0,
+        }
+    }
+
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+
+    /// Kümülatif kayıp. Geç gelen paketler alınmış sayılır, negatif kayıp 0'a sabitlenir.
+    pub fn lost(&self) -> u64 {
+        self.expected().saturating_sub(self.received)
+    }
+
+    pub fn loss_ratio(&self) -> f64 {
+        let expected = self.expected();
+        if expected == 0 {
+            0.0
+        } else {
+            self.lost() as f64 / expected as f64
+        }
+    }
+
+    pub fn duplicates(&self) -> u64 {
+        self.duplicates
+    }
+
+    pub fn reordered(&self) -> u64 {
+        self.reordered
+    }
+
+    pub fn late(&self) -> u64 {
+        self.late
+    }
+
+    pub fn max_ext_seq(&self) -> u64 {
+        self.max_ext_seq
+    }
+
+    /// `mark`'tan bu yana geçen aralığın kayıp oranını hesaplar ve `mark`'ı günceller.
+    pub fn interval(&self, mark: &mut IntervalMark) -> IntervalReport {
+        let expected = self.expected() - mark.expected.min(self.expected());
+        let received = self.received - mark.received.min(self.received);
+        mark.expected = self.expected();
+        mark.received = self.received;
+
+        let lost = expected.saturating_sub(received);
+        let fraction_lost = if expected == 0 {
+            0.0
+        } else {
+            lost as f64 / expected as f64
+        };
+        IntervalReport {
+            expected,
+            received,
+            lost,
+            fraction_lost,
+        }
+    }
+}
+
+/// 8 kHz zaman damgası birimindeki jitter'ı milisaniyeye çevirir.
+pub fn jitter_to_millis(jitter: f64) -> f64 {
+    jitter / 8.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtp::jitter::JitterBuffer;
+    use sentiric_rtp_core::{RtpHeader, RtpPacket};
+    use tokio::time::Instant;
+
+    fn feed(stats: &mut ReceptionStats, jb: &mut JitterBuffer, seqs: &[u16]) {
+        for &seq in seqs {
+            let packet = RtpPacket {
+                header: RtpHeader::new(0, seq, seq as u32 * 160, 0xABCD),
+                payload: vec![0; 160],
+            };
+            let outcome = jb.push(packet, Instant::now());
+            stats.record(outcome, jb.highest_ext_seq());
+        }
+    }
+
+    #[test]
+    fn test_wraparound_reorder_duplicate_and_intervals() {
+        let mut stats = ReceptionStats::new();
+        let mut jb = JitterBuffer::new(3, 50);
+        let mut mark = IntervalMark::default();
+
+        // Sıra numarası 65535 -> 0 geçişinde kayıp sayılmaz.
+        feed(&mut stats, &mut jb, &[65534]);
+        let base = stats.max_ext_seq();
+        feed(&mut stats, &mut jb, &[65535, 0, 1]);
+        assert_eq!(stats.max_ext_seq() - base, 3);
+        assert_eq!(stats.expected(), 4);
+        assert_eq!(stats.lost(), 0);
+
+        let first = stats.interval(&mut mark);
+        assert_eq!((first.expected, first.received, first.lost), (4, 4, 0));
+
+        // 3 sıra dışı gelir ve tekrarlanır, 5 kaybolur.
+        feed(&mut stats, &mut jb, &[2, 4, 3, 3, 6]);
+        assert_eq!(stats.reordered(), 1);
+        assert_eq!(stats.duplicates(), 1);
+        assert_eq!(stats.received(), 8);
+        assert_eq!(stats.expected(), 9);
+        assert_eq!(stats.lost(), 1);
+
+        // Aralık yalnızca son işaretten bu yanayı sayar.
+        let second = stats.interval(&mut mark);
+        assert_eq!((second.expected, second.received, second.lost), (5, 4, 1));
+        assert!((second.fraction_lost - 0.2).abs() < 1e-9);
+        let empty = stats.interval(&mut mark);
+        assert_eq!(
+            (empty.expected, empty.lost, empty.fraction_lost),
+            (0, 0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,611 @@
+// Dosya: src/rtp/store.rs
+use crate::config::S3Sse;
+use crate::metrics::S3_UPLOAD_FAILURES;
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use aws_sdk_s3::error::ProvideErrorMetadata;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart, ServerSideEncryption};
+use aws_sdk_s3::Client as S3Client;
+use bytes::Bytes;
+use metrics::counter;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::AsyncWriteExt;
+use tokio::time::{sleep, timeout, Duration};
+use tracing::{debug, error, info, instrument, warn};
+
+/// Yerel depoda tamamlanmamış multipart yüklemelerin parçaları (kök dizin altında).
+pub const LOCAL_UPLOADS_DIR: &str = ".uploads";
+
+/// Multipart yükleme depoda artık yok (iptal edildi veya yaşam döngüsü kuralıyla silindi).
+/// Kalıcıdır; aynı yükleme kimliğiyle yeniden denenmez.
+#[derive(Debug)]
+pub struct NoSuchUpload;
+
+impl std::fmt::Display for NoSuchUpload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("NoSuchUpload")
+    }
+}
+
+impl std::error::Error for NoSuchUpload {}
+
+/// Hata zincirinde `NoSuchUpload` var mı.
+pub fn is_no_such_upload(error: &anyhow::Error) -> bool {
+    error.chain().any(|e| e.is::<NoSuchUpload>())
+}
+
+fn no_such_upload(upload_id: &str) -> anyhow::Error {
+    anyhow::Error::new(NoSuchUpload).context(format!("Multipart yükleme yok: {}", upload_id))
+}
+
+/// Yüklenmiş bir multipart parçası (numara ve depo etiketi).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UploadedPart {
+    pub part_number: i32,
+    pub e_tag: String,
+}
+
+/// Kayıtların yazıldığı nesne deposu. Kayıt çağrı sürerken parça parça akıtıldığı için arayüz
+/// S3 multipart modelini izler: parçalar herhangi bir sırayla yüklenir, tamamlanınca numara
+/// sırasıyla birleştirilir. Aynı numara birden çok kez yüklenebilir, son yüklenen geçerlidir:
+/// 1 numaralı parça (başlık) dolunca yer tutucu başlıkla erkenden, kapanışta yamalı hâliyle
+/// yeniden gelir.
+#[async_trait]
+pub trait RecordingStore: Send + Sync + std::fmt::Debug {
+    /// `key`'in olaylarda yayınlanan adresi (`s3://...`, `file://...`).
+    fn uri(&self, key: &str) -> String;
+
+    /// Uzak (S3) depolarda bucket adı; kesintide kayıt spool'a yalnızca bunlar için alınır.
+    fn bucket(&self) -> Option<&str> {
+        None
+    }
+
+    /// Küçük bir nesneyi tek seferde yazar.
+    async fn put(&self, key: &str, content_type: &str, body: Vec<u8>, call_id: &str) -> Result<()>;
+
+    /// Multipart yüklemeyi açar ve kimliğini döndürür.
+    async fn create_multipart(&self, key: &str, content_type: &str) -> Result<String>;
+
+    /// Parçayı yükler ve etiketini döndürür.
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Bytes,
+        call_id: &str,
+    ) -> Result<String>;
+
+    /// `parts` numara sırasıyla verilmelidir.
+    async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[UploadedPart],
+        call_id: &str,
+    ) -> Result<()>;
+
+    /// Yarım yüklemeyi iptal eder; hata yalnızca loglanır.
+    async fn abort_multipart(&self, key: &str, upload_id: &str, call_id: &str);
+}
+
+#[derive(Debug, Clone)]
+pub struct S3Store {
+    client: Arc<S3Client>,
+    bucket: String,
+    sse: S3Sse,
+}
+
+impl S3Store {
+    pub fn new(client: Arc<S3Client>, bucket: String, sse: S3Sse) -> Self {
+        Self {
+            client,
+            bucket,
+            sse,
+        }
+    }
+}
+
+#[async_trait]
+impl RecordingStore for S3Store {
+    fn uri(&self, key: &str) -> String {
+        format!("s3://{}/{}", self.bucket, key)
+    }
+
+    fn bucket(&self) -> Option<&str> {
+        Some(&self.bucket)
+    }
+
+    #[instrument(skip(self, body), fields(s3.bucket = %self.bucket, s3.key = %key, file.size_bytes = body.len()))]
+    async fn put(
+        &self,
+        key: &str,
+        content_type: &str,
+        body: Vec<u8>,
+        call_id: &str, // [ARCH-COMPLIANCE] Trace ID context propagation eklendi
+    ) -> Result<()> {
+        const MAX_RETRIES: u32 = 3;
+        let mut attempt = 0;
+        let sse = &self.sse;
+
+        // [ARCH-COMPLIANCE] SUTS v4.2: Kayıt süreci bir arka plan görevi olduğu için DEBUG yapıldı.
+        // Başarı (SUCCESS) INFO kalmaya devam edecek.
+        debug!(event = "S3_UPLOAD_START", sip.call_id = %call_id, "☁️ Kayıt dosyası doğrudan bellekten S3'e yükleniyor...");
+
+        loop {
+            attempt += 1;
+            let s3_future = self
+                .client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .content_type(content_type)
+                .set_server_side_encryption(sse.algorithm().map(ServerSideEncryption::from))
+                .set_ssekms_key_id(sse.kms_key_id())
+                .set_sse_customer_algorithm(sse.customer_algorithm())
+                .set_sse_customer_key(sse.customer_key())
+                .set_sse_customer_key_md5(sse.customer_key_md5())
+                .body(ByteStream::from(body.clone()))
+                .send();
+
+            match timeout(Duration::from_secs(15), s3_future).await {
+                Ok(Ok(_)) => {
+                    info!(event = "S3_UPLOAD_SUCCESS", sip.call_id = %call_id, attempt = attempt, "✅ Dosya S3'e başarıyla yüklendi.");
+                    return Ok(());
+                }
+                Ok(Err(e)) => {
+                    counter!(S3_UPLOAD_FAILURES).increment(1);
+
+                    if attempt >= MAX_RETRIES {
+                        error!(event = "S3_UPLOAD_ERROR", sip.call_id = %call_id, error = ?e, "❌ S3 Upload {} deneme sonrası başarısız oldu.", MAX_RETRIES);
+                        return Err(anyhow!("S3 Upload Bounded Retry Failed: {:?}", e));
+                    }
+
+                    let backoff = Duration::from_millis(2u64.pow(attempt) * 500);
+                    warn!(event = "S3_UPLOAD_RETRY", sip.call_id = %call_id, attempt = attempt, backoff_ms = backoff.as_millis(), "⚠️ S3 Upload başarısız, tekrar deneniyor...");
+                    sleep(backoff).await;
+                }
+                Err(_) => {
+                    counter!(S3_UPLOAD_FAILURES).increment(1);
+
+                    if attempt >= MAX_RETRIES {
+                        error!(event = "S3_UPLOAD_TIMEOUT_FATAL", sip.call_id = %call_id, "❌ S3 Upload {} deneme boyunca Timeout yedi.", MAX_RETRIES);
+                        return Err(anyhow!("S3 Upload Timeout Exceeded after retries"));
+                    }
+
+                    let backoff = Duration::from_millis(2u64.pow(attempt) * 500);
+                    warn!(event = "S3_UPLOAD_TIMEOUT", sip.call_id = %call_id, attempt = attempt, "⚠️ S3 Upload isteği 15 saniyede cevap vermedi (Timeout). Tekrar deneniyor...");
+                    sleep(backoff).await;
+                }
+            }
+        }
+    }
+
+    async fn create_multipart(&self, key: &str, content_type: &str) -> Result<String> {
+        let sse = &self.sse;
+        let created = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .content_type(content_type)
+            .set_server_side_encryption(sse.algorithm().map(ServerSideEncryption::from))
+            .set_ssekms_key_id(sse.kms_key_id())
+            .set_sse_customer_algorithm(sse.customer_algorithm())
+            .set_sse_customer_key(sse.customer_key())
+            .set_sse_customer_key_md5(sse.customer_key_md5())
+            .send()
+            .await
+            .map_err(|e| {
+                counter!(S3_UPLOAD_FAILURES).increment(1);
+                anyhow!("Multipart başlatılamadı: {:?}", e)
+            })?;
+        Ok(created
+            .upload_id()
+            .ok_or_else(|| anyhow!("S3 upload_id döndürmedi"))?
+            .to_string())
+    }
+
+    async fn upload_part(
+        &self,
+        key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Bytes,
+        call_id: &str,
+    ) -> Result<String> {
+        const MAX_RETRIES: u32 = 3;
+        let mut attempt = 0;
+        let sse = &self.sse;
+
+        loop {
+            attempt += 1;
+            let s3_future = self
+                .client
+                .upload_part()
+                .bucket(&self.bucket)
+                .key(key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .set_sse_customer_algorithm(sse.customer_algorithm())
+                .set_sse_customer_key(sse.customer_key())
+                .set_sse_customer_key_md5(sse.customer_key_md5())
+                .body(ByteStream::from(data.clone()))
+                .send();
+
+            let error = match timeout(Duration::from_secs(30), s3_future).await {
+                Ok(Ok(output)) => {
+                    return output
+                        .e_tag()
+                        .map(str::to_string)
+                        .ok_or_else(|| anyhow!("S3 parça {} için ETag döndürmedi", part_number));
+                }
+                Ok(Err(e)) if e.code() == Some("NoSuchUpload") => {
+                    return Err(no_such_upload(upload_id));
+                }
+                Ok(Err(e)) => format!("{:?}", e),
+                Err(_) => "timeout".to_string(),
+            };
+
+            counter!(S3_UPLOAD_FAILURES).increment(1);
+            if attempt >= MAX_RETRIES {
+                error!(event = "S3_PART_UPLOAD_ERROR", sip.call_id = %call_id, part = part_number, error = %error, "❌ Kayıt parçası {} deneme sonrası yüklenemedi.", MAX_RETRIES);
+                return Err(anyhow!("S3 part {} upload failed: {}", part_number, error));
+            }
+            let backoff = Duration::from_millis(2u64.pow(attempt) * 500);
+            warn!(event = "S3_PART_UPLOAD_RETRY", sip.call_id = %call_id, part = part_number, attempt = attempt, backoff_ms = backoff.as_millis(), "⚠️ Kayıt parçası yüklenemedi, tekrar deneniyor...");
+            sleep(backoff).await;
+        }
+    }
+
+    async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[UploadedPart],
+        call_id: &str,
+    ) -> Result<()> {
+        let sse = &self.sse;
+        let parts = parts
+            .iter()
+            .map(|p| {
+                CompletedPart::builder()
+                    .part_number(p.part_number)
+                    .e_tag(&p.e_tag)
+                    .build()
+            })
+            .collect();
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .set_sse_customer_algorithm(sse.customer_algorithm())
+            .set_sse_customer_key(sse.customer_key())
+            .set_sse_customer_key_md5(sse.customer_key_md5())
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| match e.code() {
+                Some("NoSuchUpload") => no_such_upload(upload_id),
+                _ => anyhow!("Multipart tamamlama başarısız: {:?}", e),
+            })?;
+        info!(event = "S3_MULTIPART_COMPLETE", sip.call_id = %call_id, s3.key = %key, "✅ Kayıt multipart olarak S3'e tamamlandı.");
+        Ok(())
+    }
+
+    async fn abort_multipart(&self, key: &str, upload_id: &str, call_id: &str) {
+        match self
+            .client
+            .abort_multipart_upload()
+            .bucket(&self.bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .send()
+            .await
+        {
+            Ok(_) => {
+                warn!(event = "S3_MULTIPART_ABORTED", sip.call_id = %call_id, s3.key = %key, "Yarım kalan multipart kayıt yüklemesi iptal edildi.")
+            }
+            Err(e) => {
+                error!(event = "S3_MULTIPART_ABORT_FAIL", sip.call_id = %call_id, s3.key = %key, error = ?e, "🔥 Multipart yükleme iptal edilemedi, bucket lifecycle kuralı temizlemeli.")
+            }
+        }
+    }
+}
+
+/// `root` altındaki yerel dosya sistemi deposu. Multipart parçaları `.uploads/<id>/` altında
+/// bekler; tamamlanınca sırayla birleştirilip hedef dosyaya taşınır (yarım dosya görünmez).
+#[derive(Debug, Clone)]
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.root.join(key)
+    }
+
+    fn upload_dir(&self, upload_id: &str) -> PathBuf {
+        self.root.join(LOCAL_UPLOADS_DIR).join(upload_id)
+    }
+
+    async fn write_atomic(path: &Path, parts: &[PathBuf]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut tmp = path.to_path_buf().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let result = async {
+            let mut file = tokio::fs::File::create(&tmp).await?;
+            for part in parts {
+                let mut src = tokio::fs::File::open(part).await?;
+                tokio::io::copy(&mut src, &mut file).await?;
+            }
+            file.sync_all().await?;
+            tokio::fs::rename(&tmp, path).await?;
+            Ok::<(), anyhow::Error>(())
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&tmp).await;
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl RecordingStore for LocalStore {
+    fn uri(&self, key: &str) -> String {
+        format!("file://{}", self.path(key).display())
+    }
+
+    async fn put(
+        &self,
+        key: &str,
+        _content_type: &str,
+        body: Vec<u8>,
+        call_id: &str,
+    ) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::File::create(&path).await?;
+        file.write_all(&body).await?;
+        file.sync_all().await?;
+        info!(event = "RECORDING_FILE_WRITTEN", sip.call_id = %call_id, file.path = %path.display(), "✅ Kayıt yerel diske yazıldı.");
+        Ok(())
+    }
+
+    async fn create_multipart(&self, _key: &str, _content_type: &str) -> Result<String> {
+        let upload_id = uuid::Uuid::new_v4().to_string();
+        tokio::fs::create_dir_all(self.upload_dir(&upload_id)).await?;
+        Ok(upload_id)
+    }
+
+    async fn upload_part(
+        &self,
+        _key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Bytes,
+        _call_id: &str,
+    ) -> Result<String> {
+        let path = self.upload_dir(upload_id).join(part_number.to_string());
+        tokio::fs::write(&path, &data)
+            .await
+            .with_context(|| format!("Kayıt parçası yazılamadı: {}", path.display()))?;
+        Ok(part_number.to_string())
+    }
+
+    async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[UploadedPart],
+        call_id: &str,
+    ) -> Result<()> {
+        let dir = self.upload_dir(upload_id);
+        let files: Vec<PathBuf> = parts
+            .iter()
+            .map(|p| dir.join(p.part_number.to_string()))
+            .collect();
+        let path = self.path(key);
+        match Self::write_atomic(&path, &files).await {
+            Ok(()) => {
+                let _ = tokio::fs::remove_dir_all(&dir).await;
+                info!(event = "RECORDING_FILE_WRITTEN", sip.call_id = %call_id, file.path = %path.display(), "✅ Kayıt yerel diske yazıldı.");
+                Ok(())
+            }
+            Err(e) => {
+                error!(event = "RECORDING_FILE_ERROR", sip.call_id = %call_id, file.path = %path.display(), error = %e, "❌ Kayıt dosyası yazılamadı.");
+                Err(e)
+            }
+        }
+    }
+
+    async fn abort_multipart(&self, _key: &str, upload_id: &str, _call_id: &str) {
+        let _ = tokio::fs::remove_dir_all(self.upload_dir(upload_id)).await;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredObject {
+    pub content_type: String,
+    pub body: Vec<u8>,
+}
+
+/// Süreç belleğinde tutulan depo; testler ve MinIO'suz geliştirme/CI ortamı içindir.
+/// Kayıtlar servis kapanınca kaybolur.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    objects: Mutex<HashMap<String, StoredObject>>,
+    uploads: Mutex<HashMap<String, MemoryUpload>>,
+}
+
+/// Açık multipart yükleme: içerik türü ve numaralı parçalar.
+type MemoryUpload = (String, BTreeMap<i32, Bytes>);
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn object(&self, key: &str) -> Option<StoredObject> {
+        self.objects.lock().unwrap().get(key).cloned()
+    }
+
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.objects.lock().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    /// Açık kalmış multipart yükleme sayısı.
+    pub fn open_uploads(&self) -> usize {
+        self.uploads.lock().unwrap().len()
+    }
+
+    /// Açık multipart yüklemelerde `part_number` numaralı parçalar.
+    pub fn open_parts(&self, part_number: i32) -> Vec<Bytes> {
+        let uploads = self.uploads.lock().unwrap();
+        uploads
+            .values()
+            .filter_map(|(_, parts)| parts.get(&part_number).cloned())
+            .collect()
+    }
+}
+
+#[async_trait]
+impl RecordingStore for MemoryStore {
+    fn uri(&self, key: &str) -> String {
+        format!("memory://{}", key)
+    }
+
+    async fn put(
+        &self,
+        key: &str,
+        content_type: &str,
+        body: Vec<u8>,
+        _call_id: &str,
+    ) -> Result<()> {
+        self.objects.lock().unwrap().insert(
+            key.to_string(),
+            StoredObject {
+                content_type: content_type.to_string(),
+                body,
+            },
+        );
+        Ok(())
+    }
+
+    async fn create_multipart(&self, _key: &str, content_type: &str) -> Result<String> {
+        let upload_id = uuid::Uuid::new_v4().to_string();
+        self.uploads.lock().unwrap().insert(
+            upload_id.clone(),
+            (content_type.to_string(), BTreeMap::new()),
+        );
+        Ok(upload_id)
+    }
+
+    async fn upload_part(
+        &self,
+        _key: &str,
+        upload_id: &str,
+        part_number: i32,
+        data: Bytes,
+        _call_id: &str,
+    ) -> Result<String> {
+        let mut uploads = self.uploads.lock().unwrap();
+        let (_, parts) = uploads
+            .get_mut(upload_id)
+            .ok_or_else(|| no_such_upload(upload_id))?;
+        parts.insert(part_number, data);
+        Ok(part_number.to_string())
+    }
+
+    async fn complete_multipart(
+        &self,
+        key: &str,
+        upload_id: &str,
+        parts: &[UploadedPart],
+        _call_id: &str,
+    ) -> Result<()> {
+        let (content_type, mut stored) = self
+            .uploads
+            .lock()
+            .unwrap()
+            .remove(upload_id)
+            .ok_or_else(|| no_such_upload(upload_id))?;
+        let mut body = Vec::new();
+        for part in parts {
+            let data = stored
+                .remove(&part.part_number)
+                .ok_or_else(|| anyhow!("Parça {} yüklenmemiş", part.part_number))?;
+            body.extend_from_slice(&data);
+        }
+        self.objects
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), StoredObject { content_type, body });
+        Ok(())
+    }
+
+    async fn abort_multipart(&self, _key: &str, upload_id: &str, _call_id: &str) {
+        self.uploads.lock().unwrap().remove(upload_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_local_store_assembles_parts_in_number_order() {
+        let root = std::env::temp_dir().join(format!("sentiric-store-{}", std::process::id()));
+        let store = LocalStore::new(&root);
+        let upload_id = store
+            .create_multipart("a/kayit.wav", "audio/wav")
+            .await
+            .unwrap();
+        let mut parts = Vec::new();
+        // Başlık parçası önce yer tutucuyla, kapanışta yamalı hâliyle yeniden gelir.
+        for (n, data) in [(1, &b"???"[..]), (2, b"iki"), (3, b"uc"), (1, b"bir")] {
+            let e_tag = store
+                .upload_part("a/kayit.wav", &upload_id, n, Bytes::from_static(data), "t")
+                .await
+                .unwrap();
+            parts.retain(|p: &UploadedPart| p.part_number != n);
+            parts.push(UploadedPart {
+                part_number: n,
+                e_tag,
+            });
+        }
+        parts.sort_by_key(|p| p.part_number);
+        store
+            .complete_multipart("a/kayit.wav", &upload_id, &parts, "t")
+            .await
+            .unwrap();
+
+        assert_eq!(
+            std::fs::read(root.join("a/kayit.wav")).unwrap(),
+            b"birikiuc"
+        );
+        assert!(!root.join(LOCAL_UPLOADS_DIR).join(&upload_id).exists());
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,202 @@
+// Dosya: src/rtp/vad.rs
+use crate::config::AppConfig;
+
+const FRAME_MS: u32 = 20;
+const NOISE_FLOOR_INIT_DB: f32 = -60.0;
+const NOISE_FLOOR_MIN_DB: f32 = -90.0;
+// Gürültü tabanı sessizlikte yavaş yükselir, daha sessiz bir frame görünce hızla düşer.
+const NOISE_FLOOR_RISE: f32 = 0.02;
+// Yüksek ZCR (hışırtı/beyaz gürültü) ancak enerji bu kadar daha yüksekse konuşma sayılır.
+const HIGH_ZCR: f32 = 0.45;
+const HIGH_ZCR_EXTRA_DB: f32 = 6.0;
+
+#[derive(Debug, Clone)]
+pub struct VadConfig {
+    /// Konuşma için gürültü tabanının kaç dB üstünde olunmalı.
+    pub threshold_db: f32,
+    /// Bu seviyenin altı (dBFS) her durumda sessizliktir.
+    pub min_energy_dbfs: f32,
+    pub min_speech_ms: u32,
+    pub hangover_ms: u32,
+}
+
+impl VadConfig {
+    pub fn from_app_config(config: &AppConfig) -> Self {
+        Self {
+            threshold_db: config.vad_threshold_db,
+            min_energy_dbfs: config.vad_min_energy_dbfs,
+            min_speech_ms: config.vad_min_speech_ms,
+            hangover_ms: config.vad_hangover_ms,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VadEvent {
+    SpeechStarted,
+    SpeechEnded { duration_ms: u32 },
+}
+
+/// Enerji + sıfır geçiş oranı (ZCR) tabanlı, adaptif gürültü tabanlı ve hangover'lı VAD.
+/// 8 kHz, 20ms (160 örnek) frame'ler için tasarlanmıştır.
+#[derive(Debug)]
+pub struct VoiceActivityDetector {
+    config: VadConfig,
+    noise_floor_db: f32,
+    in_speech: bool,
+    speech_run: u32,
+    silence_run: u32,
+    speech_frames_total: u32,
+}
+
+impl VoiceActivityDetector {
+    pub fn new(config: VadConfig) -> Self {
+        Self {
+            config,
+            noise_floor_db: NOISE_FLOOR_INIT_DB,
+            in_speech: false,
+            speech_run: 0,
+            silence_run: 0,
+            speech_frames_total: 0,
+        }
+    }
+
+    pub fn is_speaking(&self) -> bool {
+        self.in_speech
+    }
+
+    /// Oturum kapanırken konuşma sürüyorsa bitiş olayını üretir.
+    pub fn finish(&mut self) -> Option<VadEvent> {
+        if !self.in_speech {
+            return None;
+        }
+        self.in_speech = false;
+        let duration_frames = self.speech_frames_total.saturating_sub(self.silence_run);
+        self.speech_frames_total = 0;
+        Some(VadEvent::SpeechEnded {
+            duration_ms: duration_frames * FRAME_MS,
+        })
+    }
+
+    fn frame_features(frame: &[i16]) -> (f32, f32) {
+        if frame.is_empty() {
+            return (NOISE_FLOOR_MIN_DB, 0.0);
+        }
+        let sum_sq: f64 = frame.iter().map(|&s| (s as f64) * (s as f64)).sum();
+        let mean_sq = sum_sq / frame.len() as f64 / (32768.0 * 32768.0);
+        let energy_db = (10.0 * mean_sq.max(1e-12).log10()) as f32;
+
+        let crossings = frame
+            .windows(2)
+            .filter(|w| (w[0] >= 0) != (w[1] >= 0))
+            .count();
+        let zcr = crossings as f32 / frame.len() as f32;
+        (energy_db, zcr)
+    }
+
+    fn is_speech_frame(&mut self, frame: &[i16]) -> bool {
+        let (energy_db, zcr) = Self::frame_features(frame);
+        let threshold =
+            (self.noise_floor_db + self.config.threshold_db).max(self.config.min_energy_dbfs);
+
+        let speech = if zcr > HIGH_ZCR {
+            energy_db > threshold + HIGH_ZCR_EXTRA_DB
+        } else {
+            energy_db > threshold
+        };
+
+        if !speech {
+            if energy_db < self.noise_floor_db {
+                self.noise_floor_db = energy_db.max(NOISE_FLOOR_MIN_DB);
+            } else {
+                self.noise_floor_db += (energy_db - self.noise_floor_db) * NOISE_FLOOR_RISE;
+            }
+        }
+        speech
+    }
+
+    /// Frame'i sınıflandırır. Dönüş: (frame konuşma mı, durum değişikliği olayı).
+    /// Konuşma sırasında hangover süresince gelen sessiz frame'ler de konuşma sayılır.
+    pub fn process(&mut self, frame: &[i16]) -> (bool, Option<VadEvent>) {
+        let speech = self.is_speech_frame(frame);
+        let min_speech_frames = (self.config.min_speech_ms / FRAME_MS).max(1);
+        let hangover_frames = (self.config.hangover_ms / FRAME_MS).max(1);
+
+        if speech {
+            self.speech_run += 1;
+            self.silence_run = 0;
+        } else {
+            self.silence_run += 1;
+            self.speech_run = 0;
+        }
+
+        if self.in_speech {
+            self.speech_frames_total += 1;
+            if self.silence_run >= hangover_frames {
+                self.in_speech = false;
+                // Hangover süresi konuşma süresine dahil edilmez.
+                let duration_frames = self.speech_frames_total.saturating_sub(hangover_frames);
+                self.speech_frames_total = 0;
+                return (
+                    false,
+                    Some(VadEvent::SpeechEnded {
+                        duration_ms: duration_frames * FRAME_MS,
+                    }),
+                );
+            }
+            return (true, None);
+        }
+
+        if self.speech_run >= min_speech_frames {
+            self.in_speech = true;
+            self.speech_frames_total = self.speech_run;
+            return (true, Some(VadEvent::SpeechStarted));
+        }
+        (false, None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> VadConfig {
+        VadConfig {
+            threshold_db: 9.0,
+            min_energy_dbfs: -50.0,
+            min_speech_ms: 60,
+            hangover_ms: 200,
+        }
+    }
+
+    fn voiced_frame(offset: usize) -> Vec<i16> {
+        (offset..offset + 160)
+            .map(|n| {
+                (6000.0 * (2.0 * std::f32::consts::PI * 200.0 * n as f32 / 8000.0).sin()) as i16
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_speech_start_and_end_with_hangover() {
+        let mut vad = VoiceActivityDetector::new(config());
+        let silence = vec![3i16; 160];
+        let mut events = Vec::new();
+
+        for _ in 0..20 {
+            events.extend(vad.process(&silence).1);
+        }
+        for i in 0..25 {
+            events.extend(vad.process(&voiced_frame(i * 160)).1);
+        }
+        assert_eq!(events, vec![VadEvent::SpeechStarted]);
+        assert!(vad.is_speaking());
+
+        // Hangover (10 frame) dolmadan konuşma bitmez.
+        for _ in 0..9 {
+            assert_eq!(vad.process(&silence), (true, None));
+        }
+        let (_, event) = vad.process(&silence);
+        assert_eq!(event, Some(VadEvent::SpeechEnded { duration_ms: 500 }));
+    }
+}
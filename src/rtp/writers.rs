@@ -1,68 +1,663 @@
 // sentiric-media-service/src/rtp/writers.rs
-use crate::metrics::S3_UPLOAD_FAILURES;
-use anyhow::Result;
-use aws_sdk_s3::primitives::ByteStream;
-use aws_sdk_s3::Client as S3Client;
-use metrics::counter;
+use crate::metrics::RECORDING_BUFFER_BYTES;
+use crate::rtp::encryption::RecordingEncryptor;
+use crate::rtp::spool::{now_ms, OpenUpload, RecordingSpool, SpoolEntry, SpoolWriter};
+use crate::rtp::store::{RecordingStore, UploadedPart};
+use anyhow::{anyhow, Result};
+use hound::WavSpec;
+use metrics::gauge;
+use std::collections::VecDeque;
 use std::sync::Arc;
-use tokio::time::{sleep, timeout, Duration};
-use tracing::{debug, error, info, instrument, warn};
-
-#[instrument(skip(client, data), fields(s3.bucket = %bucket, s3.key = %key, file.size_bytes = data.len()))]
-pub async fn upload_to_s3_with_retry(
-    client: Arc<S3Client>,
-    bucket: &str,
-    key: &str,
-    data: Vec<u8>,
-    call_id: &str, // [ARCH-COMPLIANCE] Trace ID context propagation eklendi
-) -> Result<()> {
-    const MAX_RETRIES: u32 = 3;
-    let mut attempt = 0;
-
-    // [ARCH-COMPLIANCE] SUTS v4.2: Kayıt süreci bir arka plan görevi olduğu için DEBUG yapıldı.
-    // Başarı (SUCCESS) INFO kalmaya devam edecek.
-    debug!(event = "S3_UPLOAD_START", sip.call_id = %call_id, "☁️ Kayıt dosyası doğrudan bellekten S3'e yükleniyor...");
-
-    loop {
-        attempt += 1;
-        let body = ByteStream::from(data.clone());
-
-        let s3_future = client
-            .put_object()
-            .bucket(bucket)
-            .key(key)
-            .body(body)
-            .send();
-
-        match timeout(Duration::from_secs(15), s3_future).await {
-            Ok(Ok(_)) => {
-                info!(event = "S3_UPLOAD_SUCCESS", sip.call_id = %call_id, attempt = attempt, "✅ Dosya S3'e başarıyla yüklendi.");
-                return Ok(());
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, warn};
+
+/// Multipart parça boyutu. S3, son parça hariç her parçanın en az 5 MiB olmasını ister.
+pub const RECORDING_PART_SIZE: usize = 5 * 1024 * 1024;
+/// Kayıt için izin verilen çıkış örnekleme hızları (oturum sesi 8 kHz'dir).
+pub const RECORDING_SAMPLE_RATES: [u32; 4] = [8000, 16000, 24000, 48000];
+const WAV_HEADER_LEN: usize = 44;
+/// Uzunluğu henüz bilinmeyen WAV'ın yer tutucu `data` boyu (akış WAV'ı geleneği). Yamalanmadan
+/// kalırsa (çökmeden kurtarılan kayıt) okuyucular dosya sonuna kadar okur.
+pub const WAV_STREAMING_DATA_LEN: u32 = u32::MAX - 36;
+/// Yükleyici kuyruğunda bekleyebilecek en fazla parça. Kuyruk dolarsa yükleyici S3'ü
+/// beklemeyi bırakıp spool'a geçer; o da yetişemezse aynı sayıda parça daha bellekte bekler,
+/// ardından kayıt düşürülür.
+const MAX_QUEUED_PARTS: usize = 2;
+
+/// Kanonik 44 baytlık PCM WAV başlığı.
+pub fn wav_header(spec: &WavSpec, data_len: u32) -> [u8; WAV_HEADER_LEN] {
+    let block_align = spec.channels * (spec.bits_per_sample / 8);
+    let byte_rate = spec.sample_rate * block_align as u32;
+    let mut header = [0u8; WAV_HEADER_LEN];
+    header[0..4].copy_from_slice(b"RIFF");
+    header[4..8].copy_from_slice(&(36 + data_len).to_le_bytes());
+    header[8..12].copy_from_slice(b"WAVE");
+    header[12..16].copy_from_slice(b"fmt ");
+    header[16..20].copy_from_slice(&16u32.to_le_bytes());
+    header[20..22].copy_from_slice(&1u16.to_le_bytes());
+    header[22..24].copy_from_slice(&spec.channels.to_le_bytes());
+    header[24..28].copy_from_slice(&spec.sample_rate.to_le_bytes());
+    header[28..32].copy_from_slice(&byte_rate.to_le_bytes());
+    header[32..34].copy_from_slice(&block_align.to_le_bytes());
+    header[34..36].copy_from_slice(&spec.bits_per_sample.to_le_bytes());
+    header[36..40].copy_from_slice(b"data");
+    header[40..44].copy_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+#[derive(Debug)]
+struct MultipartProgress {
+    /// Multipart hiç açılamadıysa (S3 baştan erişilemez) `None`; parçalar spool'dadır.
+    upload_id: Option<String>,
+    parts: Vec<UploadedPart>,
+    /// S3 hata verdikten sonraki parçalar buraya yazılır.
+    spool: Option<SpoolWriter>,
+    /// Spool dizinindeki açık yükleme kaydı; yükleme kapanınca silinir.
+    open_upload: Option<OpenUpload>,
+}
+
+/// `StreamingUpload::finish` sonucu.
+#[derive(Debug)]
+pub enum UploadOutcome {
+    /// Nesne hedefe yazıldı.
+    Stored,
+    /// S3'e ulaşılamadı; kayıt spool'da, manifestosu `RecordingSpool::commit` ile kalıcılaşır.
+    Spooled(Box<SpoolEntry>),
+}
+
+/// Kaydın yazılacağı yer (`StartRecordingRequest.output_uri`): depo ve içindeki anahtar.
+#[derive(Debug, Clone)]
+pub struct RecordingTarget {
+    store: Arc<dyn RecordingStore>,
+    key: String,
+}
+
+impl RecordingTarget {
+    pub fn new(store: Arc<dyn RecordingStore>, key: impl Into<String>) -> Self {
+        Self {
+            store,
+            key: key.into(),
+        }
+    }
+
+    pub fn store(&self) -> &Arc<dyn RecordingStore> {
+        &self.store
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn uri(&self) -> String {
+        self.store.uri(&self.key)
+    }
+
+    /// Kaydın yanındaki JSON metadata nesnesi (`<kayıt>.json`).
+    pub fn sidecar(&self) -> Self {
+        Self::new(self.store.clone(), format!("{}.json", self.key))
+    }
+
+    /// Küçük bir nesneyi tek seferde yazar (metadata sidecar'ı gibi).
+    pub async fn put(&self, content_type: &str, body: Vec<u8>, call_id: &str) -> Result<()> {
+        self.store.put(&self.key, content_type, body, call_id).await
+    }
+}
+
+/// Kaydı çağrı sürerken depoya sabit boyutlu parçalar hâlinde akıtır. Dosya başlığı (WAV,
+/// FLAC) uzunluk bilgisi içerdiği için başlığı taşıyan ilk parça dolduğunda yer tutucu
+/// başlıkla hemen 1 numara olarak yüklenir (servis çökerse kayıt kurtarılabilir), tamamlanışta
+/// yamalanmış başlıkla aynı numarayla yeniden yüklenir (depo aynı numaralı parçayı değiştirir
+/// ve parçaları numara sırasıyla birleştirir). İlk parçayı dolduramayan kısa kayıtlar tek
+/// seferde yazılır.
+///
+/// Şifreleyici verilirse her parça ayrı bir AES-GCM segmenti olarak şifrelenir (ilk parça
+/// segment 0); zarf başlığı ilk parçanın önüne eklenir. Parçalar tam `RECORDING_PART_SIZE`
+/// boyundadır, bu yüzden son segment her zaman daha kısadır ve çözücü onu tanır. Segment 0
+/// aynı nonce ile iki kez mühürlenemeyeceği için şifreli kayıtta ilk parça tamamlanışa kadar
+/// bellekte bekler.
+#[derive(Debug)]
+pub struct StreamingUpload {
+    target: RecordingTarget,
+    content_type: &'static str,
+    call_id: String,
+    encryptor: Option<RecordingEncryptor>,
+    spool: Option<Arc<RecordingSpool>>,
+    head: Vec<u8>,
+    current: Vec<u8>,
+    len: u64,
+    parts_sent: u32,
+    /// İlk parça yer tutucu başlıkla yüklemeye verildi.
+    head_sent: bool,
+    parts_tx: Option<mpsc::Sender<(i32, Vec<u8>)>>,
+    /// Kuyruk doluyken gelen parçalar; sonraki yazmada yeniden denenir.
+    backlog: VecDeque<(i32, Vec<u8>)>,
+    /// Kuyruk dolunca yükleyiciyi S3'ü beklemeden spool'a geçirir.
+    spill: Arc<Notify>,
+    overflowed: bool,
+    uploader: Option<JoinHandle<Result<MultipartProgress>>>,
+}
+
+impl StreamingUpload {
+    pub fn new(
+        target: RecordingTarget,
+        content_type: &'static str,
+        call_id: String,
+        encryptor: Option<RecordingEncryptor>,
+        spool: Option<Arc<RecordingSpool>>,
+    ) -> Self {
+        // Spool yalnızca uzak depolar içindir; yerel depo zaten diske yazar.
+        let spool = spool.filter(|_| target.store.bucket().is_some());
+        Self {
+            target,
+            content_type,
+            call_id,
+            encryptor,
+            spool,
+            head: Vec::with_capacity(RECORDING_PART_SIZE),
+            current: Vec::new(),
+            len: 0,
+            parts_sent: 0,
+            head_sent: false,
+            parts_tx: None,
+            backlog: VecDeque::new(),
+            spill: Arc::new(Notify::new()),
+            overflowed: false,
+            uploader: None,
+        }
+    }
+
+    pub fn uri(&self) -> String {
+        self.target.uri()
+    }
+
+    /// Başlık dahil yazılmış toplam (şifresiz) bayt.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Kodlanmış baytları ekler; dolan parça arka plandaki yükleyiciye devredilir.
+    /// RTP döngüsünü bloklamaz.
+    pub fn write(&mut self, bytes: &[u8]) {
+        self.len += bytes.len() as u64;
+        gauge!(RECORDING_BUFFER_BYTES).increment(bytes.len() as f64);
+
+        let mut rest = bytes;
+        if self.head.len() < RECORDING_PART_SIZE {
+            let take = rest.len().min(RECORDING_PART_SIZE - self.head.len());
+            self.head.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+            if self.head.len() == RECORDING_PART_SIZE && self.encryptor.is_none() {
+                self.head_sent = true;
+                self.enqueue(1, self.head.clone());
             }
-            Ok(Err(e)) => {
-                counter!(S3_UPLOAD_FAILURES).increment(1);
+        }
+        self.current.extend_from_slice(rest);
+
+        while self.current.len() >= RECORDING_PART_SIZE {
+            let rest = self.current.split_off(RECORDING_PART_SIZE);
+            let part = std::mem::replace(&mut self.current, rest);
+            self.send_part(part);
+        }
+    }
+
+    fn send_part(&mut self, part: Vec<u8>) {
+        gauge!(RECORDING_BUFFER_BYTES).decrement(part.len() as f64);
+        self.parts_sent += 1;
+        let part = match &self.encryptor {
+            Some(encryptor) => encryptor.seal(self.parts_sent, false, &part),
+            None => part,
+        };
+        self.enqueue(self.parts_sent as i32 + 1, part);
+    }
 
-                if attempt >= MAX_RETRIES {
-                    error!(event = "S3_UPLOAD_ERROR", sip.call_id = %call_id, error = ?e, "❌ S3 Upload {} deneme sonrası başarısız oldu.", MAX_RETRIES);
-                    return Err(anyhow::anyhow!("S3 Upload Bounded Retry Failed: {:?}", e));
+    /// Parçayı arka plandaki yükleyiciye devreder. Kuyruk doluysa yükleyiciyi spool'a geçirir
+    /// ve parçayı bir sonraki yazmaya kadar bekletir; bekleyenler de sınırı aşarsa kayıt
+    /// düşürülür (`finish` hata döner).
+    fn enqueue(&mut self, part_number: i32, part: Vec<u8>) {
+        if self.overflowed {
+            return;
+        }
+        if self.parts_tx.is_none() {
+            let (tx, rx) = mpsc::channel(MAX_QUEUED_PARTS);
+            self.uploader = Some(tokio::spawn(run_part_uploader(
+                self.target.clone(),
+                self.content_type,
+                self.call_id.clone(),
+                self.spool.clone(),
+                self.spill.clone(),
+                rx,
+            )));
+            self.parts_tx = Some(tx);
+        }
+        self.backlog.push_back((part_number, part));
+        let Some(tx) = &self.parts_tx else {
+            return;
+        };
+        while let Some(next) = self.backlog.pop_front() {
+            match tx.try_send(next) {
+                Ok(()) => {}
+                Err(TrySendError::Full(next)) => {
+                    self.backlog.push_front(next);
+                    self.spill.notify_one();
+                    break;
                 }
+                // Yükleyici hata verip durdu; hata `finish`te döner.
+                Err(TrySendError::Closed(_)) => {
+                    self.backlog.clear();
+                    break;
+                }
+            }
+        }
+        if self.backlog.len() > MAX_QUEUED_PARTS {
+            error!(event = "RECORDING_UPLOAD_BACKLOG_OVERFLOW", sip.call_id = %self.call_id, "🔥 Kayıt parçaları ne depoya ne spool'a yetişiyor, kayıt düşürüldü!");
+            self.backlog.clear();
+            self.parts_tx = None;
+            self.overflowed = true;
+        }
+    }
 
-                let backoff = Duration::from_millis(2u64.pow(attempt) * 500);
-                warn!(event = "S3_UPLOAD_RETRY", sip.call_id = %call_id, attempt = attempt, backoff_ms = backoff.as_millis(), "⚠️ S3 Upload başarısız, tekrar deneniyor...");
-                sleep(backoff).await;
+    /// Yamalanmış ilk parçayı ve (varsa) son parçayı hedefe yazılacak hâle getirir.
+    fn seal_tail(&mut self, head: Vec<u8>) -> (Vec<u8>, Option<Vec<u8>>) {
+        let current = std::mem::take(&mut self.current);
+        let Some(encryptor) = &self.encryptor else {
+            return (head, Some(current).filter(|c| !c.is_empty()));
+        };
+        let mut sealed = encryptor.header().to_vec();
+        if head.len() < RECORDING_PART_SIZE {
+            // Kısa kayıt: tek ve son segment ilk parçadır.
+            sealed.extend(encryptor.seal(0, true, &head));
+            return (sealed, None);
+        }
+        sealed.extend(encryptor.seal(0, false, &head));
+        // Son segment boş olsa bile yazılır; kesilme tespiti ona dayanır.
+        let last = encryptor.seal(self.parts_sent + 1, true, &current);
+        (sealed, Some(last))
+    }
+
+    fn release_buffers(&mut self) {
+        gauge!(RECORDING_BUFFER_BYTES).decrement((self.head.len() + self.current.len()) as f64);
+    }
+
+    /// `header` verilirse akışın başındaki aynı uzunluktaki bayt aralığının üzerine yazılır,
+    /// ardından yükleme tamamlanır. S3'e ulaşılamazsa ve spool varsa yüklenemeyen kısım spool'a
+    /// yazılır (`Spooled`); yoksa yarım kalan multipart yükleme iptal edilir (depoda sahipsiz
+    /// parça kalmaz).
+    pub async fn finish(mut self, header: Option<&[u8]>) -> Result<UploadOutcome> {
+        if self.overflowed {
+            self.abort().await;
+            return Err(anyhow!("Kayıt yükleme kuyruğu taştı, parçalar kayboldu"));
+        }
+        self.release_buffers();
+        if let Some(header) = header {
+            if header.len() > self.head.len() {
+                return Err(anyhow!("Yamalanacak başlık yazılan veriden uzun"));
             }
-            Err(_) => {
-                counter!(S3_UPLOAD_FAILURES).increment(1);
+            self.head[..header.len()].copy_from_slice(header);
+        }
+        let head = std::mem::take(&mut self.head);
+        let (mut head, last) = self.seal_tail(head);
+
+        let Some(uploader) = self.uploader.take() else {
+            head.extend(last.unwrap_or_default());
+            return match self
+                .target
+                .put(self.content_type, head.clone(), &self.call_id)
+                .await
+            {
+                Ok(()) => Ok(UploadOutcome::Stored),
+                Err(e) if self.spool.is_some() => {
+                    warn!(event = "RECORDING_SPOOLING", sip.call_id = %self.call_id, error = %e, "⚠️ S3'e ulaşılamıyor, kayıt yerel spool'a yazılıyor.");
+                    self.spool_rest(None, None, &[], Some(&head)).await
+                }
+                Err(e) => Err(e),
+            };
+        };
+
+        // Bekleyen parçalar sırayla gider; son (kısa olabilen) parça en yüksek numarayı alır,
+        // bu S3 kuralına uyar.
+        if let Some(last) = last {
+            self.backlog.push_back((self.parts_sent as i32 + 2, last));
+        }
+        if let Some(tx) = self.parts_tx.take() {
+            for part in std::mem::take(&mut self.backlog) {
+                if tx.send(part).await.is_err() {
+                    break;
+                }
+            }
+        }
+
+        // Yükleyici hata verdiyse yüklemeyi zaten iptal etmiştir.
+        let mut progress = uploader
+            .await
+            .map_err(|e| anyhow!("Parça yükleyici görevi çöktü: {}", e))??;
+        let open_upload = progress.open_upload.take();
+        // Erken yüklenen ilk parça yalnızca başlığı yamalandıysa yeniden gönderilir.
+        let head = (!self.head_sent || header.is_some()).then_some(head);
+
+        let outcome = match (progress.upload_id, progress.spool) {
+            (Some(upload_id), None) => {
+                match self
+                    .complete(&upload_id, &progress.parts, head.clone())
+                    .await
+                {
+                    Ok(()) => Ok(UploadOutcome::Stored),
+                    Err(e) if self.spool.is_some() => {
+                        warn!(event = "RECORDING_SPOOLING", sip.call_id = %self.call_id, error = %e, "⚠️ Multipart tamamlanamadı, ilk parça yerel spool'a yazılıyor.");
+                        self.spool_rest(None, Some(upload_id), &progress.parts, head.as_deref())
+                            .await
+                    }
+                    Err(e) => {
+                        let (store, key) = (&self.target.store, &self.target.key);
+                        store.abort_multipart(key, &upload_id, &self.call_id).await;
+                        Err(e)
+                    }
+                }
+            }
+            // Yükleyici çağrı sırasında spool'a geçti; başlıklı parça da oraya yazılır.
+            (upload_id, writer) => {
+                self.spool_rest(writer, upload_id, &progress.parts, head.as_deref())
+                    .await
+            }
+        };
+        // Yükleme artık ya kapandı ya da spool manifestosuna devredildi. Spool'a da
+        // yazılamadıysa kayıt açılış taramasına kalır.
+        if let (Some(spool), Some(open_upload), Ok(_)) = (&self.spool, &open_upload, &outcome) {
+            spool.untrack_upload(&open_upload.id).await;
+        }
+        outcome
+    }
+
+    /// Başlıklı ilk parçayı (verilirse) 1 numara olarak yükler ve multipart yüklemeyi
+    /// kapatır. Hata durumunda yükleme açık bırakılır; iptal veya spool kararı çağırana aittir.
+    async fn complete(
+        &self,
+        upload_id: &str,
+        uploaded: &[UploadedPart],
+        head: Option<Vec<u8>>,
+    ) -> Result<()> {
+        let (store, key) = (&self.target.store, &self.target.key);
+        let mut parts = uploaded.to_vec();
+        if let Some(head) = head {
+            let e_tag = store
+                .upload_part(key, upload_id, 1, head.into(), &self.call_id)
+                .await?;
+            // Yer tutucu başlıklı parça aynı numarayla değiştirildi.
+            parts.retain(|p| p.part_number != 1);
+            parts.push(UploadedPart {
+                part_number: 1,
+                e_tag,
+            });
+        }
+        parts.sort_by_key(|p| p.part_number);
+        store
+            .complete_multipart(key, upload_id, &parts, &self.call_id)
+            .await
+    }
+
+    /// S3'e ulaşamayan kısmı (ve verilirse başlıklı ilk parçayı) spool'a yazar. Zaten
+    /// yüklenmiş parçalar açık multipart yüklemede kalır; yeniden deneme görevi oradan devam
+    /// eder.
+    async fn spool_rest(
+        &self,
+        writer: Option<SpoolWriter>,
+        upload_id: Option<String>,
+        uploaded: &[UploadedPart],
+        head: Option<&[u8]>,
+    ) -> Result<UploadOutcome> {
+        let (Some(spool), Some(bucket)) = (&self.spool, self.target.store.bucket()) else {
+            return Err(anyhow!("Kayıt spool'u yapılandırılmamış"));
+        };
+        let mut writer = match writer {
+            Some(writer) => writer,
+            None => spool.writer().await?,
+        };
+        if let Some(head) = head {
+            if let Err(e) = writer.append(1, head).await {
+                writer.discard().await;
+                return Err(e);
+            }
+        }
+        let id = writer.id().to_string();
+        let pending = writer.finish().await?;
+        Ok(UploadOutcome::Spooled(Box::new(SpoolEntry {
+            id,
+            call_id: self.call_id.clone(),
+            bucket: bucket.to_string(),
+            key: self.target.key.clone(),
+            content_type: self.content_type.to_string(),
+            upload_id,
+            uploaded: uploaded.to_vec(),
+            pending,
+            created_at_ms: now_ms(),
+            attempts: 0,
+            next_attempt_at_ms: now_ms(),
+            landed: false,
+            event: None,
+        })))
+    }
+
+    /// Yüklemeyi tamamlamadan bırakır: başlamış multipart yükleme iptal edilir, spool verisi
+    /// silinir.
+    pub async fn abort(mut self) {
+        self.release_buffers();
+        self.parts_tx = None;
+        self.backlog.clear();
+        let Some(uploader) = self.uploader.take() else {
+            return;
+        };
+        // Yükleyici hata verdiyse yüklemeyi zaten iptal etmiştir.
+        if let Ok(Ok(progress)) = uploader.await {
+            if let Some(upload_id) = &progress.upload_id {
+                self.target
+                    .store
+                    .abort_multipart(&self.target.key, upload_id, &self.call_id)
+                    .await;
+            }
+            if let Some(writer) = progress.spool {
+                writer.discard().await;
+            }
+            if let (Some(spool), Some(open_upload)) = (&self.spool, &progress.open_upload) {
+                spool.untrack_upload(&open_upload.id).await;
+            }
+        }
+    }
+}
+
+/// Gelen numaralı parçaları sırayla yükler. Kanal kapanınca (kayıt bitince) tamamlanan
+/// parçaları döndürür. Bir parça kalıcı olarak başarısız olursa veya kuyruk dolarsa (`spill`)
+/// spool varsa o ve sonraki parçalar spool'a yazılır (yükleme açık kalır), yoksa yükleme
+/// iptal edilir. Spool varsa açık yükleme ve yüklenen parçalar spool dizinine işlenir;
+/// servis çökerse açılış taraması yüklemeyi tamamlar veya iptal eder.
+async fn run_part_uploader(
+    target: RecordingTarget,
+    content_type: &str,
+    call_id: String,
+    spool: Option<Arc<RecordingSpool>>,
+    spill: Arc<Notify>,
+    mut parts_rx: mpsc::Receiver<(i32, Vec<u8>)>,
+) -> Result<MultipartProgress> {
+    let (store, key) = (&target.store, &target.key);
+    let mut progress = MultipartProgress {
+        upload_id: None,
+        parts: Vec::new(),
+        spool: None,
+        open_upload: None,
+    };
+    match store.create_multipart(key, content_type).await {
+        Ok(upload_id) => {
+            debug!(event = "S3_MULTIPART_START", sip.call_id = %call_id, s3.key = %key, "☁️ Kayıt multipart olarak depoya akıtılıyor...");
+            if let (Some(spool), Some(bucket)) = (&spool, store.bucket()) {
+                let open_upload = spool.open_upload(&call_id, bucket, key, &upload_id);
+                spool.track_upload(&open_upload).await;
+                progress.open_upload = Some(open_upload);
+            }
+            progress.upload_id = Some(upload_id);
+        }
+        Err(e) => match &spool {
+            Some(spool) => {
+                warn!(event = "RECORDING_SPOOLING", sip.call_id = %call_id, error = %e, "⚠️ S3'e ulaşılamıyor, kayıt yerel spool'a yazılıyor.");
+                progress.spool = Some(spool.writer().await?);
+            }
+            None => return Err(e),
+        },
+    }
 
-                if attempt >= MAX_RETRIES {
-                    error!(event = "S3_UPLOAD_TIMEOUT_FATAL", sip.call_id = %call_id, "❌ S3 Upload {} deneme boyunca Timeout yedi.", MAX_RETRIES);
-                    return Err(anyhow::anyhow!("S3 Upload Timeout Exceeded after retries"));
+    while let Some((part_number, part)) = parts_rx.recv().await {
+        let part = bytes::Bytes::from(part);
+        if let (None, Some(upload_id)) = (&progress.spool, &progress.upload_id) {
+            let upload = store.upload_part(key, upload_id, part_number, part.clone(), &call_id);
+            let result = match &spool {
+                // Kuyruk doldu: yavaş S3 beklenmez, bu ve sonraki parçalar spool'a yazılır.
+                Some(_) => tokio::select! {
+                    result = upload => result,
+                    _ = spill.notified() => Err(anyhow!("Yükleme kuyruğu doldu, S3 yetişemiyor")),
+                },
+                None => upload.await,
+            };
+            match (result, &spool) {
+                (Ok(e_tag), _) => {
+                    progress.parts.push(UploadedPart { part_number, e_tag });
+                    if let (Some(spool), Some(open_upload)) = (&spool, &mut progress.open_upload) {
+                        open_upload.uploaded = progress.parts.clone();
+                        spool.track_upload(open_upload).await;
+                    }
+                    continue;
                 }
+                (Err(e), Some(spool)) => {
+                    warn!(event = "RECORDING_SPOOLING", sip.call_id = %call_id, part = part_number, error = %e, "⚠️ S3'e ulaşılamıyor, kalan parçalar yerel spool'a yazılıyor.");
+                    progress.spool = Some(spool.writer().await?);
+                }
+                (Err(e), None) => {
+                    store.abort_multipart(key, upload_id, &call_id).await;
+                    return Err(e);
+                }
+            }
+        }
+        // Disk de yazılamıyorsa hata döner; yarım spool dosyası açılışta temizlenir.
+        if let Some(writer) = &mut progress.spool {
+            writer.append(part_number, &part).await?;
+        }
+    }
+    Ok(progress)
+}
 
-                let backoff = Duration::from_millis(2u64.pow(attempt) * 500);
-                warn!(event = "S3_UPLOAD_TIMEOUT", sip.call_id = %call_id, attempt = attempt, "⚠️ S3 Upload isteği 15 saniyede cevap vermedi (Timeout). Tekrar deneniyor...");
-                sleep(backoff).await;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtp::store::MemoryStore;
+    use hound::{SampleFormat, WavReader};
+
+    #[test]
+    fn test_wav_header_matches_hound() {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let samples: Vec<i16> = (0..320).map(|i| i as i16 - 160).collect();
+        let mut file = wav_header(&spec, samples.len() as u32 * 2).to_vec();
+        for s in &samples {
+            file.extend_from_slice(&s.to_le_bytes());
+        }
+
+        let mut reader = WavReader::new(std::io::Cursor::new(file)).unwrap();
+        assert_eq!(reader.spec(), spec);
+        assert_eq!(reader.duration(), 160);
+        let decoded: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap()).collect();
+        assert_eq!(decoded, samples);
+    }
+
+    #[tokio::test]
+    async fn test_streams_parts_and_patches_header() {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let store = Arc::new(MemoryStore::new());
+        let mut upload = StreamingUpload::new(
+            RecordingTarget::new(store.clone(), "kayit.wav"),
+            "audio/wav",
+            "test".into(),
+            None,
+            None,
+        );
+        upload.write(&wav_header(&spec, WAV_STREAMING_DATA_LEN));
+        // İki tam parçayı aşan kayıt: ilk parça yer tutucu başlıkla hemen yüklenir, sonda
+        // yamalı hâliyle değiştirilir.
+        let frame: Vec<u8> = (0..160i16).flat_map(|s| s.to_le_bytes()).collect();
+        let frames = RECORDING_PART_SIZE * 5 / 2 / frame.len();
+        for _ in 0..frames {
+            upload.write(&frame);
+        }
+        for _ in 0..100 {
+            if !store.open_parts(1).is_empty() {
+                break;
             }
+            tokio::task::yield_now().await;
+        }
+        let early = store.open_parts(1);
+        assert_eq!(early.len(), 1);
+        assert_eq!(
+            early[0][..WAV_HEADER_LEN],
+            wav_header(&spec, WAV_STREAMING_DATA_LEN)
+        );
+        let data_len = (upload.len() - WAV_HEADER_LEN as u64) as u32;
+        upload
+            .finish(Some(&wav_header(&spec, data_len)))
+            .await
+            .unwrap();
+
+        let object = store.object("kayit.wav").unwrap();
+        assert_eq!(object.content_type, "audio/wav");
+        assert_eq!(store.open_uploads(), 0);
+        let mut reader = WavReader::new(std::io::Cursor::new(object.body)).unwrap();
+        assert_eq!(reader.duration() as usize, frames * 160);
+        let decoded: Vec<i16> = reader.samples::<i16>().map(|s| s.unwrap()).collect();
+        assert!(decoded.chunks(160).all(|c| c.iter().copied().eq(0..160i16)));
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_stream_opens_to_original_bytes() {
+        use crate::rtp::encryption::{open_recording, Keyring};
+        use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+        let keyring = Keyring::parse(&format!(
+            r#"{{"tenants": {{"acme": {{"active_key_id": "k1", "keys": {{"k1": "{}"}}}}}}}}"#,
+            BASE64.encode([3u8; 32])
+        ))
+        .unwrap();
+        // Segment 0 ilk parça, 1..n ara parçalar; tam katta son segment boştur.
+        for total in [2 * RECORDING_PART_SIZE + 7, 3 * RECORDING_PART_SIZE] {
+            let store = Arc::new(MemoryStore::new());
+            let mut upload = StreamingUpload::new(
+                RecordingTarget::new(store.clone(), "kayit.wav.enc"),
+                "application/octet-stream",
+                "test".into(),
+                Some(keyring.encryptor("acme").unwrap()),
+                None,
+            );
+            let mut original: Vec<u8> = (0..total).map(|i| (i % 251) as u8).collect();
+            for chunk in original.chunks(1000) {
+                upload.write(chunk);
+            }
+            let header = [0xA5u8; WAV_HEADER_LEN];
+            upload.finish(Some(&header)).await.unwrap();
+            original[..WAV_HEADER_LEN].copy_from_slice(&header);
+
+            let object = store.object("kayit.wav.enc").unwrap();
+            assert_eq!(store.open_uploads(), 0);
+            let opened = open_recording(&keyring, "acme", &object.body).unwrap();
+            assert_eq!(opened.len(), total);
+            assert!(opened == original, "{} baytlık kayıt bozuldu", total);
         }
     }
 }
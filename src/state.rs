@@ -1,7 +1,10 @@
 // sentiric-media-service/src/state.rs (Üst kısımdaki değişen yer)
-use crate::audio::AudioCache;
+use crate::audio::{AudioCache, PublicOnlyResolver};
 use crate::config::AppConfig;
+use crate::rtp::encryption::Keyring;
 use crate::rtp::session::RtpSession;
+use crate::rtp::spool::RecordingSpool;
+use crate::rtp::store::RecordingStore;
 use aws_sdk_s3::Client as S3Client;
 use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
@@ -17,22 +20,40 @@ type QuarantinedPorts = Arc<Mutex<Vec<(u16, Instant)>>>;
 pub struct AppState {
     pub port_manager: PortManager,
     pub audio_cache: AudioCache,
+    /// Uzak (`http(s)://`) anonsların indirilmesi için paylaşılan istemci. İç ağ adreslerine
+    /// bağlanmaz ve yönlendirme izlemez (izin listesi yalnızca istenen sunucuyu denetler).
+    pub http_client: reqwest::Client,
     pub s3_client: Option<Arc<S3Client>>,
+    /// `output_uri` verilmeyen kayıtların deposu (`RECORDING_STORE`).
+    pub recording_store: Arc<dyn RecordingStore>,
     // [HATA BURADAYDI, LapinChannel yerine RabbitMqClient kullanıyoruz]
     pub rabbitmq_publisher: Option<Arc<crate::rabbitmq::RabbitMqClient>>,
+    pub recording_keyring: Option<Arc<Keyring>>,
+    pub recording_spool: Option<Arc<RecordingSpool>>,
 }
 
 impl AppState {
     pub fn new(
         port_manager: PortManager,
         s3_client: Option<Arc<S3Client>>,
+        recording_store: Arc<dyn RecordingStore>,
         rabbitmq_publisher: Option<Arc<crate::rabbitmq::RabbitMqClient>>,
+        recording_keyring: Option<Arc<Keyring>>,
+        recording_spool: Option<Arc<RecordingSpool>>,
     ) -> Self {
         Self {
             port_manager,
             audio_cache: Arc::new(Mutex::new(HashMap::new())),
+            http_client: reqwest::Client::builder()
+                .dns_resolver(Arc::new(PublicOnlyResolver))
+                .redirect(reqwest::redirect::Policy::none())
+                .build()
+                .expect("HTTP istemcisi oluşturulamadı"),
             s3_client,
+            recording_store,
             rabbitmq_publisher,
+            recording_keyring,
+            recording_spool,
         }
     }
 }